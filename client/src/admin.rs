@@ -0,0 +1,54 @@
+use reqwest::Method;
+use taka_the_discord_bot_api_models::{
+    cache::{CacheKeyRequest, PurgeNamespaceRequest, PurgeUserRequest},
+    logs::LogQuery,
+    rate_limit::{DeleteRateLimitOverrideRequest, RateLimitOverride},
+};
+
+use crate::{client::ApiClient, error::ClientError};
+
+/// Routes of the admins, the token must belong to one
+impl ApiClient {
+    /// Entry count and size of every cache namespace
+    pub async fn cache_namespaces(&self) -> Result<serde_json::Value, ClientError> {
+        self.success(self.request(Method::GET, "/admin/cache")).await
+    }
+
+    /// Where the key is cached and until when
+    pub async fn cache_key(&self, query: &CacheKeyRequest) -> Result<serde_json::Value, ClientError> {
+        self.success(self.request(Method::GET, "/admin/cache/key").query(query)).await
+    }
+
+    pub async fn purge_cache_key(&self, body: &CacheKeyRequest) -> Result<(), ClientError> {
+        self.success(self.request(Method::POST, "/admin/cache/purge_key").json(body)).await
+    }
+
+    /// Number of purged entries of the user
+    pub async fn purge_cache_user(&self, body: &PurgeUserRequest) -> Result<serde_json::Value, ClientError> {
+        self.success(self.request(Method::POST, "/admin/cache/purge_user").json(body)).await
+    }
+
+    /// Number of purged entries of the namespace
+    pub async fn purge_cache_namespace(&self, body: &PurgeNamespaceRequest) -> Result<serde_json::Value, ClientError> {
+        self.success(self.request(Method::POST, "/admin/cache/purge_namespace").json(body)).await
+    }
+
+    /// Matching log lines, oldest first
+    pub async fn logs(&self, query: &LogQuery) -> Result<String, ClientError> {
+        let response = self.send(self.request(Method::GET, "/admin/logs").query(query)).await?;
+        Ok(response.text().await?)
+    }
+
+    /// Default and per route quotas, and every override
+    pub async fn rate_limits(&self) -> Result<serde_json::Value, ClientError> {
+        self.success(self.request(Method::GET, "/admin/rate_limits")).await
+    }
+
+    pub async fn set_rate_limit(&self, body: &RateLimitOverride) -> Result<RateLimitOverride, ClientError> {
+        self.success_once(self.request(Method::POST, "/admin/rate_limits/set").json(body)).await
+    }
+
+    pub async fn delete_rate_limit(&self, body: &DeleteRateLimitOverrideRequest) -> Result<(), ClientError> {
+        self.success(self.request(Method::POST, "/admin/rate_limits/delete").json(body)).await
+    }
+}
@@ -0,0 +1,271 @@
+use std::time::Duration;
+
+use reqwest::{header::RETRY_AFTER, Method, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use taka_the_discord_bot_api_models::error::{ErrorBody, ErrorCode};
+
+use crate::{error::ClientError, response::Success};
+
+const API_KEY_HEADER: &str = "x-api-key";
+
+/// Rate limits, timeouts and an api that is restarting or overloaded are worth asking again
+const RETRIED_STATUSES: [StatusCode; 5] = [
+    StatusCode::TOO_MANY_REQUESTS,
+    StatusCode::REQUEST_TIMEOUT,
+    StatusCode::BAD_GATEWAY,
+    StatusCode::SERVICE_UNAVAILABLE,
+    StatusCode::GATEWAY_TIMEOUT,
+];
+
+/// Which failures a request is sent again after
+#[derive(Debug, Clone, Copy)]
+enum Retry {
+    /// Every retried status, connection error and timeout
+    Always,
+    /// Only when the api can't have acted on the request. A timeout or a 502 may come after it created
+    /// something, sending the request again would create it twice
+    Unsent,
+}
+
+impl Retry {
+    fn status(&self, status: StatusCode) -> bool {
+        match self {
+            Self::Always => RETRIED_STATUSES.contains(&status),
+            Self::Unsent => status == StatusCode::TOO_MANY_REQUESTS,
+        }
+    }
+
+    fn error(&self, error: &reqwest::Error) -> bool {
+        match self {
+            Self::Always => error.is_connect() || error.is_timeout(),
+            Self::Unsent => error.is_connect(),
+        }
+    }
+}
+
+pub struct ApiClientBuilder {
+    base_url: String,
+    token: Option<String>,
+    api_key: Option<String>,
+    max_retries: u32,
+    retry_delay: Duration,
+    timeout: Duration,
+}
+
+impl ApiClientBuilder {
+    /// JWT sent as a bearer token, the admin and watchlist routes require one
+    pub fn token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Key sent as `x-api-key`, rate limit overrides can be set for it
+    pub fn api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Times a failed request is sent again, 3 by default
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Wait before the first retry, doubled on every retry unless the api answers with `Retry-After`
+    pub fn retry_delay(mut self, retry_delay: Duration) -> Self {
+        self.retry_delay = retry_delay;
+        self
+    }
+
+    /// Renders may run until the api's own render timeout, 90 seconds by default, so the default timeout is two minutes
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn build(self) -> Result<ApiClient, ClientError> {
+        // the timeout is set per request, the leaderboard events stream for as long as they are read
+        let http = reqwest::Client::builder()
+            .connect_timeout(self.timeout)
+            .build()?;
+
+        Ok(ApiClient {
+            http,
+            base_url: self.base_url.trim_end_matches('/').to_string(),
+            token: self.token,
+            api_key: self.api_key,
+            max_retries: self.max_retries,
+            retry_delay: self.retry_delay,
+            timeout: self.timeout,
+        })
+    }
+}
+
+/// Client of the api, every route has a method answering with the server's own models
+#[derive(Clone)]
+pub struct ApiClient {
+    http: reqwest::Client,
+    base_url: String,
+    token: Option<String>,
+    api_key: Option<String>,
+    max_retries: u32,
+    retry_delay: Duration,
+    timeout: Duration,
+}
+
+impl ApiClient {
+    /// Client of the api served at `base_url`, like `http://localhost:8080`
+    pub fn builder(base_url: impl Into<String>) -> ApiClientBuilder {
+        ApiClientBuilder {
+            base_url: base_url.into(),
+            token: None,
+            api_key: None,
+            max_retries: 3,
+            retry_delay: Duration::from_millis(500),
+            timeout: Duration::from_secs(120),
+        }
+    }
+
+    pub fn new(base_url: impl Into<String>) -> Result<Self, ClientError> {
+        Self::builder(base_url).build()
+    }
+
+    /// Replaces the JWT sent with the following requests, `None` to stop sending one
+    pub fn set_token(&mut self, token: Option<String>) {
+        self.token = token;
+    }
+
+    pub(crate) fn url(&self, path: &str) -> String {
+        format!("{}{path}", self.base_url)
+    }
+
+    /// Request to `url` with the credentials, without a timeout
+    pub(crate) fn request_url(&self, method: Method, url: String) -> RequestBuilder {
+        let mut request = self.http.request(method, url);
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        if let Some(api_key) = &self.api_key {
+            request = request.header(API_KEY_HEADER, api_key);
+        }
+        request
+    }
+
+    /// Request to `path` of the server, like `/health/ready`
+    pub(crate) fn request_at(&self, method: Method, path: &str) -> RequestBuilder {
+        self.request_url(method, self.url(path)).timeout(self.timeout)
+    }
+
+    /// Request to `path` of the v1 api, like `/teto/osk`
+    pub(crate) fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        self.request_at(method, &format!("/api/v1{path}"))
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        self.retry_delay.saturating_mul(2u32.saturating_pow(attempt))
+    }
+
+    /// Sends `request` until it succeeds or can't be retried anymore
+    pub(crate) async fn send(&self, request: RequestBuilder) -> Result<Response, ClientError> {
+        self.send_with(request, Retry::Always).await
+    }
+
+    async fn send_with(&self, request: RequestBuilder, retry: Retry) -> Result<Response, ClientError> {
+        let mut attempt = 0;
+        loop {
+            let current = request.try_clone().expect("request bodies are built in memory");
+            let delay = match current.send().await {
+                Ok(response) if response.status().is_success() => return Ok(response),
+                Ok(response) if attempt < self.max_retries && retry.status(response.status()) => {
+                    retry_after(&response).unwrap_or(self.backoff(attempt))
+                },
+                Ok(response) => return Err(api_error(response).await),
+                Err(error) if attempt < self.max_retries && retry.error(&error) => self.backoff(attempt),
+                Err(error) => return Err(error.into()),
+            };
+
+            log::debug!("Request failed, retrying in {}ms", delay.as_millis());
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    pub(crate) async fn json<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, ClientError> {
+        self.json_with(request, Retry::Always).await
+    }
+
+    async fn json_with<T: DeserializeOwned>(&self, request: RequestBuilder, retry: Retry) -> Result<T, ClientError> {
+        let body = self.send_with(request, retry).await?.bytes().await?;
+        Ok(serde_json::from_slice(&body)?)
+    }
+
+    /// Data of a `{"status": "success", "data": ...}` response
+    pub(crate) async fn success<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, ClientError> {
+        Ok(self.json::<Success<T>>(request).await?.data)
+    }
+
+    /// [`Self::success`] for requests that must not be repeated once the api may have received them
+    pub(crate) async fn success_once<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, ClientError> {
+        Ok(self.json_with::<Success<T>>(request, Retry::Unsent).await?.data)
+    }
+}
+
+fn retry_after(response: &Response) -> Option<Duration> {
+    let seconds = response.headers().get(RETRY_AFTER)?.to_str().ok()?.parse().ok()?;
+    Some(Duration::from_secs(seconds))
+}
+
+/// The api's error envelope, or one made up from the status when something in front of the api answered
+pub(crate) async fn api_error(response: Response) -> ClientError {
+    let status = response.status().as_u16();
+    let body = match response.bytes().await {
+        Ok(body) => body,
+        Err(error) => return error.into(),
+    };
+
+    ClientError::Api { status, body: error_body(status, &body) }
+}
+
+fn error_body(status: u16, body: &[u8]) -> ErrorBody {
+    serde_json::from_slice::<ErrorBody>(body).unwrap_or_else(|_| ErrorBody {
+        status: "fail".to_string(),
+        code: ErrorCode::from_status(status),
+        message: String::from_utf8_lossy(body).into_owned(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::StatusCode;
+    use taka_the_discord_bot_api_models::error::ErrorCode;
+
+    use super::{error_body, Retry};
+
+    #[test]
+    fn error_envelopes_are_read_as_sent() {
+        let body = error_body(500, br#"{"status": "fail", "code": "render_failed", "message": "Couldn't render the image"}"#);
+
+        assert_eq!(body.code, ErrorCode::RenderFailed);
+        assert_eq!(body.message, "Couldn't render the image");
+    }
+
+    #[test]
+    fn other_bodies_fall_back_to_the_status() {
+        let body = error_body(502, b"<html>Bad Gateway</html>");
+
+        assert_eq!(body.status, "fail");
+        assert_eq!(body.code, ErrorCode::Upstream);
+        assert_eq!(body.message, "<html>Bad Gateway</html>");
+
+        assert_eq!(error_body(418, b"").code, ErrorCode::BadRequest);
+    }
+
+    #[test]
+    fn unsent_requests_are_only_retried_when_rate_limited() {
+        for status in [StatusCode::REQUEST_TIMEOUT, StatusCode::BAD_GATEWAY, StatusCode::SERVICE_UNAVAILABLE, StatusCode::GATEWAY_TIMEOUT] {
+            assert!(Retry::Always.status(status), "{status}");
+            assert!(!Retry::Unsent.status(status), "{status}");
+        }
+        assert!(Retry::Unsent.status(StatusCode::TOO_MANY_REQUESTS));
+    }
+}
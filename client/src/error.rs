@@ -0,0 +1,55 @@
+use std::fmt::Display;
+
+use taka_the_discord_bot_api_models::error::{ErrorBody, ErrorCode};
+
+#[derive(Debug)]
+pub enum ClientError {
+    /// The request couldn't be sent or its response couldn't be read
+    Http(reqwest::Error),
+    /// The api answered with an error status
+    Api { status: u16, body: ErrorBody },
+    /// The response isn't the JSON the route answers with
+    Decode(serde_json::Error),
+}
+
+impl ClientError {
+    /// Code of the error the api answered with
+    pub fn code(&self) -> Option<ErrorCode> {
+        match self {
+            Self::Api { body, .. } => Some(body.code),
+            _ => None,
+        }
+    }
+}
+
+impl Display for ClientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Http(source) => write!(f, "Couldn't reach the api! {source}"),
+            Self::Api { status, body } => write!(f, "The api answered {status} {:?}: {}", body.code, body.message),
+            Self::Decode(source) => write!(f, "Couldn't read the api's response! {source}"),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Http(source) => Some(source),
+            Self::Api { .. } => None,
+            Self::Decode(source) => Some(source),
+        }
+    }
+}
+
+impl From<reqwest::Error> for ClientError {
+    fn from(error: reqwest::Error) -> Self {
+        Self::Http(error)
+    }
+}
+
+impl From<serde_json::Error> for ClientError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Decode(error)
+    }
+}
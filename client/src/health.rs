@@ -0,0 +1,32 @@
+use reqwest::{Method, StatusCode};
+use serde::Deserialize;
+use taka_the_discord_bot_api_models::health::{ComponentStatus, Readiness};
+
+use crate::{client::{api_error, ApiClient}, error::ClientError};
+
+#[derive(Deserialize)]
+struct Liveness {
+    status: ComponentStatus,
+}
+
+impl ApiClient {
+    pub async fn hello(&self) -> Result<String, ClientError> {
+        let response = self.send(self.request_at(Method::GET, "/api/v1")).await?;
+        Ok(response.text().await?)
+    }
+
+    pub async fn live(&self) -> Result<ComponentStatus, ClientError> {
+        Ok(self.json::<Liveness>(self.request_at(Method::GET, "/health/live")).await?.status)
+    }
+
+    /// Health of every dependency. Not retried, a 503 carries the components that are down
+    pub async fn ready(&self) -> Result<Readiness, ClientError> {
+        let response = self.request_at(Method::GET, "/health/ready").send().await?;
+        if !matches!(response.status(), StatusCode::OK | StatusCode::SERVICE_UNAVAILABLE) {
+            return Err(api_error(response).await);
+        }
+
+        let body = response.bytes().await?;
+        Ok(serde_json::from_slice(&body)?)
+    }
+}
@@ -0,0 +1,16 @@
+//! Typed client of the api, answering with the same models the server is built with
+
+mod client;
+mod error;
+mod response;
+mod tetrio;
+mod silly_command;
+mod admin;
+mod watchlist;
+mod health;
+
+pub use client::{ApiClient, ApiClientBuilder};
+pub use error::ClientError;
+pub use response::{Packet, Success};
+pub use tetrio::LeaderboardEventMessage;
+pub use taka_the_discord_bot_api_models as models;
@@ -0,0 +1,18 @@
+use serde::Deserialize;
+
+/// tetr.io's packet, which the tetr.io routes answer with
+#[derive(Debug, Deserialize)]
+pub struct Packet<T> {
+    pub success: bool,
+    pub data: Option<T>,
+    /// tetr.io cache information, when the data comes from tetr.io
+    pub cache: Option<serde_json::Value>,
+    pub error: Option<serde_json::Value>,
+}
+
+/// `{"status": "success", "data": ...}`, which the admin and watchlist routes answer with
+#[derive(Debug, Deserialize)]
+pub struct Success<T> {
+    pub status: String,
+    pub data: T,
+}
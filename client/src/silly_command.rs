@@ -0,0 +1,22 @@
+use reqwest::Method;
+use taka_the_discord_bot_api_models::silly_command::SillyCommandData;
+
+use crate::{client::ApiClient, error::ClientError};
+
+impl ApiClient {
+    pub async fn commands(&self) -> Result<Vec<SillyCommandData>, ClientError> {
+        self.json(self.request(Method::GET, "/get_commands")).await
+    }
+
+    /// Raw bytes of an image the silly commands link to, `path` being relative to `/api/v1/images`
+    pub async fn image(&self, path: &str) -> Result<Vec<u8>, ClientError> {
+        let path = path.trim_start_matches('/')
+            .split('/')
+            .map(|segment| urlencoding::encode(segment).into_owned())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        let response = self.send(self.request(Method::GET, &format!("/images/{path}"))).await?;
+        Ok(response.bytes().await?.to_vec())
+    }
+}
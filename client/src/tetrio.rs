@@ -0,0 +1,169 @@
+use std::time::Duration;
+
+use common::LeagueRecordRequest;
+use futures_util::{stream, Stream, StreamExt};
+use reqwest::Method;
+use taka_the_discord_bot_api_models::{
+    head_to_head::{HeadToHead, HeadToHeadQuery},
+    leaderboard::{LeaderboardEvent, LeaderboardEventsQuery, PlayerRank},
+    league_stats::{MatchStats, MatchStatsQuery},
+    records::{RecordCard, RecordMode, RecordQuery},
+    tetra::{FullLeaderboardQuery, TetraData, TetraQuery, TetraTestParam},
+    tetra_history::{TetraHistory, TetraHistoryQuery},
+};
+
+use crate::{client::{api_error, ApiClient}, error::ClientError, response::Packet};
+
+/// Crawling the whole leaderboard takes minutes
+const FULL_LEADERBOARD_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+#[derive(Debug)]
+pub enum LeaderboardEventMessage {
+    Event(LeaderboardEvent),
+    /// The events were read too slowly and this many were skipped
+    Lagged(u64),
+}
+
+/// One `event:`/`data:` block of the stream, `None` for the keep-alive comments
+fn parse_event(block: &[u8]) -> Option<Result<LeaderboardEventMessage, ClientError>> {
+    let block = String::from_utf8_lossy(block);
+    let mut name = None;
+    let mut data = Vec::new();
+    for line in block.lines() {
+        if let Some(value) = line.strip_prefix("event:") {
+            name = Some(value.trim());
+        } else if let Some(value) = line.strip_prefix("data:") {
+            data.push(value.strip_prefix(' ').unwrap_or(value));
+        }
+    }
+
+    if data.is_empty() {
+        return None;
+    }
+    let data = data.join("\n");
+
+    match name {
+        Some("lagged") => Some(Ok(LeaderboardEventMessage::Lagged(data.trim().parse().unwrap_or_default()))),
+        _ => Some(serde_json::from_str(&data).map(LeaderboardEventMessage::Event).map_err(ClientError::from)),
+    }
+}
+
+impl ApiClient {
+    /// PNG of the user's profile card
+    pub async fn teto(&self, user: &str) -> Result<Packet<Box<[u8]>>, ClientError> {
+        self.json(self.request(Method::GET, &format!("/teto/{}", urlencoding::encode(user)))).await
+    }
+
+    /// Personal best of the user, with a rendered card unless `query.render` is false
+    pub async fn record(&self, user: &str, mode: RecordMode, query: &RecordQuery) -> Result<RecordCard, ClientError> {
+        let path = format!("/records/{}/{}", urlencoding::encode(user), mode.name());
+        self.json(self.request(Method::GET, &path).query(query)).await
+    }
+
+    /// Screenshot of a recent Tetra League game
+    pub async fn tetra(&self, query: &TetraQuery) -> Result<Packet<TetraData>, ClientError> {
+        self.json(self.request(Method::GET, "/tetra").query(query)).await
+    }
+
+    /// Screenshot of a Tetra League record the caller already has
+    pub async fn tetra_replay(&self, record: &LeagueRecordRequest) -> Result<Packet<TetraData>, ClientError> {
+        self.json(self.request(Method::POST, "/tetra/replay").json(record)).await
+    }
+
+    pub async fn tetra_stats(&self, query: &MatchStatsQuery) -> Result<Packet<MatchStats>, ClientError> {
+        self.json(self.request(Method::GET, "/tetra/stats").query(query)).await
+    }
+
+    pub async fn head_to_head(&self, query: &HeadToHeadQuery) -> Result<Packet<HeadToHead>, ClientError> {
+        self.json(self.request(Method::GET, "/tetra/h2h").query(query)).await
+    }
+
+    pub async fn tetra_history(&self, user: &str, query: &TetraHistoryQuery) -> Result<Packet<TetraHistory>, ClientError> {
+        let path = format!("/tetra/history/{}", urlencoding::encode(user));
+        self.json(self.request(Method::GET, &path).query(query)).await
+    }
+
+    pub async fn league_recent_test(&self, query: &TetraTestParam) -> Result<Packet<TetraData>, ClientError> {
+        self.json(self.request(Method::GET, "/league_recent_test").query(query)).await
+    }
+
+    /// Every entry of the Tetra League leaderboard, as tetr.io sends them
+    pub async fn full_leaderboard(&self, query: &FullLeaderboardQuery) -> Result<Packet<Vec<serde_json::Value>>, ClientError> {
+        let request = self.request(Method::GET, "/full_leaderboard")
+            .query(query)
+            .timeout(FULL_LEADERBOARD_TIMEOUT);
+        self.json(request).await
+    }
+
+    pub async fn player_rank(&self, user: &str) -> Result<Packet<PlayerRank>, ClientError> {
+        self.json(self.request(Method::GET, &format!("/leaderboard/rank/{}", urlencoding::encode(user)))).await
+    }
+
+    /// Changes of the leaderboard as the api notices them, for as long as the stream is read
+    pub async fn leaderboard_events(&self, query: &LeaderboardEventsQuery) -> Result<impl Stream<Item = Result<LeaderboardEventMessage, ClientError>> + Send, ClientError> {
+        let request = self.request_url(Method::GET, self.url("/api/v1/leaderboard/events")).query(query);
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            return Err(api_error(response).await);
+        }
+
+        let events = stream::unfold((response.bytes_stream().boxed(), Vec::new()), |(mut bytes, mut buffer)| async move {
+            loop {
+                if let Some(end) = buffer.windows(2).position(|window| window == b"\n\n") {
+                    let block = buffer.drain(..end + 2).collect::<Vec<_>>();
+                    match parse_event(&block) {
+                        Some(message) => return Some((message, (bytes, buffer))),
+                        None => continue,
+                    }
+                }
+
+                match bytes.next().await {
+                    Some(Ok(chunk)) => buffer.extend_from_slice(&chunk),
+                    Some(Err(error)) => return Some((Err(error.into()), (bytes, buffer))),
+                    None => return None,
+                }
+            }
+        });
+
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use taka_the_discord_bot_api_models::leaderboard::LeaderboardEvent;
+
+    use super::{parse_event, LeaderboardEventMessage};
+
+    #[test]
+    fn parses_events() {
+        let block = b"event: rank_up\ndata: {\"type\": \"rank_up\", \"player\": {\"user_id\": \"5e32fc85ab319c2ab1beb07c\", \"username\": \"osk\", \"country\": null}, \"from\": 3, \"to\": 2}\n\n";
+
+        match parse_event(block) {
+            Some(Ok(LeaderboardEventMessage::Event(LeaderboardEvent::RankUp { player, from: 3, to: 2 }))) => assert_eq!(player.username.as_deref(), Some("osk")),
+            other => panic!("expected a rank up, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn joins_data_lines() {
+        let block = b"data: {\"type\": \"dropped\",\ndata: \"player\": {\"user_id\": \"1\", \"username\": null, \"country\": null}, \"rank\": 50}\n\n";
+
+        assert!(matches!(parse_event(block), Some(Ok(LeaderboardEventMessage::Event(LeaderboardEvent::Dropped { rank: 50, .. })))));
+    }
+
+    #[test]
+    fn parses_lagged_notices() {
+        assert!(matches!(parse_event(b"event: lagged\ndata: 12\n\n"), Some(Ok(LeaderboardEventMessage::Lagged(12)))));
+    }
+
+    #[test]
+    fn skips_keep_alives() {
+        assert!(parse_event(b": keep-alive\n\n").is_none());
+    }
+
+    #[test]
+    fn reports_malformed_events() {
+        assert!(matches!(parse_event(b"data: {\"type\": \"unknown\"}\n\n"), Some(Err(_))));
+    }
+}
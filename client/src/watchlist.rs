@@ -0,0 +1,26 @@
+use reqwest::Method;
+use taka_the_discord_bot_api_models::watchlist::{CreateWatchlistRequest, CreatedWatchlist, DeleteWatchlistRequest, WatchedPlayerRequest, WatchlistData};
+
+use crate::{client::ApiClient, error::ClientError};
+
+impl ApiClient {
+    pub async fn watchlist(&self, guild_id: &str) -> Result<WatchlistData, ClientError> {
+        self.success(self.request(Method::GET, &format!("/watchlists/{}", urlencoding::encode(guild_id)))).await
+    }
+
+    pub async fn create_watchlist(&self, body: &CreateWatchlistRequest) -> Result<CreatedWatchlist, ClientError> {
+        self.success_once(self.request(Method::POST, "/watchlists/create_watchlist").json(body)).await
+    }
+
+    pub async fn delete_watchlist(&self, body: &DeleteWatchlistRequest) -> Result<(), ClientError> {
+        self.success(self.request(Method::POST, "/watchlists/delete_watchlist").json(body)).await
+    }
+
+    pub async fn add_watched_player(&self, body: &WatchedPlayerRequest) -> Result<(), ClientError> {
+        self.success(self.request(Method::POST, "/watchlists/add_player").json(body)).await
+    }
+
+    pub async fn remove_watched_player(&self, body: &WatchedPlayerRequest) -> Result<(), ClientError> {
+        self.success(self.request(Method::POST, "/watchlists/remove_player").json(body)).await
+    }
+}
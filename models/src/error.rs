@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+
+/// Machine readable reason of a failed request, sent as `code` next to the human readable message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    BadRequest,
+    InvalidBody,
+    NotLoggedIn,
+    InvalidToken,
+    InvalidCredentials,
+    Forbidden,
+    NotFound,
+    MethodNotAllowed,
+    Conflict,
+    Timeout,
+    PayloadTooLarge,
+    UnsupportedMediaType,
+    RateLimited,
+    Internal,
+    RenderFailed,
+    Upstream,
+    Unavailable,
+}
+
+impl ErrorCode {
+    /// HTTP status the code is answered with
+    pub fn status(&self) -> u16 {
+        match self {
+            Self::BadRequest => 400,
+            Self::InvalidBody => 422,
+            Self::NotLoggedIn | Self::InvalidToken | Self::InvalidCredentials => 401,
+            Self::Forbidden => 403,
+            Self::NotFound => 404,
+            Self::MethodNotAllowed => 405,
+            Self::Conflict => 409,
+            Self::Timeout => 408,
+            Self::PayloadTooLarge => 413,
+            Self::UnsupportedMediaType => 415,
+            Self::RateLimited => 429,
+            Self::Internal | Self::RenderFailed => 500,
+            Self::Upstream => 502,
+            Self::Unavailable => 503,
+        }
+    }
+
+    /// Code of an error response that wasn't built with one, like axum's extractor rejections
+    pub fn from_status(status: u16) -> Self {
+        match status {
+            422 => Self::InvalidBody,
+            401 => Self::NotLoggedIn,
+            403 => Self::Forbidden,
+            404 => Self::NotFound,
+            405 => Self::MethodNotAllowed,
+            409 => Self::Conflict,
+            408 => Self::Timeout,
+            413 => Self::PayloadTooLarge,
+            415 => Self::UnsupportedMediaType,
+            429 => Self::RateLimited,
+            502 => Self::Upstream,
+            503 => Self::Unavailable,
+            500..=599 => Self::Internal,
+            _ => Self::BadRequest,
+        }
+    }
+}
+
+/// Body of a failed request, `Accept: application/problem+json` gets the RFC 7807 form instead
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct ErrorBody {
+    /// Always `fail`
+    pub status: String,
+    pub code: ErrorCode,
+    pub message: String,
+}
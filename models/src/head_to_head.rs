@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "utoipa", into_params(parameter_in = Query))]
+pub struct HeadToHeadQuery {
+    pub user: String,
+    pub opponent: String,
+    /// Set to true to get a rendered card along with the stats
+    pub render: Option<bool>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct HeadToHeadPlayer {
+    pub user_id: Option<String>,
+    pub username: String,
+    pub wins: usize,
+    pub rounds_won: u64,
+    pub average_apm: Option<f64>,
+    pub average_pps: Option<f64>,
+    pub average_vs: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct HeadToHeadMatch {
+    pub replay_id: String,
+    pub played_at: Option<String>,
+    pub user_score: u64,
+    pub opponent_score: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct HeadToHead {
+    pub user: HeadToHeadPlayer,
+    pub opponent: HeadToHeadPlayer,
+    pub matches: Vec<HeadToHeadMatch>,
+    #[cfg_attr(feature = "utoipa", schema(value_type = Option<Vec<u8>>))]
+    pub buffer: Option<Box<[u8]>>,
+}
@@ -0,0 +1,126 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct LeaderboardNeighbour {
+    pub rank: usize,
+    pub user_id: Option<String>,
+    pub username: Option<String>,
+    #[cfg_attr(feature = "utoipa", schema(value_type = Object))]
+    pub entry: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum RankSource {
+    /// Position computed from the cached full leaderboard
+    Snapshot,
+    /// Position reported live by tetr.io, neighbours are not available
+    Live,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct PlayerRank {
+    pub user_id: Option<String>,
+    pub username: Option<String>,
+    pub country: Option<String>,
+    pub global_rank: Option<usize>,
+    pub country_rank: Option<usize>,
+    /// Share of the ladder at or above this player, in percent (lower is better)
+    pub percentile: Option<f64>,
+    pub above: Option<LeaderboardNeighbour>,
+    pub below: Option<LeaderboardNeighbour>,
+    pub source: RankSource,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct LeaderboardEventPlayer {
+    pub user_id: String,
+    pub username: Option<String>,
+    pub country: Option<String>,
+}
+
+/// Change between two consecutive snapshots of the global leaderboard
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LeaderboardEvent {
+    RankUp { player: LeaderboardEventPlayer, from: usize, to: usize },
+    RankDown { player: LeaderboardEventPlayer, from: usize, to: usize },
+    NewEntrant { player: LeaderboardEventPlayer, rank: usize },
+    /// The player left the ladder, `rank` is where they were
+    Dropped { player: LeaderboardEventPlayer, rank: usize },
+    /// The player's letter rank changed, for example from "x" to "x+"
+    RankBoundary { player: LeaderboardEventPlayer, from: String, to: String },
+}
+
+impl LeaderboardEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::RankUp { .. } => "rank_up",
+            Self::RankDown { .. } => "rank_down",
+            Self::NewEntrant { .. } => "new_entrant",
+            Self::Dropped { .. } => "dropped",
+            Self::RankBoundary { .. } => "rank_boundary",
+        }
+    }
+
+    pub fn player(&self) -> &LeaderboardEventPlayer {
+        match self {
+            Self::RankUp { player, .. }
+            | Self::RankDown { player, .. }
+            | Self::NewEntrant { player, .. }
+            | Self::Dropped { player, .. }
+            | Self::RankBoundary { player, .. } => player,
+        }
+    }
+
+    /// Number of places gained or lost, for rank up and rank down events
+    pub fn rank_change(&self) -> Option<usize> {
+        match self {
+            Self::RankUp { from, to, .. } | Self::RankDown { from, to, .. } => Some(from.abs_diff(*to)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "utoipa", into_params(parameter_in = Query))]
+pub struct LeaderboardEventsQuery {
+    pub country: Option<String>,
+    /// Comma separated list of user ids or usernames
+    pub users: Option<String>,
+    /// Ignore rank ups and downs smaller than this many places
+    pub min_change: Option<usize>,
+}
+
+impl LeaderboardEventsQuery {
+    pub fn matches(&self, event: &LeaderboardEvent) -> bool {
+        let player = event.player();
+
+        if let Some(country) = &self.country {
+            if !player.country.as_deref().is_some_and(|player_country| player_country.eq_ignore_ascii_case(country)) {
+                return false;
+            }
+        }
+
+        if let Some(users) = &self.users {
+            let watched = users.split(',')
+                .map(|user| user.trim())
+                .filter(|user| !user.is_empty())
+                .any(|user| user == player.user_id || player.username.as_deref().is_some_and(|username| username.eq_ignore_ascii_case(user)));
+            if !watched {
+                return false;
+            }
+        }
+
+        match (self.min_change, event.rank_change()) {
+            (Some(min_change), Some(change)) => change >= min_change,
+            _ => true,
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use serde::{Deserialize, Serialize};
+
+/// Community standard metrics derived from a player's APM, PPS and VS
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct DerivedStats {
+    pub apm: f64,
+    pub pps: f64,
+    pub vs: f64,
+    /// Attack per piece
+    pub app: f64,
+    /// Downstack per second
+    pub ds_second: f64,
+    /// Downstack per piece
+    pub ds_piece: f64,
+    pub app_ds_piece: f64,
+    pub vs_apm: f64,
+    pub cheese_index: f64,
+    pub garbage_efficiency: f64,
+    /// Weighted sum of the other metrics, used to compare overall strength
+    pub area: f64,
+}
+
+impl DerivedStats {
+    pub fn from_base(apm: f64, pps: f64, vs: f64) -> Self {
+        if pps <= 0.0 || apm <= 0.0 {
+            return Self { apm, pps, vs, ..Default::default() };
+        }
+
+        let app = apm / (pps * 60.0);
+        let ds_second = vs / 100.0 - apm / 60.0;
+        let ds_piece = ds_second / pps;
+        let vs_apm = vs / apm;
+        let cheese_index = ds_piece * 150.0 + (vs_apm - 2.0) * 50.0 + (0.6 - app) * 125.0;
+        let garbage_efficiency = app * ds_second / pps * 2.0;
+        let area = apm
+            + pps * 45.0
+            + vs * 0.444
+            + app * 185.0
+            + ds_second * 175.0
+            + ds_piece * 450.0
+            + garbage_efficiency * 315.0;
+
+        Self {
+            apm,
+            pps,
+            vs,
+            app,
+            ds_second,
+            ds_piece,
+            app_ds_piece: app + ds_piece,
+            vs_apm,
+            cheese_index,
+            garbage_efficiency,
+            area,
+        }
+    }
+
+    /// Reads `stats.apm`, `stats.pps` and `stats.vsscore` of a league record player entry
+    pub fn from_entry(entry: &serde_json::Value) -> Option<Self> {
+        let stat = |key: &str| entry.pointer(&format!("/stats/{key}")).and_then(|value| value.as_f64());
+        Some(Self::from_base(stat("apm")?, stat("pps")?, stat("vsscore")?))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct PlayerMatchStats {
+    pub user_id: Option<String>,
+    pub username: Option<String>,
+    pub wins: u64,
+    pub overall: DerivedStats,
+    pub rounds: Vec<DerivedStats>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct MatchStats {
+    pub replay_id: Option<String>,
+    pub players: Vec<PlayerMatchStats>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "utoipa", into_params(parameter_in = Query))]
+pub struct MatchStatsQuery {
+    pub user_id: String,
+    pub game_num: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DerivedStats;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-3, "expected {expected}, got {actual}");
+    }
+
+    #[test]
+    fn derives_round_numbers() {
+        // 60 apm at 1 pps is one attack per piece, 120 vs leaves 0.2 lines of downstack per second
+        let stats = DerivedStats::from_base(60.0, 1.0, 120.0);
+
+        assert_close(stats.app, 1.0);
+        assert_close(stats.ds_second, 0.2);
+        assert_close(stats.ds_piece, 0.2);
+        assert_close(stats.app_ds_piece, 1.2);
+        assert_close(stats.vs_apm, 2.0);
+        assert_close(stats.cheese_index, -20.0);
+        assert_close(stats.garbage_efficiency, 0.4);
+        assert_close(stats.area, 594.28);
+    }
+
+    #[test]
+    fn derives_a_league_match() {
+        let stats = DerivedStats::from_entry(&serde_json::json!({
+            "stats": { "apm": 82.14, "pps": 2.01, "vsscore": 171.3 }
+        })).expect("the entry has stats");
+
+        assert_close(stats.app, 0.6811);
+        assert_close(stats.ds_second, 0.344);
+        assert_close(stats.ds_piece, 0.1711);
+        assert_close(stats.vs_apm, 2.0855);
+        assert_close(stats.cheese_index, 19.808);
+        assert_close(stats.garbage_efficiency, 0.2331);
+        assert_close(stats.area, 585.3008);
+    }
+
+    #[test]
+    fn idle_players_only_keep_their_base_stats() {
+        let stats = DerivedStats::from_base(0.0, 0.0, 12.0);
+        assert_eq!(stats, DerivedStats { vs: 12.0, ..Default::default() });
+    }
+
+    #[test]
+    fn entries_without_stats_are_skipped() {
+        assert!(DerivedStats::from_entry(&serde_json::json!({ "stats": { "apm": 40.0 } })).is_none());
+    }
+}
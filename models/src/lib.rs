@@ -0,0 +1,16 @@
+//! Bodies, query parameters and responses of the api, shared by the server and its client
+
+pub mod silly_command;
+pub mod user;
+pub mod leaderboard;
+pub mod watchlist;
+pub mod records;
+pub mod tetra_history;
+pub mod head_to_head;
+pub mod league_stats;
+pub mod cache;
+pub mod logs;
+pub mod health;
+pub mod rate_limit;
+pub mod tetra;
+pub mod error;
@@ -0,0 +1,16 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "utoipa", into_params(parameter_in = Query))]
+pub struct LogQuery {
+    /// Only keep the last `tail` matching entries, capped by the server's `log.max_tail`
+    pub tail: Option<usize>,
+    /// Minimum severity, `warn` keeps warnings and errors
+    pub level: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    /// Case-insensitive substring the entry must contain
+    pub contains: Option<String>,
+}
@@ -0,0 +1,80 @@
+use std::{str::FromStr, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+/// `capacity` requests every `period_seconds`, refilled continuously. A capacity of 0 lifts the limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct Quota {
+    pub capacity: u32,
+    pub period_seconds: u64,
+}
+
+impl Quota {
+    pub fn is_unlimited(&self) -> bool {
+        self.capacity == 0
+    }
+
+    pub fn period(&self) -> Duration {
+        Duration::from_secs(self.period_seconds)
+    }
+
+    /// Tokens put back in the bucket every millisecond
+    pub fn refill_per_ms(&self) -> f64 {
+        self.capacity as f64 / (self.period_seconds.max(1) * 1000) as f64
+    }
+}
+
+/// Parses `requests/seconds`, e.g. `10/60`
+impl FromStr for Quota {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (capacity, period) = value.split_once('/').ok_or(format!("expected requests/seconds, got {value}"))?;
+        let capacity = capacity.trim().parse().map_err(|e| format!("invalid request count {capacity}: {e}"))?;
+        let period_seconds = match period.trim().parse() {
+            Ok(0) => return Err("the period can't be 0 seconds".to_string()),
+            Ok(period) => period,
+            Err(e) => return Err(format!("invalid period {period}: {e}")),
+        };
+
+        Ok(Self { capacity, period_seconds })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct RateLimitOverride {
+    /// `api:<key>`, `user:<id>` or `ip:<address>`
+    pub client: String,
+    /// Route template the override applies to, e.g. `/api/v1/teto/:user`, every route when `None`
+    pub route: Option<String>,
+    #[serde(flatten)]
+    pub quota: Quota,
+}
+
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct DeleteRateLimitOverrideRequest {
+    pub client: String,
+    pub route: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Quota;
+
+    #[test]
+    fn parses_requests_per_seconds() {
+        assert_eq!("10/60".parse(), Ok(Quota { capacity: 10, period_seconds: 60 }));
+        assert_eq!(" 5 / 1 ".parse(), Ok(Quota { capacity: 5, period_seconds: 1 }));
+        assert!("0/60".parse::<Quota>().expect("0 lifts the limit").is_unlimited());
+    }
+
+    #[test]
+    fn rejects_malformed_quotas() {
+        for value in ["", "10", "10/", "/60", "ten/60", "10/sixty", "-1/60", "10/0", "10/60/5"] {
+            assert!(value.parse::<Quota>().is_err(), "{value}");
+        }
+    }
+}
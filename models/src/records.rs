@@ -0,0 +1,54 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub enum RecordMode {
+    #[serde(rename = "40l")]
+    Sprint,
+    #[serde(rename = "blitz")]
+    Blitz,
+}
+
+impl RecordMode {
+    /// Name used by tetr.io and the html server for this mode
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Sprint => "40l",
+            Self::Blitz => "blitz",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct RecordStats {
+    pub mode: RecordMode,
+    pub replay_id: Option<String>,
+    /// Final time in milliseconds, for 40L
+    pub time: Option<f64>,
+    /// Final score, for Blitz
+    pub score: Option<i64>,
+    pub pieces_placed: Option<i64>,
+    pub pps: Option<f64>,
+    pub finesse_faults: Option<i64>,
+    /// Share of pieces placed with perfect finesse, in percent
+    pub finesse_percentage: Option<f64>,
+    pub global_rank: Option<i64>,
+    pub country_rank: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct RecordCard {
+    pub stats: RecordStats,
+    #[cfg_attr(feature = "utoipa", schema(value_type = Option<Vec<u8>>))]
+    pub buffer: Option<Box<[u8]>>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "utoipa", into_params(parameter_in = Query))]
+pub struct RecordQuery {
+    /// Set to false to only get the stats without rendering a card
+    pub render: Option<bool>,
+}
@@ -1,10 +1,10 @@
 #![allow(unused)]
 
-use serde::Deserialize;
-use sqlx::FromRow;
+use serde::{Deserialize, Serialize};
 
 
-#[derive(FromRow, Debug, Default)]
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
 pub struct RawSillyCommandData {
     pub id_silly_command: Option<i32>,
     pub name: Option<String>,
@@ -19,7 +19,8 @@ pub struct RawSillyCommandData {
     pub gender_attributes: Option<Vec<String>>
 }
 
-#[derive(Debug, serde::Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
 pub struct SillyCommandData {
     pub id_silly_command: i32,
     pub name: String,
@@ -85,7 +86,8 @@ impl RawSillyCommandData {
 }
 
 #[repr(i32)]
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
 pub enum SillyCommandType {
     AuthorOnly = 1,
     SingleUser = 2,
@@ -104,72 +106,76 @@ impl TryFrom<i32> for SillyCommandType {
 
 impl SillyCommandData {}
 
-#[derive(FromRow)]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
 pub struct Usages {
     pub usages: i32
 }
 
-#[derive(FromRow)]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
 pub struct CommandUsage {
     pub usages: i32
 }
 
-#[derive(FromRow)]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
 pub struct CommandUsageId {
     pub id_silly_command_usage: i32
 }
 
-#[derive(FromRow)]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
 pub struct CommandId {
     pub id_silly_command: i32
 }
 
-#[derive(FromRow)]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
 pub struct RandomImage {
     pub image: String
 }
 
-#[derive(FromRow)]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
 pub struct CommandTextId {
     pub id_silly_command_text: i32
 }
 
-#[derive(FromRow)]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
 pub struct CommandSelfActionTextId {
     pub id_silly_command_self_action_text: i32
 }
 
-#[derive(FromRow)]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
 pub struct CommandSelfActionImageId {
     pub id_silly_command_self_action: i32
 }
 
-#[derive(FromRow)]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
 pub struct CommandImageId {
     pub id_silly_command_images: i32
 }
 
 // struct AddImageAuthorRequest
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
 pub struct AddTextRequest {
     pub command_name: String,
     pub content: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
 pub struct AddTextAuthorRequest {
     pub command_name: String,
     pub content: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
 pub struct AddPreferenceRequest {
     pub command_name: String,
     pub preference: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
 pub struct AddCommandRequest {
     pub command_name: String,
     pub description: String,
@@ -177,32 +183,32 @@ pub struct AddCommandRequest {
     pub command_type: SillyCommandType,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
 pub struct IncrementCommandUsage {
     pub command: i32,
     pub author: u64,
     pub user: u64,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
 pub struct CreateCommandUsage {
     pub command: i32,
     pub author: u64,
     pub user: u64
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
 pub struct FetchCommandUsage {
     pub command: i32
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
 pub struct FetchRandomSillyImageByNameAndPreference {
     pub command: i32,
     pub preference: String
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
 pub struct FetchSillyCommandByName {
     pub name: String
 }
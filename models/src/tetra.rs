@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+
+use crate::league_stats::MatchStats;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct TetraData {
+    pub replay_id: Option<String>,
+    /// PNG screenshot of the game
+    #[cfg_attr(feature = "utoipa", schema(value_type = Vec<u8>))]
+    pub buffer: Box<[u8]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stats: Option<MatchStats>
+}
+
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "utoipa", into_params(parameter_in = Query))]
+pub struct TetraQuery {
+    pub user_id: String,
+    /// 1 is the most recent game
+    pub game_num: u32,
+    /// Set to true to get the match stats along with the screenshot
+    pub stats: Option<bool>
+}
+
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "utoipa", into_params(parameter_in = Query))]
+pub struct FullLeaderboardQuery {
+    pub country: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "utoipa", into_params(parameter_in = Query))]
+pub struct TetraTestParam {
+    pub left_score: Option<u32>,
+    pub right_score: Option<u32>,
+}
@@ -0,0 +1,33 @@
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Rating of a player right after one of their Tetra League games
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct TetraHistoryPoint {
+    pub tetrio_user_id: String,
+    pub replay_id: String,
+    pub played_at: DateTime<Utc>,
+    pub tr: Option<f64>,
+    pub glicko: Option<f64>,
+    pub rd: Option<f64>,
+    pub rank: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct TetraHistory {
+    pub user_id: String,
+    pub points: Vec<TetraHistoryPoint>,
+    #[cfg_attr(feature = "utoipa", schema(value_type = Option<Vec<u8>>))]
+    pub buffer: Option<Box<[u8]>>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "utoipa", into_params(parameter_in = Query))]
+pub struct TetraHistoryQuery {
+    /// Set to true to get a rendered line chart along with the series
+    pub render: Option<bool>,
+}
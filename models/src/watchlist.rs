@@ -0,0 +1,70 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct Watchlist {
+    pub id_watchlist: i32,
+    pub guild_id: String,
+    pub webhook_url: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct WatchedPlayer {
+    pub id_watched_player: i32,
+    pub id_watchlist: i32,
+    pub tetrio_user: String,
+    pub last_game_id: Option<String>,
+    pub last_rank: Option<String>,
+    pub best_40l: Option<f64>,
+    pub best_blitz: Option<i64>,
+    pub checked_at: Option<DateTime<Utc>>,
+}
+
+/// Watched player joined with the watchlist it belongs to, as needed by the poller
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "sqlx", derive(sqlx::FromRow))]
+pub struct WatchedPlayerTarget {
+    #[cfg_attr(feature = "sqlx", sqlx(flatten))]
+    pub player: WatchedPlayer,
+    pub guild_id: String,
+    pub webhook_url: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct WatchlistData {
+    #[serde(flatten)]
+    pub watchlist: Watchlist,
+    pub players: Vec<WatchedPlayer>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct CreateWatchlistRequest {
+    pub guild_id: String,
+    pub webhook_url: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct CreatedWatchlist {
+    pub watchlist_id: i32,
+}
+
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct DeleteWatchlistRequest {
+    pub guild_id: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct WatchedPlayerRequest {
+    pub guild_id: String,
+    pub user: String,
+}
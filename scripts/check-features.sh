@@ -0,0 +1,17 @@
+#!/bin/sh
+# builds, lints and tests the api with each feature on its own, then the whole workspace with every feature
+set -eu
+
+cd "$(dirname "$0")/.."
+
+for features in tetrio database; do
+    echo "==> $features"
+    cargo build -p taka_the_discord_bot_api --no-default-features --features "$features"
+    cargo clippy -p taka_the_discord_bot_api --all-targets --no-default-features --features "$features" -- -D warnings
+    cargo test -p taka_the_discord_bot_api --no-default-features --features "$features"
+done
+
+echo "==> workspace"
+cargo build --workspace --all-features
+cargo clippy --workspace --all-targets --all-features -- -D warnings
+cargo test --workspace --all-features
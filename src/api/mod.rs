@@ -1,8 +1,9 @@
 pub mod v1;
 
-use std::fmt::Display;
+use std::{fmt::Display, sync::Arc};
 
 use axum::Router;
+use utoipa_swagger_ui::SwaggerUi;
 pub use v1 as api_v1;
 
 #[derive(Debug)]
@@ -14,11 +15,20 @@ impl Display for Error {
     }
 }
 
+impl std::error::Error for Error {}
 
-pub async fn api() -> Result<Router, Error> {
+
+/// Where Swagger UI is served, it needs a looser content security policy than the api
+pub const DOCS_PATH: &str = "/api/v1/docs";
+
+/// Every route backed by the api state, to be merged at the root of the server, and that state
+pub async fn api(options: &v1::ApiV1Options) -> Result<(Router, Arc<v1::ApiV1State>), Error> {
+    let (v1, health, state) = v1::api_v1(options).await?;
     let api = 
         Router::new()
-            .nest("/v1", v1::api_v1().await?);
+            .nest("/api/v1", v1)
+            .nest("/health", health)
+            .merge(SwaggerUi::new(DOCS_PATH).url("/api/v1/openapi.json", v1::openapi::openapi()));
 
-    Ok(api)
+    Ok((api, state))
 }
\ No newline at end of file
@@ -0,0 +1,561 @@
+use std::{collections::{HashMap, HashSet}, sync::{atomic::{AtomicU64, Ordering}, Arc}, time::{Duration, Instant, SystemTime, UNIX_EPOCH}};
+
+use moka::Expiry;
+use redis::AsyncCommands;
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::sync::Mutex;
+
+use crate::config::ConfigSource;
+
+use super::{metrics::Metrics, Error};
+
+// how long redis is left alone after it failed, before trying to reconnect
+const REDIS_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+const REDIS_CONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+
+// keys of a namespace whose redis memory usage is measured, the total is extrapolated from them
+const MEMORY_USAGE_SAMPLE: usize = 1000;
+
+// MEMORY USAGE commands sent per pipeline
+const MEMORY_USAGE_BATCH: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CacheNamespace {
+    TetoImage,
+    RecordCard,
+    RecordStats,
+    Leaderboard,
+    Commands,
+    TetrioUser,
+    TetrioLeagueRecords,
+}
+
+impl CacheNamespace {
+    pub const ALL: [CacheNamespace; 7] = [
+        Self::TetoImage,
+        Self::RecordCard,
+        Self::RecordStats,
+        Self::Leaderboard,
+        Self::Commands,
+        Self::TetrioUser,
+        Self::TetrioLeagueRecords,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::TetoImage => "teto_image",
+            Self::RecordCard => "record_card",
+            Self::RecordStats => "record_stats",
+            Self::Leaderboard => "leaderboard",
+            Self::Commands => "commands",
+            Self::TetrioUser => "tetrio_user",
+            Self::TetrioLeagueRecords => "tetrio_league_records",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|namespace| namespace.name() == name)
+    }
+
+    /// Whether keys of the namespace end with a username, the only ones `purge_user` touches
+    fn is_user_keyed(&self) -> bool {
+        match self {
+            Self::TetoImage | Self::RecordCard | Self::RecordStats | Self::TetrioUser | Self::TetrioLeagueRecords => true,
+            Self::Leaderboard | Self::Commands => false,
+        }
+    }
+
+    fn prefix(&self) -> String {
+        format!("taka:{}:", self.name())
+    }
+
+    fn default_ttl(&self) -> Duration {
+        match self {
+            Self::TetoImage | Self::RecordCard | Self::RecordStats | Self::TetrioUser | Self::TetrioLeagueRecords => Duration::from_secs(60),
+            Self::Leaderboard => Duration::from_secs(3600),
+            Self::Commands => Duration::from_secs(300),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct NamespaceStats {
+    pub namespace: &'static str,
+    pub ttl_seconds: u64,
+    pub memory_keys: usize,
+    pub memory_bytes: usize,
+    /// `None` while redis is unreachable
+    pub redis_keys: Option<usize>,
+    /// Estimated from a sample of the namespace's keys
+    pub redis_bytes: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct KeyInfo {
+    pub key: String,
+    pub in_memory: bool,
+    /// Time left in redis in milliseconds, -1 when the key never expires and `None` when it isn't there
+    pub redis_ttl_ms: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Default)]
+pub struct PurgeResult {
+    pub memory_keys: usize,
+    pub redis_keys: usize,
+}
+
+/// Json value kept in memory along with its own time to live
+#[derive(Clone)]
+struct MemoryEntry {
+    json: Arc<str>,
+    ttl: Duration,
+}
+
+struct MemoryEntryExpiry;
+
+impl Expiry<String, MemoryEntry> for MemoryEntryExpiry {
+    fn expire_after_create(&self, _key: &String, value: &MemoryEntry, _created_at: Instant) -> Option<Duration> {
+        Some(value.ttl)
+    }
+}
+
+#[derive(Clone)]
+pub struct CacheOptions {
+    /// Size of the in-memory tier, in bytes of serialized json
+    pub memory_capacity: u64,
+    pub ttls: HashMap<CacheNamespace, Duration>,
+    /// How long an expired entry may still be served when refreshing it fails
+    pub max_stale: HashMap<CacheNamespace, Duration>,
+}
+
+impl CacheOptions {
+    pub fn from_config(source: &mut ConfigSource) -> Self {
+        let mut ttls = HashMap::new();
+        let mut max_stale = HashMap::new();
+        for namespace in CacheNamespace::ALL {
+            let name = format!("CACHE_TTL_{}_SECONDS", namespace.name().to_uppercase());
+            ttls.insert(namespace, Duration::from_secs(source.parse(&name, namespace.default_ttl().as_secs())));
+            let name = format!("CACHE_MAX_STALE_{}_SECONDS", namespace.name().to_uppercase());
+            max_stale.insert(namespace, Duration::from_secs(source.parse(&name, 3600)));
+        }
+
+        Self {
+            memory_capacity: source.parse("CACHE_MEMORY_CAPACITY_BYTES", 256 * 1024 * 1024),
+            ttls,
+            max_stale,
+        }
+    }
+}
+
+/// Bounded in-process cache in front of redis. Entries are namespaced as `taka:{namespace}:{key}` in both tiers,
+/// and the cache keeps serving from memory alone while redis is unreachable.
+pub struct TieredCache {
+    memory: moka::future::Cache<String, MemoryEntry>,
+    redis: redis::Client,
+    connection: Mutex<Option<redis::aio::MultiplexedConnection>>,
+    // milliseconds since the unix epoch before which redis isn't tried again
+    redis_retry_at: AtomicU64,
+    ttls: HashMap<CacheNamespace, Duration>,
+    max_stale: HashMap<CacheNamespace, Duration>,
+    // keys with a background refresh in flight
+    refreshing: Arc<std::sync::Mutex<HashSet<String>>>,
+}
+
+/// Claim on the background refresh of a key, released when dropped so a failed or aborted refresh doesn't block the next one
+pub struct RefreshGuard {
+    refreshing: Arc<std::sync::Mutex<HashSet<String>>>,
+    key: String,
+}
+
+impl Drop for RefreshGuard {
+    fn drop(&mut self) {
+        let mut refreshing = self.refreshing.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        refreshing.remove(&self.key);
+    }
+}
+
+pub fn now_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64
+}
+
+impl TieredCache {
+    pub fn new(redis: redis::Client, options: CacheOptions) -> Self {
+        let memory = moka::future::Cache::builder()
+            .max_capacity(options.memory_capacity)
+            .weigher(|key: &String, value: &MemoryEntry| (key.len() + value.json.len()).try_into().unwrap_or(u32::MAX))
+            .expire_after(MemoryEntryExpiry)
+            .build();
+
+        Self {
+            memory,
+            redis,
+            connection: Mutex::new(None),
+            redis_retry_at: AtomicU64::new(0),
+            ttls: options.ttls,
+            max_stale: options.max_stale,
+            refreshing: Arc::new(std::sync::Mutex::new(HashSet::new())),
+        }
+    }
+
+    pub fn key(namespace: CacheNamespace, key: &str) -> String {
+        format!("taka:{}:{}", namespace.name(), key.to_lowercase())
+    }
+
+    pub fn ttl(&self, namespace: CacheNamespace) -> Duration {
+        self.ttls.get(&namespace).copied().unwrap_or(namespace.default_ttl())
+    }
+
+    pub fn max_stale(&self, namespace: CacheNamespace) -> Duration {
+        self.max_stale.get(&namespace).copied().unwrap_or_default()
+    }
+
+    /// Claims the background refresh of `key` until the guard is dropped, `None` when one is already running
+    pub fn begin_refresh(&self, namespace: CacheNamespace, key: &str) -> Option<RefreshGuard> {
+        let key = Self::key(namespace, key);
+        let mut refreshing = self.refreshing.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        refreshing.insert(key.clone()).then(|| RefreshGuard { refreshing: Arc::clone(&self.refreshing), key })
+    }
+
+    /// Shared redis connection, `None` while redis is considered down
+    pub async fn redis_connection(&self) -> Option<redis::aio::MultiplexedConnection> {
+        if now_millis() < self.redis_retry_at.load(Ordering::Relaxed) {
+            return None;
+        }
+
+        let mut connection = self.connection.lock().await;
+        if let Some(connection) = connection.as_ref() {
+            return Some(connection.clone());
+        }
+
+        match tokio::time::timeout(REDIS_CONNECT_TIMEOUT, self.redis.get_multiplexed_async_connection()).await {
+            Ok(Ok(new_connection)) => {
+                *connection = Some(new_connection.clone());
+                Some(new_connection)
+            },
+            Ok(Err(e)) => {
+                self.mark_redis_down(&format!("{e}"));
+                None
+            },
+            Err(_) => {
+                self.mark_redis_down("connection timed out");
+                None
+            }
+        }
+    }
+
+    fn mark_redis_down(&self, reason: &str) {
+        log::warn!("Redis unavailable, serving from memory for {REDIS_RETRY_DELAY:?}: {reason}");
+        self.redis_retry_at.store(now_millis() + REDIS_RETRY_DELAY.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    async fn forget_connection(&self, reason: &str) {
+        self.connection.lock().await.take();
+        self.mark_redis_down(reason);
+    }
+
+    #[tracing::instrument(level = "debug", skip(self), fields(namespace = namespace.name()))]
+    pub async fn get<T: DeserializeOwned>(&self, namespace: CacheNamespace, key: &str) -> Option<T> {
+        let key = Self::key(namespace, key);
+
+        if let Some(entry) = self.memory.get(&key).await {
+            Metrics::global().cache_lookup(namespace.name(), "memory");
+            return serde_json::from_str(&entry.json).ok();
+        }
+
+        let Some(mut connection) = self.redis_connection().await else {
+            Metrics::global().cache_lookup(namespace.name(), "miss");
+            return None;
+        };
+        let result: redis::RedisResult<(Option<String>, i64)> = redis::pipe()
+            .get(&key)
+            .pttl(&key)
+            .query_async(&mut connection)
+            .await;
+
+        let (json, ttl) = match result {
+            Ok((Some(json), ttl)) => (json, ttl),
+            Ok((None, _)) => {
+                Metrics::global().cache_lookup(namespace.name(), "miss");
+                return None;
+            },
+            Err(e) => {
+                Metrics::global().cache_lookup(namespace.name(), "miss");
+                self.forget_connection(&format!("{e}")).await;
+                return None;
+            }
+        };
+        Metrics::global().cache_lookup(namespace.name(), "redis");
+
+        let value = serde_json::from_str(&json).ok()?;
+        // a negative ttl means the key has no expiry, fall back to the namespace default
+        let ttl = u64::try_from(ttl).map(Duration::from_millis).unwrap_or(self.ttl(namespace));
+        self.memory.insert(key, MemoryEntry { json: json.into(), ttl }).await;
+
+        Some(value)
+    }
+
+    /// Stores `value` for `ttl`, or the namespace's time to live when `None`
+    #[tracing::instrument(level = "debug", skip(self, value), fields(namespace = namespace.name()))]
+    pub async fn set<T: Serialize>(&self, namespace: CacheNamespace, key: &str, value: &T, ttl: Option<Duration>) -> Result<(), Error> {
+        let key = Self::key(namespace, key);
+        let ttl = ttl.unwrap_or(self.ttl(namespace));
+        if ttl.is_zero() {
+            return Ok(());
+        }
+
+        let json = serde_json::to_string(value).map_err(|e| Error(format!("Couldn't serialize cache entry! {e}")))?;
+
+        if let Some(mut connection) = self.redis_connection().await {
+            if let Err(e) = connection.pset_ex::<_, _, ()>(&key, &json, ttl.as_millis() as u64).await {
+                self.forget_connection(&format!("{e}")).await;
+            }
+        }
+
+        self.memory.insert(key, MemoryEntry { json: json.into(), ttl }).await;
+        Ok(())
+    }
+
+    /// Drops every key of the memory tier matching `predicate`
+    async fn invalidate_memory_where(&self, predicate: impl Fn(&str) -> bool) -> usize {
+        let keys = self.memory.iter()
+            .filter(|(key, _)| predicate(key))
+            .map(|(key, _)| key)
+            .collect::<Vec<_>>();
+
+        for key in &keys {
+            self.memory.invalidate(key.as_str()).await;
+        }
+        keys.len()
+    }
+
+    /// Round trip to redis, ignoring the retry backoff so a recovered redis is noticed right away
+    pub async fn ping(&self) -> Result<(), Error> {
+        self.redis_retry_at.store(0, Ordering::Relaxed);
+        let mut connection = self.redis_connection().await.ok_or(Error("Couldn't connect to redis".to_string()))?;
+
+        if let Err(e) = redis::cmd("PING").query_async::<_, String>(&mut connection).await {
+            self.forget_connection(&format!("{e}")).await;
+            return Err(Error(format!("Couldn't ping redis! {e}")));
+        }
+        Ok(())
+    }
+
+    /// Drops the redis connection for good, the cache only answers from memory afterwards
+    pub async fn close(&self) {
+        self.redis_retry_at.store(u64::MAX, Ordering::Relaxed);
+        self.connection.lock().await.take();
+    }
+
+    /// Keys of redis matching `pattern`, `None` when redis is down
+    async fn scan_redis(&self, pattern: &str) -> Option<Vec<String>> {
+        let mut connection = self.redis_connection().await?;
+        let mut iter = match connection.scan_match::<_, String>(pattern).await {
+            Ok(iter) => iter,
+            Err(e) => {
+                self.forget_connection(&format!("{e}")).await;
+                return None;
+            }
+        };
+
+        let mut keys = vec![];
+        while let Some(key) = iter.next_item().await {
+            keys.push(key);
+        }
+        Some(keys)
+    }
+
+    async fn delete_redis_keys(&self, keys: &[String]) -> usize {
+        if keys.is_empty() {
+            return 0;
+        }
+        let Some(mut connection) = self.redis_connection().await else {
+            return 0;
+        };
+        match connection.del::<_, usize>(keys).await {
+            Ok(deleted) => deleted,
+            Err(e) => {
+                self.forget_connection(&format!("{e}")).await;
+                0
+            }
+        }
+    }
+
+    pub async fn namespace_stats(&self) -> Vec<NamespaceStats> {
+        let mut stats = vec![];
+        for namespace in CacheNamespace::ALL {
+            let prefix = namespace.prefix();
+            let (memory_keys, memory_bytes) = self.memory.iter()
+                .filter(|(key, _)| key.starts_with(&prefix))
+                .fold((0, 0), |(count, bytes), (key, entry)| (count + 1, bytes + key.len() + entry.json.len()));
+
+            let mut namespace_stats = NamespaceStats {
+                namespace: namespace.name(),
+                ttl_seconds: self.ttl(namespace).as_secs(),
+                memory_keys,
+                memory_bytes,
+                redis_keys: None,
+                redis_bytes: None,
+            };
+
+            if let Some(keys) = self.scan_redis(&format!("{}*", escape_glob(&prefix))).await {
+                namespace_stats.redis_keys = Some(keys.len());
+                namespace_stats.redis_bytes = self.estimate_redis_bytes(&keys).await;
+            }
+
+            stats.push(namespace_stats);
+        }
+        stats
+    }
+
+    /// Memory used in redis by `keys`, measured on a sample of them and batched so a large namespace doesn't block redis
+    async fn estimate_redis_bytes(&self, keys: &[String]) -> Option<usize> {
+        let sample = &keys[..keys.len().min(MEMORY_USAGE_SAMPLE)];
+        if sample.is_empty() {
+            return Some(0);
+        }
+
+        let mut sampled_bytes = 0;
+        for batch in sample.chunks(MEMORY_USAGE_BATCH) {
+            let mut connection = self.redis_connection().await?;
+            let mut pipe = redis::pipe();
+            for key in batch {
+                pipe.cmd("MEMORY").arg("USAGE").arg(key);
+            }
+            let usage: Vec<Option<usize>> = match pipe.query_async(&mut connection).await {
+                Ok(usage) => usage,
+                Err(e) => {
+                    self.forget_connection(&format!("{e}")).await;
+                    return None;
+                }
+            };
+            sampled_bytes += usage.into_iter().flatten().sum::<usize>();
+        }
+
+        Some(sampled_bytes * keys.len() / sample.len())
+    }
+
+    pub async fn key_info(&self, namespace: CacheNamespace, key: &str) -> KeyInfo {
+        let full_key = Self::key(namespace, key);
+        let in_memory = self.memory.contains_key(&full_key);
+
+        let mut redis_ttl_ms = None;
+        if let Some(mut connection) = self.redis_connection().await {
+            match connection.pttl::<_, i64>(&full_key).await {
+                // -2 means the key doesn't exist, -1 that it never expires
+                Ok(ttl) if ttl != -2 => redis_ttl_ms = Some(ttl),
+                Ok(_) => {},
+                Err(e) => self.forget_connection(&format!("{e}")).await
+            }
+        }
+
+        KeyInfo { key: full_key, in_memory, redis_ttl_ms }
+    }
+
+    /// Removes the entries of `user` in the namespaces keyed by username, keys being the username or ending with `/{username}`
+    pub async fn purge_user(&self, user: &str) -> PurgeResult {
+        let user = user.to_lowercase();
+        let mut result = PurgeResult::default();
+
+        for namespace in CacheNamespace::ALL.into_iter().filter(CacheNamespace::is_user_keyed) {
+            let prefix = namespace.prefix();
+            result.memory_keys += self.invalidate_memory_where(|key| {
+                key.strip_prefix(&prefix).is_some_and(|key| is_key_of_user(key, &user))
+            }).await;
+
+            let mut keys = vec![format!("{prefix}{user}")];
+            keys.extend(self.scan_redis(&format!("{}*/{}", escape_glob(&prefix), escape_glob(&user))).await.unwrap_or_default());
+            result.redis_keys += self.delete_redis_keys(&keys).await;
+        }
+
+        result
+    }
+
+    pub async fn purge_namespace(&self, namespace: CacheNamespace) -> PurgeResult {
+        let prefix = namespace.prefix();
+        let memory_keys = self.invalidate_memory_where(|key| key.starts_with(&prefix)).await;
+
+        let keys = self.scan_redis(&format!("{}*", escape_glob(&prefix))).await.unwrap_or_default();
+        let redis_keys = self.delete_redis_keys(&keys).await;
+
+        PurgeResult { memory_keys, redis_keys }
+    }
+
+    pub async fn invalidate(&self, namespace: CacheNamespace, key: &str) {
+        let key = Self::key(namespace, key);
+        self.memory.invalidate(&key).await;
+
+        if let Some(mut connection) = self.redis_connection().await {
+            if let Err(e) = connection.del::<_, ()>(&key).await {
+                self.forget_connection(&format!("{e}")).await;
+            }
+        }
+    }
+}
+
+fn is_key_of_user(key: &str, user: &str) -> bool {
+    key == user || key.strip_suffix(user).is_some_and(|rest| rest.ends_with('/'))
+}
+
+/// Escapes the characters redis glob patterns give a meaning to, so `text` only matches itself
+fn escape_glob(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Time left before tetr.io considers a response stale, read from its `cache.cached_until`
+pub fn tetrio_cache_ttl<C: Serialize>(cache: &C) -> Option<Duration> {
+    let cached_until = serde_json::to_value(cache).ok()?.get("cached_until")?.as_u64()?;
+    Some(Duration::from_millis(cached_until.saturating_sub(now_millis())))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{escape_glob, is_key_of_user, CacheNamespace, CacheOptions, TieredCache};
+
+    #[test]
+    fn escapes_glob_metacharacters() {
+        assert_eq!(escape_glob("taka:tetrio_user:"), "taka:tetrio_user:");
+        assert_eq!(escape_glob("a*b?c[d]e\\f"), "a\\*b\\?c\\[d\\]e\\\\f");
+    }
+
+    #[test]
+    fn matches_whole_usernames_only() {
+        assert!(is_key_of_user("osk", "osk"));
+        assert!(is_key_of_user("40l/osk", "osk"));
+        assert!(is_key_of_user("summaries/league/osk", "osk"));
+        assert!(!is_key_of_user("40l/notosk", "osk"));
+        assert!(!is_key_of_user("osk/lock", "osk"));
+    }
+
+    #[test]
+    fn only_purges_user_keyed_namespaces() {
+        // leaderboard keys end with a country or `global`, which are valid usernames
+        assert!(!CacheNamespace::Leaderboard.is_user_keyed());
+        assert!(!CacheNamespace::Commands.is_user_keyed());
+        assert!(CacheNamespace::TetrioUser.is_user_keyed());
+        assert!(CacheNamespace::RecordCard.is_user_keyed());
+    }
+
+    #[test]
+    fn refresh_claims_are_released_on_drop() {
+        // the client only connects when a command is sent
+        let redis = redis::Client::open("redis://127.0.0.1/").expect("the url is valid");
+        let cache = TieredCache::new(redis, CacheOptions { memory_capacity: 1024, ttls: HashMap::new(), max_stale: HashMap::new() });
+
+        let guard = cache.begin_refresh(CacheNamespace::TetrioUser, "osk");
+        assert!(guard.is_some());
+        assert!(cache.begin_refresh(CacheNamespace::TetrioUser, "OSK").is_none());
+
+        drop(guard);
+        assert!(cache.begin_refresh(CacheNamespace::TetrioUser, "osk").is_some());
+    }
+}
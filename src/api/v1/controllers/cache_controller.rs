@@ -0,0 +1,102 @@
+#![cfg(feature = "database")]
+
+use std::sync::Arc;
+
+use axum::{extract::{Query, State}, response::IntoResponse, Extension, Json};
+use serde_json::json;
+
+use crate::api::api_v1::{cache::CacheNamespace, error::ApiError, models::{cache::{CacheKeyRequest, PurgeNamespaceRequest, PurgeUserRequest}, user::User}, ApiV1State};
+
+fn parse_namespace(namespace: &str) -> Result<CacheNamespace, ApiError> {
+    CacheNamespace::from_name(namespace).ok_or_else(|| {
+        ApiError::bad_request(format!("Unknown cache namespace {namespace}"))
+    })
+}
+
+#[utoipa::path(get, path = "/admin/cache", responses(
+    (status = 200, description = "Entry count and size of every cache namespace", body = crate::api::api_v1::openapi::AnySuccess),
+    (status = 401, description = "Missing or invalid token", body = crate::api::api_v1::openapi::ErrorBody),
+    (status = 403, description = "The user isn't an admin", body = crate::api::api_v1::openapi::ErrorBody),
+), security(("bearer" = []), ("cookie" = [])))]
+pub async fn list_namespaces(
+    State(state): State<Arc<ApiV1State>>,
+    Extension(_): Extension<User>,
+) -> Result<impl IntoResponse, ApiError> {
+    Ok(Json(json!({
+        "status": "success",
+        "data": {
+            "namespaces": state.cache.namespace_stats().await,
+        }
+    })))
+}
+
+#[utoipa::path(get, path = "/admin/cache/key", params(CacheKeyRequest), responses(
+    (status = 200, description = "Where the key is cached and until when", body = crate::api::api_v1::openapi::AnySuccess),
+    (status = 400, description = "Unknown namespace", body = crate::api::api_v1::openapi::ErrorBody),
+    (status = 401, description = "Missing or invalid token", body = crate::api::api_v1::openapi::ErrorBody),
+    (status = 403, description = "The user isn't an admin", body = crate::api::api_v1::openapi::ErrorBody),
+), security(("bearer" = []), ("cookie" = [])))]
+pub async fn key_info(
+    State(state): State<Arc<ApiV1State>>,
+    Extension(_): Extension<User>,
+    Query(query): Query<CacheKeyRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let namespace = parse_namespace(&query.namespace)?;
+
+    Ok(Json(json!({
+        "status": "success",
+        "data": state.cache.key_info(namespace, &query.key).await
+    })))
+}
+
+#[utoipa::path(post, path = "/admin/cache/purge_key", request_body = CacheKeyRequest, responses(
+    (status = 200, description = "The key is no longer cached", body = crate::api::api_v1::openapi::AnySuccess),
+    (status = 400, description = "Unknown namespace", body = crate::api::api_v1::openapi::ErrorBody),
+    (status = 401, description = "Missing or invalid token", body = crate::api::api_v1::openapi::ErrorBody),
+    (status = 403, description = "The user isn't an admin", body = crate::api::api_v1::openapi::ErrorBody),
+), security(("bearer" = []), ("cookie" = [])))]
+pub async fn purge_key(
+    State(state): State<Arc<ApiV1State>>,
+    Extension(_): Extension<User>,
+    Json(body): Json<CacheKeyRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let namespace = parse_namespace(&body.namespace)?;
+    state.cache.invalidate(namespace, &body.key).await;
+
+    Ok(Json(json!({"status": "success", "data": ()})))
+}
+
+#[utoipa::path(post, path = "/admin/cache/purge_user", request_body = PurgeUserRequest, responses(
+    (status = 200, description = "Number of purged entries of the user", body = crate::api::api_v1::openapi::AnySuccess),
+    (status = 401, description = "Missing or invalid token", body = crate::api::api_v1::openapi::ErrorBody),
+    (status = 403, description = "The user isn't an admin", body = crate::api::api_v1::openapi::ErrorBody),
+), security(("bearer" = []), ("cookie" = [])))]
+pub async fn purge_user(
+    State(state): State<Arc<ApiV1State>>,
+    Extension(_): Extension<User>,
+    Json(body): Json<PurgeUserRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    Ok(Json(json!({
+        "status": "success",
+        "data": state.cache.purge_user(&body.user).await
+    })))
+}
+
+#[utoipa::path(post, path = "/admin/cache/purge_namespace", request_body = PurgeNamespaceRequest, responses(
+    (status = 200, description = "Number of purged entries of the namespace", body = crate::api::api_v1::openapi::AnySuccess),
+    (status = 400, description = "Unknown namespace", body = crate::api::api_v1::openapi::ErrorBody),
+    (status = 401, description = "Missing or invalid token", body = crate::api::api_v1::openapi::ErrorBody),
+    (status = 403, description = "The user isn't an admin", body = crate::api::api_v1::openapi::ErrorBody),
+), security(("bearer" = []), ("cookie" = [])))]
+pub async fn purge_namespace(
+    State(state): State<Arc<ApiV1State>>,
+    Extension(_): Extension<User>,
+    Json(body): Json<PurgeNamespaceRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let namespace = parse_namespace(&body.namespace)?;
+
+    Ok(Json(json!({
+        "status": "success",
+        "data": state.cache.purge_namespace(namespace).await
+    })))
+}
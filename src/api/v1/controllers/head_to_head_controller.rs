@@ -0,0 +1,23 @@
+#![cfg(feature = "tetrio")]
+
+use std::sync::Arc;
+
+use axum::{extract::{Query, State}, response::IntoResponse, Json};
+use tetrio_api::models::packet::Packet;
+
+use crate::api::api_v1::{error::ApiError, models::head_to_head::{HeadToHead, HeadToHeadQuery}, services::head_to_head::HeadToHeadService, ApiV1State};
+
+#[utoipa::path(get, path = "/tetra/h2h", params(HeadToHeadQuery), responses(
+    (status = 200, description = "Tetra League games the two players played against each other", body = crate::api::api_v1::openapi::HeadToHeadPacket),
+    (status = 404, description = "One of the players doesn't exist", body = crate::api::api_v1::openapi::ErrorBody),
+))]
+pub async fn head_to_head(State(state): State<Arc<ApiV1State>>, Query(query): Query<HeadToHeadQuery>) -> Result<impl IntoResponse, ApiError> {
+    let head_to_head = HeadToHeadService::fetch_head_to_head(&state, &query.user, &query.opponent, query.render.unwrap_or(false)).await?;
+
+    Ok(Json(Packet::<HeadToHead> {
+        success: true,
+        data: Some(head_to_head),
+        cache: None,
+        error: None
+    }))
+}
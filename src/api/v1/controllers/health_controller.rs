@@ -0,0 +1,22 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use serde_json::json;
+
+use crate::api::api_v1::{models::health::ComponentStatus, services::health::HealthService, ApiV1State};
+
+/// The process is up and serving requests
+pub async fn live() -> impl IntoResponse {
+    Json(json!({"status": "up"}))
+}
+
+/// Every dependency answers, orchestrators should only route traffic here on a 200
+pub async fn ready(State(state): State<Arc<ApiV1State>>) -> impl IntoResponse {
+    let readiness = HealthService::readiness(&state).await;
+    let status = match readiness.status {
+        ComponentStatus::Up => StatusCode::OK,
+        ComponentStatus::Down => StatusCode::SERVICE_UNAVAILABLE,
+    };
+
+    (status, Json(readiness))
+}
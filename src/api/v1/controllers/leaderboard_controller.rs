@@ -0,0 +1,44 @@
+#![cfg(feature = "tetrio")]
+
+use std::{convert::Infallible, sync::Arc};
+
+use axum::{extract::{Path, Query, State}, response::{sse::{Event, KeepAlive, Sse}, IntoResponse}, Json};
+use tetrio_api::models::packet::Packet;
+use tokio_stream::{wrappers::{errors::BroadcastStreamRecvError, BroadcastStream}, Stream, StreamExt};
+
+use crate::api::api_v1::{error::ApiError, models::leaderboard::{LeaderboardEventsQuery, PlayerRank}, services::leaderboard::LeaderboardCrawler, ApiV1State};
+
+#[utoipa::path(get, path = "/leaderboard/rank/{user}", params(("user" = String, Path, description = "tetr.io username or user id")), responses(
+    (status = 200, description = "Position of the user on the Tetra League leaderboard", body = crate::api::api_v1::openapi::PlayerRankPacket),
+    (status = 404, description = "The user isn't ranked", body = crate::api::api_v1::openapi::ErrorBody),
+))]
+pub async fn player_rank(State(state): State<Arc<ApiV1State>>, Path(user): Path<String>) -> Result<impl IntoResponse, ApiError> {
+    let rank = LeaderboardCrawler::fetch_player_rank(&state, &user).await?;
+
+    Ok(Json(Packet::<PlayerRank> {
+        success: true,
+        data: Some(rank),
+        cache: None,
+        error: None
+    }))
+}
+
+#[utoipa::path(get, path = "/leaderboard/events", params(LeaderboardEventsQuery), responses(
+    (status = 200, description = "Server-sent events named after the event type, plus `lagged` events with the number of skipped events", body = crate::api::api_v1::models::leaderboard::LeaderboardEvent, content_type = "text/event-stream"),
+))]
+pub async fn leaderboard_events(State(state): State<Arc<ApiV1State>>, Query(query): Query<LeaderboardEventsQuery>) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let stream = BroadcastStream::new(state.leaderboard_events.subscribe())
+        .filter_map(move |event| match event {
+            Ok(event) if query.matches(&event) => Event::default()
+                .event(event.name())
+                .json_data(event.as_ref())
+                .ok()
+                .map(Ok),
+            Ok(_) => None,
+            Err(BroadcastStreamRecvError::Lagged(skipped)) => Some(Ok(Event::default()
+                .event("lagged")
+                .data(skipped.to_string()))),
+        });
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
@@ -0,0 +1,34 @@
+#![cfg(feature = "database")]
+
+use std::{path::Path, sync::Arc};
+
+use axum::{body::Body, extract::{Query, State}, http::header, response::IntoResponse, Extension};
+
+use crate::api::{api_v1::{error::ApiError, models::{logs::LogQuery, user::User}, services::logs::{LogFilter, LogReader, LOG_DIRECTORY}, ApiV1State}, Error};
+
+#[utoipa::path(get, path = "/admin/logs", params(LogQuery), responses(
+    (status = 200, description = "Matching log lines, oldest first", body = String, content_type = "text/plain"),
+    (status = 400, description = "Invalid filter", body = crate::api::api_v1::openapi::ErrorBody),
+    (status = 404, description = "No logs available", body = crate::api::api_v1::openapi::ErrorBody),
+    (status = 401, description = "Missing or invalid token", body = crate::api::api_v1::openapi::ErrorBody),
+    (status = 403, description = "The user isn't an admin", body = crate::api::api_v1::openapi::ErrorBody),
+), security(("bearer" = []), ("cookie" = [])))]
+pub async fn logs(
+    State(state): State<Arc<ApiV1State>>,
+    Extension(_): Extension<User>,
+    Query(query): Query<LogQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let filter = LogFilter::try_from(&query).map_err(|Error(message)| ApiError::bad_request(message))?;
+
+    let files = LogReader::log_files(Path::new(LOG_DIRECTORY))?;
+
+    if files.is_empty() {
+        return Err(ApiError::not_found("No logs available"));
+    }
+
+    Ok((
+        [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+        // the tail is buffered in memory until the last file is read
+        Body::from_stream(LogReader::stream(files, filter, query.tail.map(|tail| tail.min(state.log_max_tail))))
+    ))
+}
@@ -1,3 +1,12 @@
 #![allow(unused)]
 pub mod user_controller;
-pub mod silly_command_controller;
\ No newline at end of file
+pub mod silly_command_controller;
+pub mod leaderboard_controller;
+pub mod watchlist_controller;
+pub mod records_controller;
+pub mod tetra_history_controller;
+pub mod head_to_head_controller;
+pub mod cache_controller;
+pub mod logs_controller;
+pub mod health_controller;
+pub mod rate_limit_controller;
\ No newline at end of file
@@ -0,0 +1,69 @@
+#![cfg(feature = "database")]
+
+use std::sync::Arc;
+
+use axum::{extract::State, response::IntoResponse, Extension, Json};
+use serde_json::json;
+
+use crate::api::api_v1::{error::ApiError, models::{rate_limit::{DeleteRateLimitOverrideRequest, RateLimitOverride}, user::User}, services::rate_limit::RateLimiter, ApiV1State};
+
+#[utoipa::path(get, path = "/admin/rate_limits", responses(
+    (status = 200, description = "Default and per route quotas, and every override", body = crate::api::api_v1::openapi::AnySuccess),
+    (status = 401, description = "Missing or invalid token", body = crate::api::api_v1::openapi::ErrorBody),
+    (status = 403, description = "The user isn't an admin", body = crate::api::api_v1::openapi::ErrorBody),
+), security(("bearer" = []), ("cookie" = [])))]
+pub async fn list_overrides(
+    State(state): State<Arc<ApiV1State>>,
+    Extension(_): Extension<User>,
+) -> Result<impl IntoResponse, ApiError> {
+    let overrides = RateLimiter::list_overrides(&state).await?;
+
+    Ok(Json(json!({
+        "status": "success",
+        "data": {
+            "default": state.rate_limit_options.default_quota,
+            "routes": state.rate_limit_options.route_quotas,
+            "overrides": overrides,
+        }
+    })))
+}
+
+#[utoipa::path(post, path = "/admin/rate_limits/set", request_body = RateLimitOverride, responses(
+    (status = 200, description = "The override is stored", body = crate::api::api_v1::openapi::RateLimitOverrideSuccess),
+    (status = 400, description = "Invalid client", body = crate::api::api_v1::openapi::ErrorBody),
+    (status = 401, description = "Missing or invalid token", body = crate::api::api_v1::openapi::ErrorBody),
+    (status = 403, description = "The user isn't an admin", body = crate::api::api_v1::openapi::ErrorBody),
+), security(("bearer" = []), ("cookie" = [])))]
+pub async fn set_override(
+    State(state): State<Arc<ApiV1State>>,
+    Extension(_): Extension<User>,
+    Json(body): Json<RateLimitOverride>,
+) -> Result<impl IntoResponse, ApiError> {
+    if !["api:", "user:", "ip:"].iter().any(|prefix| body.client.starts_with(prefix)) {
+        return Err(ApiError::bad_request("The client must be api:<key>, user:<id> or ip:<address>"));
+    }
+
+    RateLimiter::set_override(&state, &body).await?;
+
+    Ok(Json(json!({"status": "success", "data": body})))
+}
+
+#[utoipa::path(post, path = "/admin/rate_limits/delete", request_body = DeleteRateLimitOverrideRequest, responses(
+    (status = 200, description = "The override is deleted", body = crate::api::api_v1::openapi::AnySuccess),
+    (status = 404, description = "No such override", body = crate::api::api_v1::openapi::ErrorBody),
+    (status = 401, description = "Missing or invalid token", body = crate::api::api_v1::openapi::ErrorBody),
+    (status = 403, description = "The user isn't an admin", body = crate::api::api_v1::openapi::ErrorBody),
+), security(("bearer" = []), ("cookie" = [])))]
+pub async fn delete_override(
+    State(state): State<Arc<ApiV1State>>,
+    Extension(_): Extension<User>,
+    Json(body): Json<DeleteRateLimitOverrideRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let deleted = RateLimiter::delete_override(&state, &body.client, body.route.as_deref()).await?;
+
+    if !deleted {
+        return Err(ApiError::not_found("No such rate limit override"));
+    }
+
+    Ok(Json(json!({"status": "success", "data": ()})))
+}
@@ -0,0 +1,21 @@
+#![cfg(feature = "tetrio")]
+
+use std::sync::Arc;
+
+use axum::{extract::{Path, Query, State}, response::IntoResponse, Json};
+
+use crate::api::api_v1::{error::ApiError, models::records::{RecordMode, RecordQuery}, services::records::TetrioRecords, ApiV1State};
+
+#[utoipa::path(get, path = "/records/{user}/{mode}", params(
+    ("user" = String, Path, description = "tetr.io username or user id"),
+    ("mode" = RecordMode, Path),
+    RecordQuery,
+), responses(
+    (status = 200, description = "Personal best of the user, with a rendered card unless `render` is false", body = crate::api::api_v1::models::records::RecordCard),
+    (status = 404, description = "The user or the record doesn't exist", body = crate::api::api_v1::openapi::ErrorBody),
+))]
+pub async fn record(State(state): State<Arc<ApiV1State>>, Path((user, mode)): Path<(String, RecordMode)>, Query(query): Query<RecordQuery>) -> Result<impl IntoResponse, ApiError> {
+    let entry = TetrioRecords::fetch_record_card(&state, &user, mode, query.render.unwrap_or(true)).await?;
+
+    Ok(Json(entry))
+}
@@ -4,7 +4,6 @@ use std::{path::PathBuf, sync::Arc};
 
 use axum::{
     extract::{Multipart, State},
-    http::StatusCode,
     response::IntoResponse,
     Extension, Json, Router,
 };
@@ -12,6 +11,8 @@ use serde_json::json;
 use tower_http::services::ServeDir;
 
 use crate::api::{api_v1::{
+    cache::CacheNamespace,
+    error::ApiError,
     models::{silly_command::{AddCommandRequest, AddPreferenceRequest, AddTextAuthorRequest, AddTextRequest, FetchRandomSillyImageByNameAndPreference, FetchSillyCommandByName}, user::User}, services::silly_command::SillyCommandPDO, ApiV1State,
 }, v1::models::silly_command::SillyCommandData};
 
@@ -21,8 +22,21 @@ pub fn router() -> Router {
 
 }
 
-pub async fn get_commands(State(state): State<Arc<ApiV1State<'_>>>) -> impl IntoResponse {
-    Json::<Vec<SillyCommandData>>(SillyCommandPDO::fetch_silly_commands(&state).await).into_response()
+#[utoipa::path(get, path = "/get_commands", responses(
+    (status = 200, description = "Every silly command", body = Vec<SillyCommandData>),
+    (status = 500, description = "The commands couldn't be loaded", body = crate::api::api_v1::openapi::ErrorBody),
+))]
+pub async fn get_commands(State(state): State<Arc<ApiV1State>>) -> Result<impl IntoResponse, ApiError> {
+    if let Some(commands) = state.cache.get::<Vec<SillyCommandData>>(CacheNamespace::Commands, "all").await {
+        return Ok(Json(commands));
+    }
+
+    let commands = SillyCommandPDO::fetch_silly_commands(&state).await?;
+    if let Err(err) = state.cache.set(CacheNamespace::Commands, "all", &commands, None).await {
+        log::warn!("Couldn't cache commands: {err}");
+    }
+
+    Ok(Json(commands))
 }
 
 
@@ -111,10 +125,10 @@ struct AddImageRequest {
 }
 
 pub async fn add_image(
-    State(state): State<Arc<ApiV1State<'_>>>,
+    State(state): State<Arc<ApiV1State>>,
     Extension(_): Extension<User>,
     mut body: Multipart,
-) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<impl IntoResponse, ApiError> {
     // Extract image, command name, command type, and call SillyCommandPDO::add_image
     // return the id of the image
     let mut req = AddImageRequest {
@@ -123,31 +137,15 @@ pub async fn add_image(
         extension: None,
         preference: None,
     };
-    while let Some(part) = body.next_field().await.map_err(|e| {
-        let json_error = json!({
-            "status": "fail",
-            "message": format!("Internal server error: {}", e),
-        });
-        (StatusCode::INTERNAL_SERVER_ERROR, Json(json_error))
-    })? {
+    while let Some(part) = body.next_field().await.map_err(|e| ApiError::bad_request(format!("Invalid multipart body: {e}")))? {
         match part.name() {
             None => {
-                let json_error = json!({
-                    "status": "fail",
-                    "message": "Invalid field name",
-                });
-                return Err((StatusCode::BAD_REQUEST, Json(json_error)));
+                return Err(ApiError::bad_request("Invalid field name"));
             }
             Some(data) => {
                 match data {
                     "image" => {
-                        let image = part.bytes().await.map_err(|e| {
-                            let json_error = json!({
-                                "status": "fail",
-                                "message": format!("Internal server error: {}", e),
-                            });
-                            (StatusCode::INTERNAL_SERVER_ERROR, Json(json_error))
-                        })?;
+                        let image = part.bytes().await.map_err(|e| ApiError::bad_request(format!("Invalid multipart body: {e}")))?;
 
                         req.image = Some(image.to_vec());
 
@@ -160,58 +158,32 @@ pub async fn add_image(
                         // })?;
                     }
                     "command_name" => {
-                        let command_name = part.text().await.map_err(|e| {
-                            let json_error = json!({
-                                "status": "fail",
-                                "message": format!("Internal server error: {}", e),
-                            });
-                            (StatusCode::INTERNAL_SERVER_ERROR, Json(json_error))
-                        })?;
+                        let command_name = part.text().await.map_err(|e| ApiError::bad_request(format!("Invalid multipart body: {e}")))?;
 
                         let command_name = command_name.to_string();
 
                         let command_id =
                             SillyCommandPDO::fetch_silly_command_by_name(&state, &command_name)
-                                .await;
+                                .await?;
 
                         if command_id.is_none() {
-                            let json_error = json!({
-                                "status": "fail",
-                                "message": "Invalid command name",
-                            });
-                            return Err((StatusCode::BAD_REQUEST, Json(json_error)));
+                            return Err(ApiError::bad_request("Invalid command name"));
                         }
 
                         req.command_name = Some(command_name);
                     }
                     "extension" => {
-                        let extension = part.text().await.map_err(|e| {
-                            let json_error = json!({
-                                "status": "fail",
-                                "message": format!("Internal server error: {}", e),
-                            });
-                            (StatusCode::INTERNAL_SERVER_ERROR, Json(json_error))
-                        })?;
+                        let extension = part.text().await.map_err(|e| ApiError::bad_request(format!("Invalid multipart body: {e}")))?;
 
                         req.extension = Some(extension.to_string());
                     }
                     "preference" => {
-                        let preference = part.text().await.map_err(|e| {
-                            let json_error = json!({
-                                "status": "fail",
-                                "message": format!("Internal server error: {}", e),
-                            });
-                            (StatusCode::INTERNAL_SERVER_ERROR, Json(json_error))
-                        })?;
+                        let preference = part.text().await.map_err(|e| ApiError::bad_request(format!("Invalid multipart body: {e}")))?;
 
                         req.preference = Some(preference.to_string());
                     }
                     _ => {
-                        let json_error = json!({
-                            "status": "fail",
-                            "message": "Invalid field name",
-                        });
-                        return Err((StatusCode::BAD_REQUEST, Json(json_error)));
+                        return Err(ApiError::bad_request("Invalid field name"));
                     }
                 }
             }
@@ -225,22 +197,13 @@ pub async fn add_image(
         preference,
     } = req
     else {
-        let json_error = json!({
-            "status": "fail",
-            "message": "Invalid request",
-        });
-        return Err((StatusCode::BAD_REQUEST, Json(json_error)));
+        return Err(ApiError::bad_request("Invalid request"));
     };
 
     let image_id = SillyCommandPDO::add_image(&state, &command_name, image, &extension, preference)
-        .await
-        .map_err(|e| {
-            let json_error = json!({
-                "status": "fail",
-                "message": format!("Internal server error: {}", e),
-            });
-            (StatusCode::INTERNAL_SERVER_ERROR, Json(json_error))
-        })?;
+        .await?;
+
+    state.cache.invalidate(CacheNamespace::Commands, "all").await;
 
     let image_response = json!({
         "status": "success",
@@ -260,10 +223,10 @@ struct AddImageAuthorRequest {
 
 // add image author
 pub async fn add_image_author(
-    State(state): State<Arc<ApiV1State<'_>>>,
+    State(state): State<Arc<ApiV1State>>,
     Extension(_): Extension<User>,
     mut body: Multipart,
-) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<impl IntoResponse, ApiError> {
     // Extract image, command name, command type, and call SillyCommandPDO::add_image
     // return the id of the image
     let mut req = AddImageAuthorRequest {
@@ -271,31 +234,15 @@ pub async fn add_image_author(
         image: None,
         extension: None,
     };
-    while let Some(part) = body.next_field().await.map_err(|e| {
-        let json_error = json!({
-            "status": "fail",
-            "message": format!("Internal server error: {}", e),
-        });
-        (StatusCode::INTERNAL_SERVER_ERROR, Json(json_error))
-    })? {
+    while let Some(part) = body.next_field().await.map_err(|e| ApiError::bad_request(format!("Invalid multipart body: {e}")))? {
         match part.name() {
             None => {
-                let json_error = json!({
-                    "status": "fail",
-                    "message": "Invalid field name",
-                });
-                return Err((StatusCode::BAD_REQUEST, Json(json_error)));
+                return Err(ApiError::bad_request("Invalid field name"));
             }
             Some(data) => {
                 match data {
                     "image" => {
-                        let image = part.bytes().await.map_err(|e| {
-                            let json_error = json!({
-                                "status": "fail",
-                                "message": format!("Internal server error: {}", e),
-                            });
-                            (StatusCode::INTERNAL_SERVER_ERROR, Json(json_error))
-                        })?;
+                        let image = part.bytes().await.map_err(|e| ApiError::bad_request(format!("Invalid multipart body: {e}")))?;
 
                         req.image = Some(image.to_vec());
 
@@ -308,48 +255,28 @@ pub async fn add_image_author(
                         // })?;
                     }
                     "command_name" => {
-                        let command_name = part.text().await.map_err(|e| {
-                            let json_error = json!({
-                                "status": "fail",
-                                "message": format!("Internal server error: {}", e),
-                            });
-                            (StatusCode::INTERNAL_SERVER_ERROR, Json(json_error))
-                        })?;
+                        let command_name = part.text().await.map_err(|e| ApiError::bad_request(format!("Invalid multipart body: {e}")))?;
 
                         let command_name = command_name.to_string();
 
                         let command_id =
                             SillyCommandPDO::fetch_silly_command_by_name(&state, &command_name)
-                                .await;
+                                .await?;
 
                         if command_id.is_none() {
-                            let json_error = json!({
-                                "status": "fail",
-                                "message": "Invalid command name",
-                            });
-                            return Err((StatusCode::BAD_REQUEST, Json(json_error)));
+                            return Err(ApiError::bad_request("Invalid command name"));
                         }
 
                         req.command_name = Some(command_name);
                     }
                     "extension" => {
-                        let extension = part.text().await.map_err(|e| {
-                            let json_error = json!({
-                                "status": "fail",
-                                "message": format!("Internal server error: {}", e),
-                            });
-                            (StatusCode::INTERNAL_SERVER_ERROR, Json(json_error))
-                        })?;
+                        let extension = part.text().await.map_err(|e| ApiError::bad_request(format!("Invalid multipart body: {e}")))?;
 
                         req.extension = Some(extension.to_string());
                     }
 
                     _ => {
-                        let json_error = json!({
-                            "status": "fail",
-                            "message": "Invalid field name",
-                        });
-                        return Err((StatusCode::BAD_REQUEST, Json(json_error)));
+                        return Err(ApiError::bad_request("Invalid field name"));
                     }
                 }
             }
@@ -362,22 +289,13 @@ pub async fn add_image_author(
         extension: Some(extension),
     } = req
     else {
-        let json_error = json!({
-            "status": "fail",
-            "message": "Invalid request",
-        });
-        return Err((StatusCode::BAD_REQUEST, Json(json_error)));
+        return Err(ApiError::bad_request("Invalid request"));
     };
 
     let image_id = SillyCommandPDO::add_image_author(&state, &command_name, image, &extension)
-        .await
-        .map_err(|e| {
-            let json_error = json!({
-                "status": "fail",
-                "message": format!("Internal server error: {}", e),
-            });
-            (StatusCode::INTERNAL_SERVER_ERROR, Json(json_error))
-        })?;
+        .await?;
+
+    state.cache.invalidate(CacheNamespace::Commands, "all").await;
 
     let image_response = json!({
         "status": "success",
@@ -392,21 +310,16 @@ pub async fn add_image_author(
 // JSON(AddCommandRequest)
 
 pub async fn create_command(
-    State(state): State<Arc<ApiV1State<'_>>>,
+    State(state): State<Arc<ApiV1State>>,
     Extension(_): Extension<User>,
     Json(body): Json<AddCommandRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<impl IntoResponse, ApiError> {
     // Extract command name, command type, and call SillyCommandPDO::add_command
     // return the id of the command
     let command_id = SillyCommandPDO::create_command(&state, &body.command_name, &body.description, &body.footer_text, body.command_type)
-        .await
-        .map_err(|e| {
-            let json_error = json!({
-                "status": "fail",
-                "message": format!("Internal server error: {}", e),
-            });
-            (StatusCode::INTERNAL_SERVER_ERROR, Json(json_error))
-        })?;
+        .await?;
+
+    state.cache.invalidate(CacheNamespace::Commands, "all").await;
 
     let command_response = json!({
         "status": "success",
@@ -420,21 +333,16 @@ pub async fn create_command(
 
 // JSON(AddTextRequest)
 pub async fn add_text(
-    State(state): State<Arc<ApiV1State<'_>>>,
+    State(state): State<Arc<ApiV1State>>,
     Extension(_): Extension<User>,
     Json(body): Json<AddTextRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<impl IntoResponse, ApiError> {
     // Extract command name, command type, and call SillyCommandPDO::add_text
     // return the id of the text
     let text_id = SillyCommandPDO::add_text(&state, &body.command_name, &body.content)
-        .await
-        .map_err(|e| {
-            let json_error = json!({
-                "status": "fail",
-                "message": format!("Internal server error: {}", e),
-            });
-            (StatusCode::INTERNAL_SERVER_ERROR, Json(json_error))
-        })?;
+        .await?;
+
+    state.cache.invalidate(CacheNamespace::Commands, "all").await;
 
     let text_response = json!({
         "status": "success",
@@ -448,21 +356,16 @@ pub async fn add_text(
 
 // JSON(AddTextAuthorRequest)
 pub async fn add_text_author(
-    State(state): State<Arc<ApiV1State<'_>>>,
+    State(state): State<Arc<ApiV1State>>,
     Extension(_): Extension<User>,
     Json(body): Json<AddTextAuthorRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<impl IntoResponse, ApiError> {
     // Extract command name, command type, and call SillyCommandPDO::add_text
     // return the id of the text
     let text_id = SillyCommandPDO::add_text_author(&state, &body.command_name, &body.content)
-        .await
-        .map_err(|e| {
-            let json_error = json!({
-                "status": "fail",
-                "message": format!("Internal server error: {}", e),
-            });
-            (StatusCode::INTERNAL_SERVER_ERROR, Json(json_error))
-        })?;
+        .await?;
+
+    state.cache.invalidate(CacheNamespace::Commands, "all").await;
 
     let text_response = json!({
         "status": "success",
@@ -476,21 +379,16 @@ pub async fn add_text_author(
 
 // JSON(AddPreferenceRequest)
 pub async fn add_preference(
-    State(state): State<Arc<ApiV1State<'_>>>,
+    State(state): State<Arc<ApiV1State>>,
     Extension(_): Extension<User>,
     Json(body): Json<AddPreferenceRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<impl IntoResponse, ApiError> {
     // Extract command name, command type, and call SillyCommandPDO::add_preference
     // return the id of the preference
     let preference_id = SillyCommandPDO::add_preference(&state, &body.command_name, &body.preference)
-        .await
-        .map_err(|e| {
-            let json_error = json!({
-                "status": "fail",
-                "message": format!("Internal server error: {}", e),
-            });
-            (StatusCode::INTERNAL_SERVER_ERROR, Json(json_error))
-        })?;
+        .await?;
+
+    state.cache.invalidate(CacheNamespace::Commands, "all").await;
 
     let preference_response = json!({
         "status": "success",
@@ -505,21 +403,14 @@ pub async fn add_preference(
 
 // JSON(FetchRandomSillyImageByNameAndPreference)
 pub async fn fetch_random_silly_image_by_name_and_preference(
-    State(state): State<Arc<ApiV1State<'_>>>,
+    State(state): State<Arc<ApiV1State>>,
     Extension(_): Extension<User>,
     Json(body): Json<FetchRandomSillyImageByNameAndPreference>,
-) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<impl IntoResponse, ApiError> {
     // Extract command name, command type, and call SillyCommandPDO::fetch_random_silly_image_by_name_and_preference
     // return the id of the preference
     let image = SillyCommandPDO::fetch_random_silly_image_by_name_and_preference(&state, body.command, &body.preference)
-        .await
-        .map_err(|e| {
-            let json_error = json!({
-                "status": "fail",
-                "message": format!("Internal server error: {}", e),
-            });
-            (StatusCode::INTERNAL_SERVER_ERROR, Json(json_error))
-        })?;
+        .await?;
 
     let image_response = json!({
         "status": "success",
@@ -534,21 +425,15 @@ pub async fn fetch_random_silly_image_by_name_and_preference(
 
 // JSON(FetchSillyCommandByName)
 pub async fn fetch_silly_command_by_name(
-    State(state): State<Arc<ApiV1State<'_>>>,
+    State(state): State<Arc<ApiV1State>>,
     Extension(_): Extension<User>,
     Json(body): Json<FetchSillyCommandByName>,
-) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<impl IntoResponse, ApiError> {
     // Extract command name, command type, and call SillyCommandPDO::fetch_random_silly_image_by_name_and_preference
     // return the id of the preference
     let command = SillyCommandPDO::fetch_silly_command_by_name(&state, &body.name)
-        .await
-        .ok_or_else(|| {
-            let json_error = json!({
-                "status": "fail",
-                "message": "Couldn't find command",
-            });
-            (StatusCode::NOT_FOUND, Json(json_error))
-        })?;
+        .await?
+        .ok_or_else(|| ApiError::not_found("Couldn't find command"))?;
 
     let command_response = json!({
         "status": "success",
@@ -0,0 +1,23 @@
+#![cfg(all(feature = "database", feature = "tetrio"))]
+
+use std::sync::Arc;
+
+use axum::{extract::{Path, Query, State}, response::IntoResponse, Json};
+use tetrio_api::models::packet::Packet;
+
+use crate::api::api_v1::{error::ApiError, models::tetra_history::{TetraHistory, TetraHistoryQuery}, services::tetra_history::TetraHistoryService, ApiV1State};
+
+#[utoipa::path(get, path = "/tetra/history/{user}", params(("user" = String, Path, description = "tetr.io username or user id"), TetraHistoryQuery), responses(
+    (status = 200, description = "Rating of the user after each recorded Tetra League game", body = crate::api::api_v1::openapi::TetraHistoryPacket),
+    (status = 404, description = "The user doesn't exist", body = crate::api::api_v1::openapi::ErrorBody),
+))]
+pub async fn tetra_history(State(state): State<Arc<ApiV1State>>, Path(user): Path<String>, Query(query): Query<TetraHistoryQuery>) -> Result<impl IntoResponse, ApiError> {
+    let history = TetraHistoryService::fetch_history(&state, &user, query.render.unwrap_or(false)).await?;
+
+    Ok(Json(Packet::<TetraHistory> {
+        success: true,
+        data: Some(history),
+        cache: None,
+        error: None
+    }))
+}
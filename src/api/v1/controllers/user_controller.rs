@@ -2,13 +2,13 @@
 
 use std::sync::Arc;
 
-use axum::{extract::{Path, State}, http::{header, StatusCode}, response::{IntoResponse, Response}, routing::get, Extension, Json, Router};
+use axum::{extract::{Path, State}, http::header, response::{IntoResponse, Response}, routing::get, Extension, Json, Router};
 use axum_extra::extract::cookie::{Cookie, SameSite};
 use jsonwebtoken::{encode, EncodingKey, Header};
 use serde_json::json;
 use uuid::Uuid;
 
-use crate::api::api_v1::{models::user::{FilteredUser, User, RegisterUserSchema, LoginUserSchema, JwtUser, UpdatePasswordSchema, UpdateUser, UpdateUserData, ForceUpdateUser, CreateUser}, ApiV1State, services::users::UserPDO};
+use crate::api::api_v1::{error::{ApiError, ErrorCode}, models::user::{FilteredUser, User, RegisterUserSchema, LoginUserSchema, JwtUser, UpdatePasswordSchema, UpdateUser, UpdateUserData, ForceUpdateUser, CreateUser}, ApiV1State, services::users::UserPDO};
 
 
 
@@ -23,22 +23,16 @@ fn filter_user_record(user: &User) -> FilteredUser {
 }
 
 pub async fn register_user_handler(
-    State(data): State<Arc<ApiV1State<'_>>>,
+    State(data): State<Arc<ApiV1State>>,
     Json(user): Json<RegisterUserSchema>,
-) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<impl IntoResponse, ApiError> {
     
 
     let user = UserPDO::register_user(&data, &RegisterUserSchema {
         name: user.name.clone(),
         email: user.email.clone(),
         password: user.password.clone(),
-    }, None).await.map_err(|err| {
-        let error_response = serde_json::json!( {
-            "status": "fail",
-            "message": err.to_string(),
-        });
-        (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response))
-    })?;
+    }, None).await?;
 
     let user_response = serde_json::json!({"status": "success","data": serde_json::json!({
         "user": filter_user_record(&user)
@@ -47,7 +41,7 @@ pub async fn register_user_handler(
     Ok(Json(user_response))
 }
 
-pub async fn encode_token(user_id: Uuid, password_rev: Uuid, jwt_secret: &str) -> anyhow::Result<String> {
+pub async fn encode_token(user_id: Uuid, password_rev: Uuid, jwt_secret: &str) -> Result<String, jsonwebtoken::errors::Error> {
     let claims: JwtUser = JwtUser {
         id: user_id.to_string(),
         password_rev: password_rev.to_string(),
@@ -63,28 +57,16 @@ pub async fn encode_token(user_id: Uuid, password_rev: Uuid, jwt_secret: &str) -
 }
 
 pub async fn login_user_handler(
-    State(data): State<Arc<ApiV1State<'_>>>,
+    State(data): State<Arc<ApiV1State>>,
     Json(body): Json<LoginUserSchema>,
-) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<impl IntoResponse, ApiError> {
 
 
-    let user = UserPDO::login_user(&data, &body).await.map_err(|err| {
-        let error_response = serde_json::json!( {
-            "status": "fail",
-            "message": err.to_string(),
-        });
-        (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response))
-    })?;
+    let user = UserPDO::login_user(&data, &body).await?;
     
     let token = encode_token(user.id, user.password_rev, data.env.jwt_secret.as_ref())
         .await
-        .map_err(|e| {
-            let error_response = serde_json::json!( {
-                "status": "fail",
-                "message": e.to_string(),
-            });
-            (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response))
-        })?;
+        .map_err(|err| ApiError::from_source(ErrorCode::Internal, err))?;
 
     let cookie = Cookie::build(("token", token.to_owned()))
         .path("/")
@@ -100,7 +82,7 @@ pub async fn login_user_handler(
     Ok(response)
 }
 
-pub async fn logout_handler() -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+pub async fn logout_handler() -> Result<impl IntoResponse, ApiError> {
     let cookie = Cookie::build(("token", ""))
         .path("/")
         .max_age(time::Duration::hours(-1))
@@ -117,7 +99,7 @@ pub async fn logout_handler() -> Result<impl IntoResponse, (StatusCode, Json<ser
 
 pub async fn get_me_handler(
     Extension(user): Extension<User>,
-) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<impl IntoResponse, ApiError> {
     let json_response = serde_json::json!({
         "status":  "success",
         "data": serde_json::json!({
@@ -128,7 +110,7 @@ pub async fn get_me_handler(
     Ok(Json(json_response))
 }
 
-pub async fn get_users_handler() -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+pub async fn get_users_handler() -> Result<impl IntoResponse, ApiError> {
     let json_response = serde_json::json!({
         "status":  "success",
         "data": serde_json::json!({
@@ -141,18 +123,12 @@ pub async fn get_users_handler() -> Result<impl IntoResponse, (StatusCode, Json<
 
 // update password
 pub async fn update_password(
-    State(data): State<Arc<ApiV1State<'_>>>,
+    State(data): State<Arc<ApiV1State>>,
     Extension(user): Extension<User>,
     Json(body): Json<UpdatePasswordSchema>,
-) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<impl IntoResponse, ApiError> {
 
-    UserPDO::update_user_password(&data, &user.id.to_string(), &body.password).await.map_err(|err| {
-        let error_response = serde_json::json!( {
-            "status": "fail",
-            "message": err.to_string(),
-        });
-        (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response))
-    })?;
+    UserPDO::update_user_password(&data, &user.id.to_string(), &body.password).await?;
 
     let user_response = serde_json::json!({"status": "success","data": ()});
 
@@ -161,10 +137,10 @@ pub async fn update_password(
 
 // update user
 pub async fn update_user(
-    State(data): State<Arc<ApiV1State<'_>>>,
+    State(data): State<Arc<ApiV1State>>,
     Extension(user): Extension<User>,
     Json(body): Json<UpdateUser>,
-) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<impl IntoResponse, ApiError> {
 
     UserPDO::update_user(&data, &UpdateUserData {
         name: body.name.clone(),
@@ -172,13 +148,7 @@ pub async fn update_user(
         role: user.role.clone(),
         verified: user.verified,
         id: user.id.to_string(),
-    }).await.map_err(|err| {
-        let error_response = serde_json::json!( {
-            "status": "fail",
-            "message": err.to_string(),
-        });
-        (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response))
-    })?;
+    }).await?;
 
     let user_response = serde_json::json!({"status": "success","data": ()});
 
@@ -187,10 +157,10 @@ pub async fn update_user(
 
 // force update user
 pub async fn force_update_user(
-    State(data): State<Arc<ApiV1State<'_>>>,
+    State(data): State<Arc<ApiV1State>>,
     Extension(_): Extension<User>,
     Json(body): Json<ForceUpdateUser>,
-) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<impl IntoResponse, ApiError> {
 
     UserPDO::update_user(&data, &UpdateUserData {
         name: body.name.clone(),
@@ -198,13 +168,7 @@ pub async fn force_update_user(
         role: body.role.clone(),
         verified: body.verified,
         id: body.id.clone(),
-    }).await.map_err(|err| {
-        let error_response = serde_json::json!( {
-            "status": "fail",
-            "message": err.to_string(),
-        });
-        (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response))
-    })?;
+    }).await?;
 
     let user_response = serde_json::json!({"status": "success","data": ()});
 
@@ -214,10 +178,10 @@ pub async fn force_update_user(
 // create user
 
 pub async fn create_user(
-    State(data): State<Arc<ApiV1State<'_>>>,
+    State(data): State<Arc<ApiV1State>>,
     Extension(_): Extension<User>,
     Json(body): Json<CreateUser>,
-) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+) -> Result<impl IntoResponse, ApiError> {
 
     let user = UserPDO::create_user(&data, &CreateUser {
         name: body.name.clone(),
@@ -225,13 +189,7 @@ pub async fn create_user(
         password: body.password.clone(),
         role: body.role.to_string(),
         verified: body.verified
-    }).await.map_err(|err| {
-        let error_response = serde_json::json!( {
-            "status": "fail",
-            "message": err.to_string(),
-        });
-        (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response))
-    })?;
+    }).await?;
 
     let user_response = serde_json::json!({"status": "success","data": serde_json::json!({
         "user": filter_user_record(&user)
@@ -242,18 +200,12 @@ pub async fn create_user(
 
 // delete user
 pub async fn delete_user(
-    State(data): State<Arc<ApiV1State<'_>>>,
+    State(data): State<Arc<ApiV1State>>,
     Extension(_): Extension<User>,
     Path(user_id): Path<String>,
-) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-
-    UserPDO::delete_user(&data, &user_id).await.map_err(|err| {
-        let error_response = serde_json::json!( {
-            "status": "fail",
-            "message": err.to_string(),
-        });
-        (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response))
-    })?;
+) -> Result<impl IntoResponse, ApiError> {
+
+    UserPDO::delete_user(&data, &user_id).await?;
 
     let user_response = serde_json::json!({"status": "success","data": ()});
 
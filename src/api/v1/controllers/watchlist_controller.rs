@@ -0,0 +1,96 @@
+#![cfg(feature = "database")]
+
+use std::sync::Arc;
+
+use axum::{extract::{Path, State}, response::IntoResponse, Extension, Json};
+use serde_json::json;
+
+use crate::api::api_v1::{error::ApiError, models::{user::User, watchlist::{CreateWatchlistRequest, CreatedWatchlist, DeleteWatchlistRequest, WatchedPlayerRequest}}, services::watchlist::WatchlistPDO, ApiV1State};
+
+#[utoipa::path(get, path = "/watchlists/{guild_id}", params(("guild_id" = String, Path, description = "Discord guild id")), responses(
+    (status = 200, description = "Watchlist of the guild and its players", body = crate::api::api_v1::openapi::WatchlistSuccess),
+    (status = 404, description = "The guild has no watchlist", body = crate::api::api_v1::openapi::ErrorBody),
+    (status = 401, description = "Missing or invalid token", body = crate::api::api_v1::openapi::ErrorBody),
+    (status = 403, description = "Watchlists are managed by admins only", body = crate::api::api_v1::openapi::ErrorBody),
+), security(("bearer" = []), ("cookie" = [])))]
+pub async fn get_watchlist(
+    State(state): State<Arc<ApiV1State>>,
+    Extension(_): Extension<User>,
+    Path(guild_id): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let watchlist = WatchlistPDO::fetch_watchlist_data(&state, &guild_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Couldn't find watchlist"))?;
+
+    Ok(Json(json!({
+        "status": "success",
+        "data": watchlist
+    })))
+}
+
+#[utoipa::path(post, path = "/watchlists/create_watchlist", request_body = CreateWatchlistRequest, responses(
+    (status = 200, description = "Id of the new watchlist", body = crate::api::api_v1::openapi::CreatedWatchlistSuccess),
+    (status = 401, description = "Missing or invalid token", body = crate::api::api_v1::openapi::ErrorBody),
+    (status = 403, description = "Watchlists are managed by admins only", body = crate::api::api_v1::openapi::ErrorBody),
+), security(("bearer" = []), ("cookie" = [])))]
+pub async fn create_watchlist(
+    State(state): State<Arc<ApiV1State>>,
+    Extension(_): Extension<User>,
+    Json(body): Json<CreateWatchlistRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let watchlist = WatchlistPDO::create_watchlist(&state, &body.guild_id, &body.webhook_url)
+        .await?;
+
+    Ok(Json(json!({
+        "status": "success",
+        "data": CreatedWatchlist { watchlist_id: watchlist.id_watchlist }
+    })))
+}
+
+#[utoipa::path(post, path = "/watchlists/delete_watchlist", request_body = DeleteWatchlistRequest, responses(
+    (status = 200, description = "The watchlist is deleted", body = crate::api::api_v1::openapi::AnySuccess),
+    (status = 401, description = "Missing or invalid token", body = crate::api::api_v1::openapi::ErrorBody),
+    (status = 403, description = "Watchlists are managed by admins only", body = crate::api::api_v1::openapi::ErrorBody),
+), security(("bearer" = []), ("cookie" = [])))]
+pub async fn delete_watchlist(
+    State(state): State<Arc<ApiV1State>>,
+    Extension(_): Extension<User>,
+    Json(body): Json<DeleteWatchlistRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    WatchlistPDO::delete_watchlist(&state, &body.guild_id)
+        .await?;
+
+    Ok(Json(json!({"status": "success", "data": ()})))
+}
+
+#[utoipa::path(post, path = "/watchlists/add_player", request_body = WatchedPlayerRequest, responses(
+    (status = 200, description = "The player is watched", body = crate::api::api_v1::openapi::AnySuccess),
+    (status = 401, description = "Missing or invalid token", body = crate::api::api_v1::openapi::ErrorBody),
+    (status = 403, description = "Watchlists are managed by admins only", body = crate::api::api_v1::openapi::ErrorBody),
+), security(("bearer" = []), ("cookie" = [])))]
+pub async fn add_player(
+    State(state): State<Arc<ApiV1State>>,
+    Extension(_): Extension<User>,
+    Json(body): Json<WatchedPlayerRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    WatchlistPDO::add_watched_player(&state, &body.guild_id, &body.user)
+        .await?;
+
+    Ok(Json(json!({"status": "success", "data": ()})))
+}
+
+#[utoipa::path(post, path = "/watchlists/remove_player", request_body = WatchedPlayerRequest, responses(
+    (status = 200, description = "The player is no longer watched", body = crate::api::api_v1::openapi::AnySuccess),
+    (status = 401, description = "Missing or invalid token", body = crate::api::api_v1::openapi::ErrorBody),
+    (status = 403, description = "Watchlists are managed by admins only", body = crate::api::api_v1::openapi::ErrorBody),
+), security(("bearer" = []), ("cookie" = [])))]
+pub async fn remove_player(
+    State(state): State<Arc<ApiV1State>>,
+    Extension(_): Extension<User>,
+    Json(body): Json<WatchedPlayerRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    WatchlistPDO::remove_watched_player(&state, &body.guild_id, &body.user)
+        .await?;
+
+    Ok(Json(json!({"status": "success", "data": ()})))
+}
@@ -0,0 +1,203 @@
+use std::sync::Arc;
+
+use axum::{http::{header, HeaderValue, StatusCode}, response::{IntoResponse, Response}, Json};
+use serde_json::json;
+
+use crate::api::{api_v1::services::render::RenderError, Error};
+#[cfg(feature = "database")]
+use crate::api::api_v1::services::{silly_command::SillyCommandError, users::UserError, watchlist::WatchlistError};
+#[cfg(feature = "tetrio")]
+use crate::api::api_v1::services::tetrio::TetrioError;
+
+pub use taka_the_discord_bot_api_models::error::{ErrorBody, ErrorCode};
+
+/// Error answered by every endpoint, as `{"status": "fail", "code", "message"}` with the status of its code.
+/// Callers sending `Accept: application/problem+json` get an RFC 7807 body instead, see `problem_details`
+#[derive(Debug, Clone)]
+pub struct ApiError {
+    pub code: ErrorCode,
+    pub message: String,
+    /// What the service failed with, logged for server errors and never sent to the caller
+    pub source: Option<Arc<dyn std::error::Error + Send + Sync>>,
+}
+
+impl ApiError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self { code, message: message.into(), source: None }
+    }
+
+    /// An error answered as `code`, keeping `source` for the logs. Server errors get a generic message,
+    /// theirs can hold queries, hosts or paths the caller has no business seeing
+    pub fn from_source(code: ErrorCode, source: impl std::error::Error + Send + Sync + 'static) -> Self {
+        let message = if code.status() >= 500 { Self::server_message(code).to_string() } else { source.to_string() };
+        Self { code, message, source: Some(Arc::new(source)) }
+    }
+
+    fn server_message(code: ErrorCode) -> &'static str {
+        match code {
+            ErrorCode::RenderFailed => "Couldn't render the image",
+            ErrorCode::Upstream => "Couldn't get a valid answer from tetr.io",
+            ErrorCode::Unavailable => "Service unavailable, try again later",
+            _ => "Internal server error",
+        }
+    }
+
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::BadRequest, message)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::NotFound, message)
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::Internal, message)
+    }
+
+    pub fn status(&self) -> StatusCode {
+        StatusCode::from_u16(self.code.status()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+
+    /// The RFC 7807 form of the error, `instance` being the path that failed
+    pub fn problem(&self, instance: &str) -> Response {
+        let status = self.status();
+        let body = json!({
+            "type": "about:blank",
+            "title": status.canonical_reason().unwrap_or("Error"),
+            "status": status.as_u16(),
+            "detail": self.message,
+            "instance": instance,
+            "code": self.code,
+        });
+
+        let mut response = (status, Json(body)).into_response();
+        response.headers_mut().insert(header::CONTENT_TYPE, HeaderValue::from_static("application/problem+json"));
+        response
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Anything not classified by the service is our fault
+impl From<Error> for ApiError {
+    fn from(error: Error) -> Self {
+        Self::from_source(ErrorCode::Internal, error)
+    }
+}
+
+/// A pool that can't hand out connections means the database is down or overloaded, not that the query is wrong
+#[cfg(feature = "database")]
+fn database_code(error: &sqlx::Error) -> ErrorCode {
+    match error {
+        sqlx::Error::PoolTimedOut | sqlx::Error::PoolClosed | sqlx::Error::Io(_) => ErrorCode::Unavailable,
+        _ => ErrorCode::Internal,
+    }
+}
+
+#[cfg(feature = "database")]
+impl From<UserError> for ApiError {
+    fn from(error: UserError) -> Self {
+        let code = match &error {
+            UserError::Database(source) => database_code(source),
+            UserError::PasswordHash(_) => ErrorCode::Internal,
+            UserError::AlreadyExists => ErrorCode::Conflict,
+            UserError::NotFound => ErrorCode::NotFound,
+            UserError::InvalidCredentials => ErrorCode::InvalidCredentials,
+        };
+        Self::from_source(code, error)
+    }
+}
+
+#[cfg(feature = "database")]
+impl From<SillyCommandError> for ApiError {
+    fn from(error: SillyCommandError) -> Self {
+        let code = match &error {
+            SillyCommandError::Database(source) => database_code(source),
+            SillyCommandError::Io(_) => ErrorCode::Internal,
+            SillyCommandError::CommandNotFound(_) | SillyCommandError::ImageNotFound => ErrorCode::NotFound,
+        };
+        Self::from_source(code, error)
+    }
+}
+
+#[cfg(feature = "database")]
+impl From<WatchlistError> for ApiError {
+    fn from(error: WatchlistError) -> Self {
+        let code = match &error {
+            WatchlistError::Database(source) => database_code(source),
+            WatchlistError::InsecureWebhook => ErrorCode::BadRequest,
+            WatchlistError::NotFound => ErrorCode::NotFound,
+        };
+        Self::from_source(code, error)
+    }
+}
+
+#[cfg(feature = "tetrio")]
+impl From<TetrioError> for ApiError {
+    fn from(error: TetrioError) -> Self {
+        let code = match &error {
+            TetrioError::Request { .. } | TetrioError::Malformed(_) => ErrorCode::Upstream,
+            TetrioError::UserNotFound | TetrioError::NotFound(_) => ErrorCode::NotFound,
+            TetrioError::InvalidRequest(_) => ErrorCode::BadRequest,
+            TetrioError::Cache(_) | TetrioError::Serialize(_) => ErrorCode::Internal,
+            #[cfg(feature = "database")]
+            TetrioError::Database(source) => database_code(source),
+            TetrioError::Render(_) => ErrorCode::RenderFailed,
+        };
+        Self::from_source(code, error)
+    }
+}
+
+impl From<RenderError> for ApiError {
+    fn from(error: RenderError) -> Self {
+        Self::from_source(ErrorCode::RenderFailed, error)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        if self.status().is_server_error() {
+            match &self.source {
+                Some(source) => log::error!("Request failed with {:?}: {source:?}", self.code),
+                None => log::error!("Request failed with {:?}: {}", self.code, self.message),
+            }
+        }
+
+        let body = ErrorBody {
+            status: "fail".to_string(),
+            code: self.code,
+            message: self.message.clone(),
+        };
+
+        let mut response = (self.status(), Json(body)).into_response();
+        // kept around for `problem_details` to rewrite the body
+        response.extensions_mut().insert(self);
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::Error;
+
+    use super::{ApiError, ErrorCode};
+
+    #[test]
+    fn server_errors_hide_their_source() {
+        let error = ApiError::from(Error("Couldn't connect to redis://10.0.0.3:6379".to_string()));
+
+        assert_eq!(error.message, "Internal server error");
+        assert!(error.source.expect("the source is kept for the logs").to_string().contains("10.0.0.3"));
+    }
+
+    #[test]
+    fn client_errors_keep_their_message() {
+        let error = ApiError::from_source(ErrorCode::Conflict, Error("User already exists".to_string()));
+
+        assert_eq!(error.message, "User already exists");
+    }
+}
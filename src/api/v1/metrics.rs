@@ -0,0 +1,217 @@
+use std::{future::Future, sync::OnceLock, time::Instant};
+
+use axum::{extract::{MatchedPath, Request}, middleware::Next, response::Response};
+use prometheus::{Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry, TextEncoder};
+use tracing::Instrument;
+
+use super::{middlewares::request_id::set_current_route, Error};
+
+// renders take seconds, the default buckets stop too early
+const RENDER_BUCKETS: [f64; 10] = [0.1, 0.25, 0.5, 1.0, 2.0, 3.0, 5.0, 8.0, 13.0, 21.0];
+
+/// Counts a browser as in use until dropped, so a render cancelled by a timeout or a client hanging up doesn't leave the gauge up
+struct BrowserInUse<'a>(&'a IntGauge);
+
+impl<'a> BrowserInUse<'a> {
+    fn new(gauge: &'a IntGauge) -> Self {
+        gauge.inc();
+        Self(gauge)
+    }
+}
+
+impl Drop for BrowserInUse<'_> {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
+}
+
+pub struct Metrics {
+    registry: Registry,
+    requests: IntCounterVec,
+    request_duration: HistogramVec,
+    cache_lookups: IntCounterVec,
+    tetrio_requests: IntCounterVec,
+    tetrio_duration: HistogramVec,
+    renders: IntCounterVec,
+    render_duration: HistogramVec,
+    browsers_in_use: IntGauge,
+    #[cfg(feature = "database")]
+    sql_connections: IntGauge,
+    #[cfg(feature = "database")]
+    sql_idle_connections: IntGauge,
+    #[cfg(feature = "database")]
+    sql_pool: OnceLock<sqlx::PgPool>,
+}
+
+static METRICS: OnceLock<Metrics> = OnceLock::new();
+
+impl Metrics {
+    pub fn global() -> &'static Metrics {
+        METRICS.get_or_init(|| Self::new().expect("Couldn't register metrics"))
+    }
+
+    fn new() -> Result<Self, prometheus::Error> {
+        let registry = Registry::new_custom(Some("taka".to_string()), None)?;
+
+        let requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "Requests handled, by route and status"),
+            &["method", "route", "status"]
+        )?;
+        let request_duration = HistogramVec::new(
+            HistogramOpts::new("http_request_duration_seconds", "Time spent handling requests, by route"),
+            &["method", "route"]
+        )?;
+        let cache_lookups = IntCounterVec::new(
+            Opts::new("cache_lookups_total", "Cache lookups by namespace, result is memory, redis or miss"),
+            &["namespace", "result"]
+        )?;
+        let tetrio_requests = IntCounterVec::new(
+            Opts::new("tetrio_requests_total", "Calls made to the tetr.io API on tiered cache misses, by endpoint and outcome"),
+            &["endpoint", "outcome"]
+        )?;
+        let tetrio_duration = HistogramVec::new(
+            HistogramOpts::new("tetrio_request_duration_seconds", "Time spent waiting on the tetr.io API"),
+            &["endpoint"]
+        )?;
+        let renders = IntCounterVec::new(
+            Opts::new("renders_total", "Headless chrome renders, by kind and outcome"),
+            &["kind", "outcome"]
+        )?;
+        let render_duration = HistogramVec::new(
+            HistogramOpts::new("render_duration_seconds", "Time spent rendering images").buckets(RENDER_BUCKETS.to_vec()),
+            &["kind"]
+        )?;
+        let browsers_in_use = IntGauge::new("browsers_in_use", "Headless chrome instances currently rendering")?;
+
+        registry.register(Box::new(requests.clone()))?;
+        registry.register(Box::new(request_duration.clone()))?;
+        registry.register(Box::new(cache_lookups.clone()))?;
+        registry.register(Box::new(tetrio_requests.clone()))?;
+        registry.register(Box::new(tetrio_duration.clone()))?;
+        registry.register(Box::new(renders.clone()))?;
+        registry.register(Box::new(render_duration.clone()))?;
+        registry.register(Box::new(browsers_in_use.clone()))?;
+
+        #[cfg(feature = "database")]
+        let sql_connections = IntGauge::new("sql_pool_connections", "Open Postgres connections")?;
+        #[cfg(feature = "database")]
+        let sql_idle_connections = IntGauge::new("sql_pool_idle_connections", "Idle Postgres connections")?;
+        #[cfg(feature = "database")]
+        {
+            registry.register(Box::new(sql_connections.clone()))?;
+            registry.register(Box::new(sql_idle_connections.clone()))?;
+        }
+
+        Ok(Self {
+            registry,
+            requests,
+            request_duration,
+            cache_lookups,
+            tetrio_requests,
+            tetrio_duration,
+            renders,
+            render_duration,
+            browsers_in_use,
+            #[cfg(feature = "database")]
+            sql_connections,
+            #[cfg(feature = "database")]
+            sql_idle_connections,
+            #[cfg(feature = "database")]
+            sql_pool: OnceLock::new(),
+        })
+    }
+
+    /// Reports the usage of `pool` on every scrape
+    #[cfg(feature = "database")]
+    pub fn watch_sql_pool(&self, pool: sqlx::PgPool) {
+        let _ = self.sql_pool.set(pool);
+    }
+
+    pub fn cache_lookup(&self, namespace: &str, result: &str) {
+        self.cache_lookups.with_label_values(&[namespace, result]).inc();
+    }
+
+    /// Times a call to tetr.io. Only wrap requests that missed the tiered cache, like [`TetrioLookup`](super::services::tetrio::TetrioLookup) does,
+    /// so cached answers aren't counted as tetr.io calls
+    pub async fn observe_tetrio<T, E>(&self, endpoint: &str, request: impl Future<Output = Result<T, E>>) -> Result<T, E> {
+        let start = Instant::now();
+        let result = request.instrument(tracing::info_span!("tetrio", endpoint)).await;
+        self.tetrio_duration.with_label_values(&[endpoint]).observe(start.elapsed().as_secs_f64());
+        let outcome = if result.is_ok() { "success" } else { "error" };
+        self.tetrio_requests.with_label_values(&[endpoint, outcome]).inc();
+        result
+    }
+
+    pub async fn observe_render<T, E>(&self, kind: &str, render: impl Future<Output = Result<T, E>>) -> Result<T, E> {
+        let in_use = BrowserInUse::new(&self.browsers_in_use);
+        let start = Instant::now();
+        let result = render.instrument(tracing::info_span!("render", kind)).await;
+        drop(in_use);
+
+        self.render_duration.with_label_values(&[kind]).observe(start.elapsed().as_secs_f64());
+        let outcome = if result.is_ok() { "success" } else { "failure" };
+        self.renders.with_label_values(&[kind, outcome]).inc();
+        result
+    }
+
+    /// Every metric in the prometheus text format
+    pub fn encode(&self) -> Result<String, Error> {
+        #[cfg(feature = "database")]
+        if let Some(pool) = self.sql_pool.get() {
+            self.sql_connections.set(pool.size() as i64);
+            self.sql_idle_connections.set(pool.num_idle() as i64);
+        }
+
+        let mut buffer = vec![];
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)
+            .map_err(|e| Error(format!("Couldn't encode metrics! {e}")))?;
+        String::from_utf8(buffer).map_err(|e| Error(format!("Couldn't encode metrics! {e}")))
+    }
+}
+
+/// Counts and times every request by its route template, so `/teto/a` and `/teto/b` share a series
+pub async fn track_requests(request: Request, next: Next) -> Response {
+    let route = request.extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or("unmatched".to_string());
+    set_current_route(&route);
+    let method = request.method().to_string();
+
+    let start = Instant::now();
+    let response = next.run(request).await;
+
+    let metrics = Metrics::global();
+    metrics.request_duration.with_label_values(&[&method, &route]).observe(start.elapsed().as_secs_f64());
+    metrics.requests.with_label_values(&[&method, &route, response.status().as_str()]).inc();
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use prometheus::IntGauge;
+
+    use super::BrowserInUse;
+
+    #[test]
+    fn browsers_are_released_when_a_render_is_dropped() {
+        let gauge = IntGauge::new("browsers_in_use", "test").expect("the gauge is valid");
+
+        let in_use = BrowserInUse::new(&gauge);
+        assert_eq!(gauge.get(), 1);
+
+        drop(in_use);
+        assert_eq!(gauge.get(), 0);
+    }
+
+    #[tokio::test]
+    async fn cancelled_renders_release_their_browser() {
+        let metrics = super::Metrics::global();
+        let before = metrics.browsers_in_use.get();
+
+        let render = metrics.observe_render("test", std::future::pending::<Result<(), ()>>());
+        let _ = tokio::time::timeout(std::time::Duration::from_millis(10), render).await;
+
+        assert_eq!(metrics.browsers_in_use.get(), before);
+    }
+}
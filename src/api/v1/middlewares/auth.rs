@@ -4,32 +4,25 @@ use std::sync::Arc;
 
 use axum::{
     extract::State,
-    http::{header, Request, StatusCode},
+    http::{header, Request},
     middleware::Next,
     response::IntoResponse,
-    Json, body::Body,
+    body::Body,
 };
 
 use axum_extra::extract::cookie::CookieJar;
 use jsonwebtoken::{decode, DecodingKey, Validation};
-use serde::Serialize;
 
-use crate::api::api_v1::{ApiV1State, models::user::{JwtUser, User}, services::users::UserPDO};
+use crate::api::api_v1::{error::{ApiError, ErrorCode}, ApiV1State, middlewares::request_id::set_current_user, models::user::{JwtUser, User}, services::users::UserPDO};
 
 
 
-#[derive(Debug, Serialize)]
-pub struct ErrorResponse {
-    pub status: &'static str,
-    pub message: String,
-}
-
 pub async fn auth(
     cookie_jar: CookieJar,
-    State(data): State<Arc<ApiV1State<'_>>>,
+    State(data): State<Arc<ApiV1State>>,
     mut req: Request<Body>,
     next: Next,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
     let token = cookie_jar
         .get("token")
         .map(|cookie| cookie.value().to_string())
@@ -43,11 +36,7 @@ pub async fn auth(
         });
 
     let token = token.ok_or_else(|| {
-        let json_error = ErrorResponse {
-            status: "fail",
-            message: "You are not logged in, please provide token".to_string(),
-        };
-        (StatusCode::UNAUTHORIZED, Json(json_error))
+        ApiError::new(ErrorCode::NotLoggedIn, "You are not logged in, please provide token")
     })?;
 
     let claims = decode::<JwtUser>(
@@ -55,59 +44,30 @@ pub async fn auth(
         &DecodingKey::from_secret(data.env.jwt_secret.as_ref()),
         &Validation::default(),
     )
-    .map_err(|_| {
-        let json_error = ErrorResponse {
-            status: "fail",
-            message: "Invalid token".to_string(),
-        };
-        (StatusCode::UNAUTHORIZED, Json(json_error))
-    })?
+    .map_err(|_| ApiError::new(ErrorCode::InvalidToken, "Invalid token"))?
     .claims;
 
     let _ = uuid::Uuid::parse_str(&claims.id).map_err(|_| {
-        let json_error = ErrorResponse {
-            status: "fail",
-            message: "Invalid token".to_string(),
-        };
-        (StatusCode::UNAUTHORIZED, Json(json_error))
+        ApiError::new(ErrorCode::InvalidToken, "Invalid token")
     })?;
 
     let password_rev = uuid::Uuid::parse_str(&claims.password_rev).map_err(|_| {
-        let json_error = ErrorResponse {
-            status: "fail",
-            message: "Invalid token".to_string(),
-        };
-        (StatusCode::UNAUTHORIZED, Json(json_error))
+        ApiError::new(ErrorCode::InvalidToken, "Invalid token")
     })?;
 
-    let user = UserPDO::fetch_user_by_id(&data, &claims.id)
-    .await
-    .map_err(|err| {
-        let json_error = ErrorResponse {
-            status: "fail",
-            message: format!("Internal server error: {}", err),
-        };
-        (StatusCode::INTERNAL_SERVER_ERROR, Json(json_error))
-    })?;
+    let user = UserPDO::fetch_user_by_id(&data, &claims.id).await?;
 
 
     let user = user.ok_or_else(|| {
-        let json_error = ErrorResponse {
-            status: "fail",
-            message: "Invalid Token".to_string(),
-        };
-        (StatusCode::UNAUTHORIZED, Json(json_error))
+        ApiError::new(ErrorCode::InvalidToken, "Invalid Token")
     })?;
 
     // check password rev
     if user.password_rev != password_rev {
-        let json_error = ErrorResponse {
-            status: "fail",
-            message: "Invalid token".to_string(),
-        };
-        return Err((StatusCode::UNAUTHORIZED, Json(json_error)));
+        return Err(ApiError::new(ErrorCode::InvalidToken, "Invalid token"));
     }
 
+    set_current_user(&user.id.to_string());
     req.extensions_mut().insert(user);
     Ok(next.run(req).await)
 }
@@ -116,22 +76,14 @@ pub async fn auth(
 pub async fn is_admin(
     req: Request<Body>,
     next: Next,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<impl IntoResponse, ApiError> {
     let user = req.extensions().get::<User>().ok_or_else(|| {
-        let json_error = ErrorResponse {
-            status: "fail",
-            message: "You are not logged in, please provide token".to_string(),
-        };
-        (StatusCode::UNAUTHORIZED, Json(json_error))
+        ApiError::new(ErrorCode::NotLoggedIn, "You are not logged in, please provide token")
     })?;
 
     if !user.is_admin() {
-        let json_error = ErrorResponse {
-            status: "fail",
-            message: "You are not authorized to perform this action".to_string(),
-        };
-        return Err((StatusCode::FORBIDDEN, Json(json_error)));
+        return Err(ApiError::new(ErrorCode::Forbidden, "You are not authorized to perform this action"));
     }
 
     Ok(next.run(req).await)
-}
\ No newline at end of file
+}
@@ -1 +1,4 @@
-pub mod auth;
\ No newline at end of file
+pub mod auth;
+pub mod request_id;
+pub mod rate_limit;
+pub mod problem_details;
\ No newline at end of file
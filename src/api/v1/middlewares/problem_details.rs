@@ -0,0 +1,76 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::Request,
+    http::header,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::api::api_v1::error::{ApiError, ErrorCode};
+
+// plain text rejections are a line or two, anything bigger isn't one of them
+const MAX_REJECTION_BODY: usize = 16 * 1024;
+
+fn wants_problem_details(request: &Request) -> bool {
+    request.headers()
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|accept| accept.to_str().ok())
+        .any(|accept| accept.contains("application/problem+json"))
+}
+
+/// Error responses that weren't built from an `ApiError`: axum's extractor rejections, timeouts, body limits
+async fn rejection(response: Response) -> Result<ApiError, Response> {
+    let is_text = response.headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .map_or(true, |content_type| content_type.starts_with("text/plain"));
+    if !is_text {
+        return Err(response);
+    }
+
+    let (parts, body) = response.into_parts();
+    match to_bytes(body, MAX_REJECTION_BODY).await {
+        Ok(body) => {
+            let message = match String::from_utf8_lossy(&body).trim() {
+                "" => parts.status.canonical_reason().unwrap_or("Error").to_string(),
+                message => message.to_string(),
+            };
+            Ok(ApiError::new(ErrorCode::from_status(parts.status.as_u16()), message))
+        },
+        Err(_) => Err(Response::from_parts(parts, Body::empty())),
+    }
+}
+
+/// Puts every error in the api's envelope, or in an RFC 7807 `application/problem+json` body when the caller asks for it
+pub async fn problem_details(request: Request, next: Next) -> Response {
+    let problem = wants_problem_details(&request);
+    let instance = request.uri().path().to_string();
+    let response = next.run(request).await;
+
+    let status = response.status();
+    if !status.is_client_error() && !status.is_server_error() {
+        return response;
+    }
+
+    let (error, headers) = match response.extensions().get::<ApiError>().cloned() {
+        Some(_) if !problem => return response,
+        Some(error) => (error, response.headers().clone()),
+        None => {
+            let headers = response.headers().clone();
+            match rejection(response).await {
+                Ok(error) => (error, headers),
+                Err(response) => return response,
+            }
+        }
+    };
+
+    let mut rewritten = if problem { error.problem(&instance) } else { error.into_response() };
+    // keep what the handler or the layers added, like Retry-After or the rate limit headers
+    for (name, value) in headers.iter() {
+        if name != header::CONTENT_TYPE && name != header::CONTENT_LENGTH {
+            rewritten.headers_mut().append(name.clone(), value.clone());
+        }
+    }
+    rewritten
+}
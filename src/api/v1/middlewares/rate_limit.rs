@@ -0,0 +1,123 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use axum::{
+    extract::{ConnectInfo, MatchedPath, Request, State},
+    http::{header, HeaderName, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::api::api_v1::{error::{ApiError, ErrorCode}, models::rate_limit::Quota, services::rate_limit::{RateLimitDecision, RateLimiter}, ApiV1State};
+
+pub static API_KEY_HEADER: HeaderName = HeaderName::from_static("x-api-key");
+
+pub static RATE_LIMIT_HEADERS: [HeaderName; 4] = [
+    HeaderName::from_static("ratelimit-limit"),
+    HeaderName::from_static("ratelimit-remaining"),
+    HeaderName::from_static("ratelimit-reset"),
+    HeaderName::from_static("ratelimit-policy"),
+];
+
+/// User id of a validly signed token, the user itself is only checked by the auth middleware
+#[cfg(feature = "database")]
+fn token_user(state: &ApiV1State, request: &Request) -> Option<String> {
+    use axum_extra::extract::cookie::CookieJar;
+    use jsonwebtoken::{decode, DecodingKey, Validation};
+
+    use crate::api::api_v1::models::user::JwtUser;
+
+    let token = CookieJar::from_headers(request.headers())
+        .get("token")
+        .map(|cookie| cookie.value().to_string())
+        .or_else(|| {
+            request.headers()
+                .get(header::AUTHORIZATION)
+                .and_then(|auth_header| auth_header.to_str().ok())
+                .map(|auth_value| auth_value.trim_start_matches("Bearer").trim().to_string())
+        })?;
+
+    decode::<JwtUser>(&token, &DecodingKey::from_secret(state.env.jwt_secret.as_ref()), &Validation::default())
+        .ok()
+        .map(|token| token.claims.id)
+}
+
+fn client_address(state: &ApiV1State, request: &Request) -> String {
+    let forwarded = request.headers()
+        .get("x-forwarded-for")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| state.rate_limit_options.forwarded_client(value))
+        .map(|address| address.to_string());
+
+    forwarded
+        .or_else(|| request.extensions().get::<ConnectInfo<SocketAddr>>().map(|info| info.0.ip().to_string()))
+        .unwrap_or("unknown".to_string())
+}
+
+/// Who the request is counted against: a known api key, then a signed in user, then the caller's address
+async fn client(state: &ApiV1State, request: &Request) -> String {
+    if let Some(key) = request.headers().get(&API_KEY_HEADER).and_then(|key| key.to_str().ok()) {
+        let client = format!("api:{key}");
+        if RateLimiter::is_known_client(state, &client).await {
+            return client;
+        }
+    }
+
+    #[cfg(feature = "database")]
+    if let Some(user_id) = token_user(state, request) {
+        return format!("user:{user_id}");
+    }
+
+    format!("ip:{}", client_address(state, request))
+}
+
+fn set_rate_limit_headers(response: &mut Response, quota: Quota, decision: &RateLimitDecision) {
+    let [limit, remaining, reset, policy] = RATE_LIMIT_HEADERS.clone();
+    let headers = response.headers_mut();
+    headers.insert(limit, HeaderValue::from(quota.capacity));
+    headers.insert(remaining, HeaderValue::from(decision.remaining));
+    headers.insert(reset, HeaderValue::from(decision.reset.as_secs()));
+    if let Ok(value) = HeaderValue::from_str(&format!("{};w={}", quota.capacity, quota.period().as_secs())) {
+        headers.insert(policy, value);
+    }
+}
+
+/// Token bucket per client and route, shared through redis. Requests go through untouched while redis is down
+pub async fn rate_limit(State(state): State<Arc<ApiV1State>>, request: Request, next: Next) -> Response {
+    if !state.rate_limit_options.enabled {
+        return next.run(request).await;
+    }
+
+    let route = request.extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or("unmatched".to_string());
+    let client = client(&state, &request).await;
+
+    let quota = match RateLimiter::find_override(&state, &client, &route).await {
+        Some(quota) => quota,
+        None => state.rate_limit_options.quota(&route),
+    };
+    if quota.is_unlimited() {
+        return next.run(request).await;
+    }
+
+    let Some(decision) = RateLimiter::take(&state, &client, &route, quota).await else {
+        return next.run(request).await;
+    };
+
+    if !decision.allowed {
+        let retry_after = decision.retry_after.as_secs().max(1);
+        // api keys are secrets, keep them out of the logs
+        let shown_client = if client.starts_with("api:") { "api:<key>" } else { client.as_str() };
+        log::info!(client = shown_client, route = route.as_str(); "Rate limited, retry in {retry_after}s");
+
+        let mut response = ApiError::new(ErrorCode::RateLimited, format!("Too many requests, retry in {retry_after} seconds")).into_response();
+        set_rate_limit_headers(&mut response, quota, &decision);
+        response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+        return response;
+    }
+
+    let mut response = next.run(request).await;
+    set_rate_limit_headers(&mut response, quota, &decision);
+    response
+}
@@ -0,0 +1,99 @@
+use std::{future::Future, sync::{Arc, Mutex}, time::Instant};
+
+use axum::{
+    body::Body,
+    http::{HeaderName, HeaderValue, Request},
+    middleware::Next,
+    response::Response,
+};
+use tracing::Instrument;
+
+pub static REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+// inbound ids longer than this are replaced, they end up in every log line
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+/// What is known about the request handled by the current task, attached to every log line
+#[derive(Debug, Default)]
+pub struct RequestContext {
+    pub id: String,
+    route: Mutex<Option<String>>,
+    user_id: Mutex<Option<String>>,
+}
+
+impl RequestContext {
+    pub fn route(&self) -> Option<String> {
+        self.route.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+    }
+
+    pub fn user_id(&self) -> Option<String> {
+        self.user_id.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+    }
+}
+
+tokio::task_local! {
+    static REQUEST_CONTEXT: Arc<RequestContext>;
+}
+
+pub fn current_request() -> Option<Arc<RequestContext>> {
+    REQUEST_CONTEXT.try_with(Arc::clone).ok()
+}
+
+/// Id of the request handled by the current task, if any
+pub fn current_request_id() -> Option<String> {
+    REQUEST_CONTEXT.try_with(|context| context.id.clone()).ok()
+}
+
+/// Records the route template the request was matched to
+pub fn set_current_route(route: &str) {
+    let _ = REQUEST_CONTEXT.try_with(|context| {
+        *context.route.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(route.to_string());
+    });
+}
+
+/// Records the authenticated user making the request
+pub fn set_current_user(user_id: &str) {
+    let _ = REQUEST_CONTEXT.try_with(|context| {
+        *context.user_id.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(user_id.to_string());
+    });
+}
+
+/// Runs `future` with the current request context and span, for work spawned off a request
+pub fn propagate<F: Future>(future: F) -> impl Future<Output = F::Output> {
+    let context = current_request().unwrap_or_default();
+    REQUEST_CONTEXT.scope(context, future.in_current_span())
+}
+
+/// Tags every request with an id, reusing the caller's `X-Request-Id` when it sent a usable one
+pub async fn request_id(mut req: Request<Body>, next: Next) -> Response {
+    let id = req.headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|id| id.to_str().ok())
+        .filter(|id| !id.is_empty() && id.len() <= MAX_REQUEST_ID_LENGTH)
+        .map(|id| id.to_string())
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    let header = HeaderValue::from_str(&id).ok();
+    if let Some(header) = &header {
+        req.headers_mut().insert(REQUEST_ID_HEADER.clone(), header.clone());
+    }
+
+    let method = req.method().clone();
+    let path = req.uri().path().to_string();
+    let span = tracing::info_span!("request", request_id = %id, method = %method, path = %path);
+    let context = Arc::new(RequestContext { id, ..Default::default() });
+
+    let start = Instant::now();
+    let mut response = REQUEST_CONTEXT.scope(context, async {
+        let response = next.run(req).await;
+        let latency_ms = start.elapsed().as_millis() as u64;
+        let status = response.status().as_u16();
+        log::info!(latency_ms = latency_ms, status = status; "{method} {path} responded {status}");
+        response
+    }.instrument(span)).await;
+
+    if let Some(header) = header {
+        response.headers_mut().insert(REQUEST_ID_HEADER.clone(), header);
+    }
+    response
+}
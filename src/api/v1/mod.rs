@@ -2,26 +2,63 @@ pub mod models;
 pub mod middlewares;
 pub mod controllers;
 pub mod services;
+pub mod cache;
+pub mod metrics;
+pub mod error;
+pub mod openapi;
+pub mod routes;
 
 use std::{path::PathBuf, sync::Arc, time::Duration};
+use cache::{CacheOptions, TieredCache};
+#[cfg(feature = "tetrio")]
+use cache::{tetrio_cache_ttl, CacheNamespace};
+#[cfg(feature = "tetrio")]
 use common::LeagueRecordRequest;
+#[cfg(feature = "database")]
 use controllers::silly_command_controller::get_commands;
-use headless_chrome::{protocol::cdp::Page::CaptureScreenshotFormatOption, LaunchOptions};
+#[cfg(feature = "tetrio")]
+use models::leaderboard::LeaderboardEvent;
+#[cfg(feature = "tetrio")]
+use models::league_stats::{MatchStats, MatchStatsQuery};
+#[cfg(feature = "tetrio")]
+use models::tetra::{FullLeaderboardQuery, TetraData, TetraQuery, TetraTestParam};
+#[cfg(feature = "tetrio")]
+use services::league_stats::LeagueStats;
+use services::{rate_limit::RateLimitOptions, render::RenderError};
+#[cfg(feature = "tetrio")]
+use services::render::render_page;
+use middlewares::rate_limit::rate_limit;
+use middlewares::problem_details::problem_details;
+use metrics::track_requests;
+#[cfg(any(feature = "database", feature = "tetrio"))]
+use metrics::Metrics;
+#[cfg(feature = "tetrio")]
+use services::stale_cache::StaleCache;
+#[cfg(feature = "tetrio")]
+use services::tetrio::{TetrioClient, TetrioError};
+#[cfg(feature = "tetrio")]
+use error::ApiError;
+#[cfg(feature = "tetrio")]
+use services::leaderboard::{LeaderboardCrawler, LeaderboardCrawlerOptions, LeaderboardSnapshot};
+#[cfg(all(feature = "database", feature = "tetrio"))]
+use services::watchlist_poller::{WatchlistPoller, WatchlistPollerOptions};
+#[cfg(feature = "database")]
+use middlewares::auth::{auth, is_admin};
+#[cfg(feature = "tetrio")]
+use headless_chrome::protocol::cdp::Page::CaptureScreenshotFormatOption;
+use headless_chrome::LaunchOptions;
 
-use axum::{Router, response::IntoResponse, routing::{get, post}, extract::{State, Path, Query}, Json};
+use axum::{extract::DefaultBodyLimit, middleware, Router, response::IntoResponse, routing::MethodRouter};
+#[cfg(feature = "tetrio")]
+use axum::{extract::{State, Path, Query}, Json};
 use headless_chrome::Browser;
-use itertools::Itertools;
-use serde::{Serialize, Deserialize};
-use serde_json::json;
 #[cfg(feature = "database")]
 use sqlx::{postgres::PgPoolOptions, PgPool};
-use tetrio_api::{http::{ cached_client::CachedClient, caches::moka::MokaCache, clients::reqwest_client::{RedisReqwestClient, ReqwestClient}, parameters::{personal_user_records::{PersonalLeaderboard, PersonalRecordsQuery}, value_bound_query::{Prisecter, ValueBoundQuery}}}, models::{cache::Cache, packet::SuccessPacket}};
 #[cfg(feature = "tetrio")]
-use tetrio_api::models::packet::Packet;
-use tower_http::services::ServeDir;
-
-
+use tetrio_api::{http::{caches::moka::MokaCache, clients::reqwest_client::ReqwestClient}, models::packet::{Packet, SuccessPacket}};
+use tower_http::{services::ServeDir, timeout::TimeoutLayer};
 
+use crate::config::ConfigSource;
 
 use super::Error;
 
@@ -30,26 +67,10 @@ use super::Error;
 
 // use self::{services::{silly_command::SillyCommandPDO, users::UserPDO}, models::user::{FilteredUser, User, RegisterUserSchema, JwtUser, LoginUserSchema, UpdatePasswordSchema, UpdateUserData, UpdateUser, ForceUpdateUser, CreateUser}};
 
+#[cfg(feature = "tetrio")]
 type TetoResponse = Packet<Box<[u8]>>;
 
-#[derive(Serialize)]
-struct TetraData {
-    replay_id: Option<String>, 
-    buffer: Box<[u8]>
-}
-
-#[derive(Deserialize)]
-struct TetraQuery {
-    pub user_id: String,
-    pub game_num: u32
-}
-
-
-#[derive(Deserialize)]
-pub struct FullLeaderboardQuery {
-    pub country: Option<String>,
-}
-
+#[cfg(feature = "tetrio")]
 type TetraResponse = Packet<TetraData>;
 
 #[allow(dead_code)]
@@ -58,45 +79,230 @@ struct Env {
 }
 
 #[allow(dead_code)]
-pub struct ApiV1State<'a> {
+pub struct ApiV1State {
     // sql_connection: PgPool,
     #[cfg(feature = "tetrio")]
-    http_client: tetrio_api::http::clients::reqwest_client::RedisReqwestClient<'a>,
+    http_client: TetrioClient,
 
+    cache: TieredCache,
+    #[cfg(feature = "tetrio")]
+    leaderboard_options: LeaderboardCrawlerOptions,
+    #[cfg(feature = "tetrio")]
+    leaderboard_snapshots: moka::future::Cache<String, Arc<LeaderboardSnapshot>>,
+    #[cfg(feature = "tetrio")]
+    last_leaderboard: tokio::sync::RwLock<Option<Arc<LeaderboardSnapshot>>>,
+    #[cfg(feature = "tetrio")]
+    leaderboard_events: tokio::sync::broadcast::Sender<Arc<LeaderboardEvent>>,
     #[cfg(feature = "tetrio")]
     html_server_url: String,
     #[cfg(feature = "database")]
     sql_connection: PgPool,
+    #[cfg(all(feature = "database", feature = "tetrio"))]
+    watchlist_options: WatchlistPollerOptions,
+    #[cfg(all(feature = "database", feature = "tetrio"))]
+    webhook_client: reqwest::Client,
+    #[cfg(feature = "database")]
+    log_max_tail: usize,
+    browser_options: BrowserOptions,
+    health_check_timeout: Duration,
+    rate_limit_options: RateLimitOptions,
+    // crawlers and pollers spawned for this state, stopped by `close`
+    background_tasks: std::sync::Mutex<Vec<tokio::task::AbortHandle>>,
     env: Env
 }
 
-pub fn create_browser(width: u32, height: u32) -> Result<Browser, Error> {
+impl ApiV1State {
+    #[cfg_attr(not(feature = "tetrio"), allow(dead_code))]
+    fn spawn_background<F>(&self, task: F) where F: std::future::Future<Output = ()> + Send + 'static {
+        let handle = tokio::spawn(task).abort_handle();
+        self.background_tasks.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push(handle);
+    }
+
+    /// Stops the background tasks and closes the database and redis connections.
+    /// Browsers need nothing, headless_chrome kills chrome when a render drops its `Browser`.
+    pub async fn close(&self) {
+        for task in self.background_tasks.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).drain(..) {
+            task.abort();
+        }
+
+        #[cfg(feature = "database")]
+        self.sql_connection.close().await;
+        self.cache.close().await;
+    }
+}
+
+#[derive(Clone)]
+pub struct BrowserOptions {
+    /// Chrome binary to launch, headless_chrome looks for one when `None`
+    pub path: Option<PathBuf>,
+    /// Time a browser may stay without activity before it is closed
+    pub idle_timeout: Duration,
+    pub sandbox: bool,
+}
+
+impl BrowserOptions {
+    pub fn from_config(source: &mut ConfigSource) -> Self {
+        Self {
+            path: source.optional("BROWSER_PATH").map(PathBuf::from),
+            idle_timeout: Duration::from_secs(source.parse("BROWSER_IDLE_TIMEOUT_SECONDS", 15)),
+            sandbox: source.parse("BROWSER_SANDBOX", false),
+        }
+    }
+}
+
+#[cfg(feature = "database")]
+#[derive(Clone)]
+pub struct DatabaseOptions {
+    pub url: String,
+    pub max_connections: u32,
+    /// How long a query waits for a free connection before failing
+    pub acquire_timeout: Duration,
+}
+
+#[cfg(feature = "database")]
+impl DatabaseOptions {
+    pub fn from_config(source: &mut ConfigSource) -> Self {
+        Self {
+            url: source.required("DATABASE_URL"),
+            max_connections: source.parse("DATABASE_MAX_CONNECTIONS", 25),
+            acquire_timeout: Duration::from_secs(source.parse("DATABASE_ACQUIRE_TIMEOUT_SECONDS", 30)),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RouteLimits {
+    /// Largest body accepted by the json routes, the default for every route
+    pub json_body_limit: usize,
+    /// Time a regular request may take before answering 408, `None` for no limit
+    pub request_timeout: Option<Duration>,
+    /// Time a render may take, they launch chrome so they get much longer
+    pub render_timeout: Option<Duration>,
+}
+
+impl RouteLimits {
+    pub fn from_config(source: &mut ConfigSource) -> Self {
+        Self {
+            json_body_limit: source.parse("BODY_LIMIT_JSON_BYTES", 1024 * 1024),
+            request_timeout: source.seconds_or_disabled("REQUEST_TIMEOUT_SECONDS", 30),
+            render_timeout: source.seconds_or_disabled("RENDER_TIMEOUT_SECONDS", 90),
+        }
+    }
+}
+
+/// Applies `timeout` to a single route, streaming routes are left without one
+fn with_timeout<S>(route: MethodRouter<S>, timeout: Option<Duration>) -> MethodRouter<S> where S: Clone + Send + Sync + 'static {
+    match timeout {
+        Some(timeout) => route.layer(TimeoutLayer::new(timeout)),
+        None => route,
+    }
+}
+
+pub struct ApiV1Options {
+    pub redis_url: String,
+    pub jwt_secret: String,
+    #[cfg(feature = "tetrio")]
+    pub html_server_url: String,
+    #[cfg(feature = "database")]
+    pub database: DatabaseOptions,
+    pub browser: BrowserOptions,
+    pub cache: CacheOptions,
+    /// A dependency slower than this to answer is reported down by the readiness check
+    pub health_check_timeout: Duration,
+    pub limits: RouteLimits,
+    pub rate_limit: RateLimitOptions,
+    #[cfg(feature = "tetrio")]
+    pub leaderboard: LeaderboardCrawlerOptions,
+    #[cfg(all(feature = "database", feature = "tetrio"))]
+    pub watchlist: WatchlistPollerOptions,
+    /// Most log entries `/admin/logs` holds back for its `tail` parameter
+    #[cfg(feature = "database")]
+    pub log_max_tail: usize,
+}
+
+impl ApiV1Options {
+    pub fn from_config(source: &mut ConfigSource) -> Self {
+        // a config file shared with builds that have these features isn't an error
+        #[cfg(not(feature = "tetrio"))]
+        {
+            source.skip("HTML_SERVER_URL");
+            source.skip("LEADERBOARD_");
+        }
+        #[cfg(not(feature = "database"))]
+        {
+            source.skip("DATABASE_");
+            source.skip("LOG_MAX_TAIL");
+        }
+        #[cfg(not(all(feature = "database", feature = "tetrio")))]
+        source.skip("WATCHLIST_");
+
+        Self {
+            redis_url: source.required("REDIS_URL"),
+            jwt_secret: source.required("JWT_SECRET"),
+            #[cfg(feature = "tetrio")]
+            html_server_url: source.required("HTML_SERVER_URL"),
+            #[cfg(feature = "database")]
+            database: DatabaseOptions::from_config(source),
+            browser: BrowserOptions::from_config(source),
+            cache: CacheOptions::from_config(source),
+            health_check_timeout: Duration::from_secs(source.parse("HEALTH_CHECK_TIMEOUT_SECONDS", 5)),
+            limits: RouteLimits::from_config(source),
+            rate_limit: RateLimitOptions::from_config(source),
+            #[cfg(feature = "tetrio")]
+            leaderboard: LeaderboardCrawlerOptions::from_config(source),
+            #[cfg(all(feature = "database", feature = "tetrio"))]
+            watchlist: WatchlistPollerOptions::from_config(source),
+            #[cfg(feature = "database")]
+            log_max_tail: source.parse("LOG_MAX_TAIL", 10_000),
+        }
+    }
+}
+
+pub fn create_browser(options: &BrowserOptions, width: u32, height: u32) -> Result<Browser, RenderError> {
 
     let launch_options = LaunchOptions::default_builder()
     .headless(true)
-    // .path(Some("/var/www/taka_the_discord_bot/headless-chrome/chrome-linux/chrome".into()))
+    .path(options.path.clone())
     // // .fetcher_options(FetcherOptions::default().with_revision(browser_version))
     .window_size(Some((width, height)))
-    .sandbox(false)
-    .idle_browser_timeout(Duration::from_secs(15))
+    .sandbox(options.sandbox)
+    .idle_browser_timeout(options.idle_timeout)
     .build().map_err(|e| 
-        Error(format!("Couldn't create browser build options! {e}"))
+        RenderError::Launch(anyhow::anyhow!("Invalid browser options! {e}"))
     )?;
 
-    log::debug!("made browser configuration");
+    tracing::debug!("made browser configuration");
 
-    let browser = headless_chrome::Browser::new(launch_options).map_err(|e| 
-        Error(format!("Couldn't create browser! {e}"))
-    )?;
-    log::debug!("launched browser");
+    let browser = headless_chrome::Browser::new(launch_options).map_err(RenderError::Launch)?;
+    tracing::debug!("launched browser");
 
     Ok(browser)
 }
 
 
-pub async fn api_v1() -> Result<Router<()>, Error>{
+/// Tables of the features built in, created when missing
+#[cfg(feature = "database")]
+const TABLES: &[(&str, &str)] = &[
+    ("watchlist", include_str!("./sql/watchlists/create_tables.sql")),
+    #[cfg(feature = "tetrio")]
+    ("tetra league history", include_str!("./sql/tetra_history/create_tables.sql")),
+];
 
-    create_browser(200, 200)?;
+#[cfg(feature = "database")]
+async fn create_tables(sql_connection: &PgPool) -> Result<(), Error> {
+    for (name, statements) in TABLES {
+        sqlx::raw_sql(statements)
+            .execute(sql_connection)
+            .await
+            .map_err(|e| Error(format!("Couldn't create the {name} tables! {e}")))?;
+    }
+    Ok(())
+}
+
+/// Routes of the v1 api, along with the health routes sharing its state and the state itself, to close it on shutdown
+pub async fn api_v1(options: &ApiV1Options) -> Result<(Router<()>, Router<()>, Arc<ApiV1State>), Error>{
+
+    create_browser(&options.browser, 200, 200)?;
 
 
 
@@ -115,51 +321,131 @@ pub async fn api_v1() -> Result<Router<()>, Error>{
 
     // log::info!("Database working ! {row:?}");
 
-    let html_server_url = std::env::var("HTML_SERVER_URL").map_err(|e|
-       Error(format!("Couldn't get env variable HTML_SERVER_URL {e}"))
-    )?;
-
-    #[cfg(feature = "database")]
-
-    let sql_connection_url =
-        &std::env::var("DATABASE_URL").expect("Couldn't get DATABASE_URL");
     #[cfg(feature = "database")]
     let sql_connection = PgPoolOptions::new()
-        .max_connections(25)
-        .connect(sql_connection_url)
-        .await.expect("Couldn't initialize connection pool");
+        .max_connections(options.database.max_connections)
+        .acquire_timeout(options.database.acquire_timeout)
+        .connect(&options.database.url)
+        .await.map_err(|e| Error(format!("Couldn't initialize connection pool! {e}")))?;
+    #[cfg(feature = "database")]
+    create_tables(&sql_connection).await?;
+    #[cfg(feature = "database")]
+    Metrics::global().watch_sql_pool(sql_connection.clone());
+
+    let client = redis::Client::open(options.redis_url.as_str()).map_err(|e| Error(format!("Invalid REDIS_URL! {e}")))?;
 
-    let redis_url = std::env::var("REDIS_URL").expect("Couldn't get tetrio token");
-    let client = redis::Client::open(redis_url).expect("Couldn't open redis connection");
+    #[cfg(feature = "tetrio")]
+    let leaderboard_options = options.leaderboard.clone();
+    #[cfg(feature = "tetrio")]
+    let leaderboard_snapshots = moka::future::Cache::builder()
+        .max_capacity(4)
+        .time_to_live(leaderboard_options.cache_duration)
+        .build();
+    #[cfg(feature = "tetrio")]
+    let (leaderboard_events, _) = tokio::sync::broadcast::channel(16384);
 
     let state = Arc::new(ApiV1State{
         // sql_connection,
-        http_client: RedisReqwestClient::new(
-            ReqwestClient::default(),
-            tetrio_api::http::caches::redis_cache::RedisCache { client: std::borrow::Cow::Owned(client) }
-        ),
-        html_server_url,
+        // responses shared between instances are cached by `TetrioLookup`, the client itself never needs redis
+        #[cfg(feature = "tetrio")]
+        http_client: TetrioClient::new(ReqwestClient::default(), MokaCache::default()),
+        cache: TieredCache::new(client, options.cache.clone()),
+        #[cfg(feature = "tetrio")]
+        leaderboard_options,
+        #[cfg(feature = "tetrio")]
+        leaderboard_snapshots,
+        #[cfg(feature = "tetrio")]
+        last_leaderboard: tokio::sync::RwLock::new(None),
+        #[cfg(feature = "tetrio")]
+        leaderboard_events,
+        #[cfg(feature = "tetrio")]
+        html_server_url: options.html_server_url.clone(),
+        browser_options: options.browser.clone(),
+        health_check_timeout: options.health_check_timeout,
+        rate_limit_options: options.rate_limit.clone(),
+        background_tasks: std::sync::Mutex::new(vec![]),
         env: Env {
-            jwt_secret: std::env::var("JWT_SECRET").map_err(|e|
-                Error(format!("Couldn't get env variable JWT_SECRET {e}"))
-            )?
+            jwt_secret: options.jwt_secret.clone()
         },
-        sql_connection
+        #[cfg(feature = "database")]
+        sql_connection,
+        #[cfg(all(feature = "database", feature = "tetrio"))]
+        watchlist_options: options.watchlist.clone(),
+        #[cfg(all(feature = "database", feature = "tetrio"))]
+        webhook_client: reqwest::Client::new(),
+        #[cfg(feature = "database")]
+        log_max_tail: options.log_max_tail,
     });
 
+    #[cfg(feature = "tetrio")]
+    if let Some(interval) = state.leaderboard_options.refresh_interval {
+        state.spawn_background(LeaderboardCrawler::refresh_periodically(Arc::clone(&state), interval));
+    }
+
+    #[cfg(all(feature = "database", feature = "tetrio"))]
+    if let Some(interval) = state.watchlist_options.interval {
+        state.spawn_background(WatchlistPoller::run(Arc::clone(&state), interval));
+    }
+
     // let user = users::UserPDO::fetch_user_by_id(&state, "650caddd-b045-43d5-b691-dcc749e24b3c").await.expect("Couldn't find admin user").expect("Couldn't find admin user");
     // let token = encode_token(user.id, user.password_rev, state.env.jwt_secret.as_ref()).await.expect("Couldn't encode admin user token");
     // eprintln!("Bot token: {token}");
 
-    let api = 
-    Router::new()
-        .route("/", get(hello))
-        .route("/teto/:user", get(teto))
-        .route("/tetra", get(tetra))
-        .route("/tetra/replay", post(tetra_replay))
-        .route("/league_recent_test", get(league_recent_test))        
-        .route("/get_commands", get(get_commands))
-        .route("/full_leaderboard", get(full_leaderboard))
+    let limits = &options.limits;
+    let api = Router::new()
+        .route(routes::HELLO.path, with_timeout(routes::HELLO.on(hello), limits.request_timeout));
+
+    #[cfg(feature = "tetrio")]
+    // records render by default, head-to-head on request, like every render they get the render timeout
+    let api = api
+        .route(routes::TETO.path, with_timeout(routes::TETO.on(teto), limits.render_timeout))
+        .route(routes::RECORD.path, with_timeout(routes::RECORD.on(controllers::records_controller::record), limits.render_timeout))
+        .route(routes::TETRA.path, with_timeout(routes::TETRA.on(tetra), limits.render_timeout))
+        .route(routes::TETRA_REPLAY.path, with_timeout(routes::TETRA_REPLAY.on(tetra_replay), limits.render_timeout))
+        .route(routes::TETRA_STATS.path, with_timeout(routes::TETRA_STATS.on(tetra_stats), limits.request_timeout))
+        .route(routes::HEAD_TO_HEAD.path, with_timeout(routes::HEAD_TO_HEAD.on(controllers::head_to_head_controller::head_to_head), limits.render_timeout))
+        .route(routes::LEAGUE_RECENT_TEST.path, with_timeout(routes::LEAGUE_RECENT_TEST.on(league_recent_test), limits.render_timeout))
+        // crawling the whole leaderboard takes minutes, the events are a stream
+        .route(routes::FULL_LEADERBOARD.path, routes::FULL_LEADERBOARD.on(full_leaderboard))
+        .route(routes::PLAYER_RANK.path, with_timeout(routes::PLAYER_RANK.on(controllers::leaderboard_controller::player_rank), limits.request_timeout))
+        .route(routes::LEADERBOARD_EVENTS.path, routes::LEADERBOARD_EVENTS.on(controllers::leaderboard_controller::leaderboard_events));
+
+    // the cache, logs, rate limits and watchlists are managed by admins only
+    #[cfg(feature = "database")]
+    let admin = Router::new()
+        .route(routes::CACHE_NAMESPACES.path, with_timeout(routes::CACHE_NAMESPACES.on(controllers::cache_controller::list_namespaces), limits.request_timeout))
+        .route(routes::CACHE_KEY.path, with_timeout(routes::CACHE_KEY.on(controllers::cache_controller::key_info), limits.request_timeout))
+        .route(routes::PURGE_CACHE_KEY.path, with_timeout(routes::PURGE_CACHE_KEY.on(controllers::cache_controller::purge_key), limits.request_timeout))
+        .route(routes::PURGE_CACHE_USER.path, with_timeout(routes::PURGE_CACHE_USER.on(controllers::cache_controller::purge_user), limits.request_timeout))
+        // the logs are streamed for as long as the files take to read
+        .route(routes::LOGS.path, routes::LOGS.on(controllers::logs_controller::logs))
+        .route(routes::RATE_LIMITS.path, with_timeout(routes::RATE_LIMITS.on(controllers::rate_limit_controller::list_overrides), limits.request_timeout))
+        .route(routes::SET_RATE_LIMIT.path, with_timeout(routes::SET_RATE_LIMIT.on(controllers::rate_limit_controller::set_override), limits.request_timeout))
+        .route(routes::DELETE_RATE_LIMIT.path, with_timeout(routes::DELETE_RATE_LIMIT.on(controllers::rate_limit_controller::delete_override), limits.request_timeout))
+        .route(routes::PURGE_CACHE_NAMESPACE.path, with_timeout(routes::PURGE_CACHE_NAMESPACE.on(controllers::cache_controller::purge_namespace), limits.request_timeout))
+        .route(routes::WATCHLIST.path, with_timeout(routes::WATCHLIST.on(controllers::watchlist_controller::get_watchlist), limits.request_timeout))
+        .route(routes::CREATE_WATCHLIST.path, with_timeout(routes::CREATE_WATCHLIST.on(controllers::watchlist_controller::create_watchlist), limits.request_timeout))
+        .route(routes::DELETE_WATCHLIST.path, with_timeout(routes::DELETE_WATCHLIST.on(controllers::watchlist_controller::delete_watchlist), limits.request_timeout))
+        .route(routes::ADD_WATCHED_PLAYER.path, with_timeout(routes::ADD_WATCHED_PLAYER.on(controllers::watchlist_controller::add_player), limits.request_timeout))
+        .route(routes::REMOVE_WATCHED_PLAYER.path, with_timeout(routes::REMOVE_WATCHED_PLAYER.on(controllers::watchlist_controller::remove_player), limits.request_timeout))
+        .route_layer(middleware::from_fn(is_admin))
+        .route_layer(middleware::from_fn_with_state(state.clone(), auth));
+
+    #[cfg(feature = "database")]
+    let api = api
+        .route(routes::GET_COMMANDS.path, with_timeout(routes::GET_COMMANDS.on(get_commands), limits.request_timeout))
+        .merge(admin);
+
+    // history is recorded in the database from tetr.io data, and rendered on request
+    #[cfg(all(feature = "database", feature = "tetrio"))]
+    let api = api
+        .route(routes::TETRA_HISTORY.path, with_timeout(routes::TETRA_HISTORY.on(controllers::tetra_history_controller::tetra_history), limits.render_timeout));
+
+    let api = api
+        .route_layer(middleware::from_fn_with_state(state.clone(), rate_limit))
+        .route_layer(middleware::from_fn(track_requests))
+        .layer(DefaultBodyLimit::max(limits.json_body_limit))
+        .layer(middleware::from_fn(problem_details))
         .nest_service("/images", ServeDir::new(PathBuf::from("assets")))
         .with_state(Arc::clone(&state));
         // .route("/auth/register", post(register_user_handler))
@@ -276,186 +562,146 @@ pub async fn api_v1() -> Result<Router<()>, Error>{
         //         .route_layer(middleware::from_fn(is_admin))
         // );
 
-    Ok(api)
+    let health = Router::new()
+        .route(routes::LIVE.path, routes::LIVE.on(controllers::health_controller::live))
+        .route(routes::READY.path, routes::READY.on(controllers::health_controller::ready))
+        .with_state(Arc::clone(&state));
+
+    Ok((api, health, state))
 }
 
 
+#[utoipa::path(get, path = "/", responses((status = 200, description = "The api is up", body = String, content_type = "text/plain")))]
 async fn hello() -> impl IntoResponse {
     "Hello!"
 }
 
-pub async fn get_full_leaderboard(state: &ApiV1State<'_>, country: Option<String>) -> Result<Packet<Vec<serde_json::Value>>, Box<dyn std::error::Error>> {
-    let client = &state.http_client;
-    let url = format!("/BEAN_BLASTER_SERVER?country={country:?}");
-    let session_id = "X_TEST_BEAN_BLASTER";
-    match client.get_from_cache::<Vec<serde_json::Value>>(&url, Some(&session_id)).await? {
-        Some(packet) => return Ok(packet),
-        None => {}
-    };
-
-    let mut query = ValueBoundQuery::NotBound { limit: Some(100), country: country.clone(), };
-    let url = format!("users/by/{}", "league");
-    let result = client.make_tetrio_api_request::<serde_json::Value>(CachedClient::<ReqwestClient, MokaCache>::make_url(&url, &query.as_query_params()), Some(session_id)).await?;
-    let result = result.data.unwrap_or(json! ({ 
-      "entries": []
-    }));
-    let default_p = json!( Prisecter {pri: 0., sec: 0., ter: 0. } );
-    let entries = result.get("entries").ok_or("Couldn't fetch leaderboard!")?.as_array().ok_or("Couldn't fetch leaderboard!")?;
-    let p = entries.last().map(|entry| serde_json::from_value::<Prisecter>(entry.get("p").unwrap_or(&default_p).clone())).ok_or("Couldn't fetch leaderboard!")??;
-
-    let mut results = vec![];
-    results.push(entries.clone());
-
-    query = ValueBoundQuery::After { after: p, limit: Some(100), country: country.clone() };
-    loop {
-      
-      let session_id = "X_BEANBLASTER";
-      let url = format!("users/by/{}", "league");
-      let result = client.make_tetrio_api_request::<serde_json::Value>(CachedClient::<ReqwestClient, MokaCache>::make_url(&url, &query.as_query_params()), Some(session_id)).await?;
-      let result = result.data.unwrap_or(json! ({ 
-        "entries": []
-      }));
-      let default_p = json!( Prisecter {pri: 0., sec: 0., ter: 0. } );
-      let entries = result.get("entries").ok_or("Couldn't fetch leaderboard!")?.as_array().ok_or("Couldn't fetch leaderboard!")?;
-      
-      let p = entries.last().map(|entry| serde_json::from_value::<Prisecter>(entry.get("p").unwrap_or(&default_p).clone())).ok_or("Couldn't fetch leaderboard!")??;
-     
-      query = ValueBoundQuery::After { after: p, limit: Some(100), country: country.clone() };
-  
-      results.push(entries.clone());
-
-
-      if entries.len() != 100 {
-        break;
-      }
-    };
-
-
-    let result = Packet {
-        success: true,
-        error: None,
-        data: Some(results.into_iter().flatten().collect_vec()),
-        cache: Some(Cache::cached_for(Duration::from_secs(3600)))
-    };
-
-    let json = serde_json::to_string(&result)?;
-
-    state.http_client.cache_tetrio_api_result_if_not_present::<serde_json::Value>(url, Some(&session_id), &json).await?;
-
-    Ok(result)
-    
-}
-
-pub async fn full_leaderboard(State(state): State<Arc<ApiV1State<'_>>>, Query(query): Query<FullLeaderboardQuery>) -> impl IntoResponse {
-    let leaderboard = get_full_leaderboard(&state, query.country).await;
-
-
-    Json(
-    match leaderboard {
-        Err(err) => Packet {
-            cache: None,
-            data: None,
-            error: Some(tetrio_api::models::packet::Error { msg: err.to_string() }),
-            success: false,
-        },
-        Ok(result) => result
-    }).into_response()
+#[cfg(feature = "tetrio")]
+pub async fn get_full_leaderboard(state: &ApiV1State, country: Option<String>) -> Result<Packet<Vec<serde_json::Value>>, TetrioError> {
+    LeaderboardCrawler::fetch_full_leaderboard(state, country).await
 }
 
-#[derive(Deserialize)]
-pub struct TetraTestParam {
-    left_score: Option<u32>,
-    right_score: Option<u32>,
+#[cfg(feature = "tetrio")]
+#[utoipa::path(get, path = "/full_leaderboard", params(FullLeaderboardQuery), responses(
+    (status = 200, description = "Every entry of the Tetra League leaderboard", body = openapi::FullLeaderboardPacket),
+    (status = 502, description = "tetr.io didn't answer", body = openapi::ErrorBody),
+))]
+pub async fn full_leaderboard(State(state): State<Arc<ApiV1State>>, Query(query): Query<FullLeaderboardQuery>) -> Result<impl IntoResponse, ApiError> {
+    let leaderboard = get_full_leaderboard(&state, query.country).await?;
+
+    Ok(Json(leaderboard))
 }
 
-async fn take_tetra_league_screenshot_of_url(rounds: u64, url: String) -> Result<Vec<u8>, Error> {
-    log::debug!("made configuration");
+// a league game lasts at most 14 rounds, the window grows by one row per round
+#[cfg(feature = "tetrio")]
+const MAX_LEAGUE_ROUNDS: u64 = 14;
 
-        let browser = create_browser(
+#[cfg(feature = "tetrio")]
+async fn take_tetra_league_screenshot_of_url(options: &BrowserOptions, rounds: u64, url: String) -> Result<Vec<u8>, RenderError> {
+    let options = options.clone();
+    let rounds = rounds.clamp(1, MAX_LEAGUE_ROUNDS) as u32;
+    services::render::blocking(move || {
+        let browser = tracing::info_span!("launch").in_scope(|| create_browser(
+            &options,
             1185,
-            350 + 60 * (rounds - 1) as u32)?;
+            350 + 60 * (rounds - 1)))?;
+
+        let tab = browser.new_tab().map_err(RenderError::page("Couldn't create new tab!"))?;
 
-        log::debug!("launched browser");
-        let tab = browser.new_tab().map_err(|e| Error(format!("Couldn't create new tab! {e}")))?;
-        log::debug!("opened tab");
+        tracing::info_span!("navigate", url = %url).in_scope(|| {
+            tab.navigate_to(&url).map_err(RenderError::page("Couldn't load tetra league replay page!"))
+        })?;
 
-        tab.navigate_to(&url).map_err(|e| Error(format!("Couldn't load tetra league replay page! {e}")))?;
-        log::debug!("navigated to tab");
+        tracing::info_span!("wait_for_element", selector = "#multilog").in_scope(|| {
+            tab.wait_for_element("#multilog").map_err(RenderError::page("Couldn't find element to screenshot!"))
+        })?;
 
-        let _element = tab.wait_for_element("#multilog").map_err(|e| Error(format!("Couldn't find element to screenshot! {e}")))?;
-        log::debug!("waited for element");
-        let buffer =
-            tab.capture_screenshot(CaptureScreenshotFormatOption::Png, None, None, true).map_err(|e| Error(format!("Couldn't take screenshot! {e}")))?;
-        log::debug!("took screenshot");
+        let buffer = tracing::info_span!("capture").in_scope(|| {
+            tab.capture_screenshot(CaptureScreenshotFormatOption::Png, None, None, true).map_err(RenderError::page("Couldn't take screenshot!"))
+        })?;
 
-        tab.close(true).map_err(|e| Error(format!("Couldn't close tab {e}")))?;
+        tab.close(true).map_err(RenderError::page("Couldn't close tab"))?;
         Ok(buffer)
+    }).await
 }
 
-async fn take_tetra_league_test_screenshot(state: &ApiV1State<'_>, left_score: Option<u32>, right_score: Option<u32>) -> Result<TetraData, Error> {
+#[cfg(feature = "tetrio")]
+async fn take_tetra_league_test_screenshot(state: &ApiV1State, left_score: Option<u32>, right_score: Option<u32>) -> Result<TetraData, RenderError> {
     let buffer = {
         let left_score = left_score.unwrap_or(5);
         let right_score = right_score.unwrap_or(5);
         let max_score = std::cmp::max(left_score, right_score);
-        log::debug!("made configuration");
+        tracing::debug!("made configuration");
 
-        take_tetra_league_screenshot_of_url(max_score.into(), format!(
+        Metrics::global().observe_render("tetra_league_test", take_tetra_league_screenshot_of_url(&state.browser_options, max_score.into(), format!(
             "{}/league_recent_test?left_score={}&right_score={}",
             state.html_server_url, left_score, right_score 
-        )).await?
+        ))).await?
     };
 
     Ok(TetraData {
         replay_id: None,
-        buffer: buffer.into_boxed_slice()
+        buffer: buffer.into_boxed_slice(),
+        stats: None
     })
 }
 
-async fn take_tetra_replay_screenshot(state: &ApiV1State<'_>, data: common::LeagueRecordRequest) -> Result<TetraData, Error> {
-    if data.league_record.rounds.len() > 14 {
-        return Err(Error("Replay has more than 14 rounds".to_string()));
+#[cfg(feature = "tetrio")]
+async fn take_tetra_replay_screenshot(state: &ApiV1State, data: common::LeagueRecordRequest) -> Result<TetraData, TetrioError> {
+    if data.league_record.rounds.len() as u64 > MAX_LEAGUE_ROUNDS {
+        return Err(TetrioError::InvalidRequest("Replay has more than 14 rounds".to_string()));
     }
 
-    let Ok(obj_string) = serde_json::to_string(&data) else {
-        return Err(Error("Couldn't serialize data".to_string()));
-    };
+    let obj_string = serde_json::to_string(&data).map_err(RenderError::Serialize)?;
 
     let encoded = urlencoding::encode(&obj_string);
 
     let buffer = {
-        take_tetra_league_screenshot_of_url(data.league_record.rounds.len() as u64, format!("{}/league_replay_from_data?data={}", state.html_server_url, encoded)).await?        
+        Metrics::global().observe_render("tetra_replay", take_tetra_league_screenshot_of_url(&state.browser_options, data.league_record.rounds.len() as u64, format!("{}/league_replay_from_data?data={}", state.html_server_url, encoded))).await?
     };
 
     Ok(TetraData {
         replay_id: None,
-        buffer: buffer.into_boxed_slice()
+        buffer: buffer.into_boxed_slice(),
+        stats: None
     })
 
 }
 
 
-async fn take_tetra_screenshot(state: &ApiV1State<'_>, user: &str, game_num: u32) -> Result<TetraData, Error> {
-    let packet = state.http_client
-        .fetch_user_personal_league_records(&user, PersonalLeaderboard::Recent, PersonalRecordsQuery::None)
-        .await
-        .map_err(|e| Error(format!("Couldn't fetch tetra league game: {e}")))?;
+#[cfg(feature = "tetrio")]
+/// Screenshot of the `game_num`th most recent league game of `user`, with its stats when `stats` is set
+async fn take_tetra_screenshot(state: &Arc<ApiV1State>, user: &str, game_num: u32, stats: bool) -> Result<TetraData, TetrioError> {
+    let records = StaleCache::fetch_recent_league_records(state, user).await?;
 
-    let Some(data) = &packet.data else {
-        return Err(Error("User does not have tetra league records".to_string()))
+    let Some(entries) = records.packet.data else {
+        return Err(TetrioError::NotFound("User does not have tetra league records".to_string()))
     };
 
     let game_num = if game_num <= 0 { 1 } else { game_num };
 
-    let Some(record) = data.entries.get((game_num - 1) as usize) else {
-        return Err(Error("Tetra league game not found".to_string()))
+    let Some(record) = entries.get((game_num - 1) as usize) else {
+        return Err(TetrioError::NotFound("Tetra league game not found".to_string()))
+    };
+
+    // the record is already there, the stats don't need another request
+    let stats = match stats {
+        true => Some(LeagueStats::record_stats(record)?),
+        false => None
+    };
+
+    let rounds = match record.pointer("/results/rounds").and_then(|rounds| rounds.as_array()) {
+        Some(rounds) if !rounds.is_empty() => rounds.len(),
+        _ => return Err(TetrioError::Malformed("tetra league record rounds")),
     };
+    let replay_id = record.get("replayid").and_then(|id| id.as_str()).unwrap_or_default();
 
     let buffer = {
         let buffer = {
-            take_tetra_league_screenshot_of_url(record.results.rounds.len() as u64, format!(
+            Metrics::global().observe_render("tetra_league", take_tetra_league_screenshot_of_url(&state.browser_options, rounds as u64, format!(
                 "{}/league_replay?user_id={}&replay_id={}",
-                state.html_server_url, user, record.replayid
-            )).await?        
+                state.html_server_url, user, replay_id
+            ))).await?
         };
     
         buffer
@@ -463,188 +709,132 @@ async fn take_tetra_screenshot(state: &ApiV1State<'_>, user: &str, game_num: u32
 
     Ok(TetraData {
         replay_id: None,
-        buffer: buffer.into_boxed_slice()
+        buffer: buffer.into_boxed_slice(),
+        stats
     })
 }
 
-async fn tetra_replay(State(state): State<Arc<ApiV1State<'_>>>,
-    Json(payload): axum::extract::Json<LeagueRecordRequest>) -> impl IntoResponse {
-
-    match take_tetra_replay_screenshot(&state, payload).await {    
-        Ok(data) => Json(TetraResponse {
-            success: true,
-            data: Some(data),
-            cache: None,
-            error: None
-        }).into_response(),
-        Err(err) => Json(TetraResponse {
-            success: false,
-            data: None,
-            cache: None,
-            error: Some(tetrio_api::models::packet::Error {msg: err.0 })
-        }).into_response()
-    }
+#[cfg(feature = "tetrio")]
+#[utoipa::path(post, path = "/tetra/replay", request_body = openapi::LeagueRecordRequest, responses(
+    (status = 200, description = "Screenshot of the replay", body = openapi::TetraPacket),
+    (status = 400, description = "The replay has more than 14 rounds", body = openapi::ErrorBody),
+    (status = 500, description = "The screenshot couldn't be taken", body = openapi::ErrorBody),
+))]
+async fn tetra_replay(State(state): State<Arc<ApiV1State>>,
+    Json(payload): axum::extract::Json<LeagueRecordRequest>) -> Result<impl IntoResponse, ApiError> {
+    let data = take_tetra_replay_screenshot(&state, payload).await?;
+
+    Ok(Json(TetraResponse {
+        success: true,
+        data: Some(data),
+        cache: None,
+        error: None
+    }))
  }
 
-async fn tetra(State(state): State<Arc<ApiV1State<'_>>>,
- Query(query): Query<TetraQuery>) -> impl IntoResponse {
-    match take_tetra_screenshot(&state, &query.user_id, query.game_num).await {    
-        Ok(data) => Json(TetraResponse {
-            success: true,
-            data: Some(data),
-            cache: None,
-            error: None
-        }).into_response(),
-        Err(err) => Json(TetraResponse {
-            success: false,
-            data: None,
-            cache: None,
-            error: Some(tetrio_api::models::packet::Error {msg: err.0 })
-        }).into_response()
-    }
-
-    
+#[cfg(feature = "tetrio")]
+#[utoipa::path(get, path = "/tetra", params(TetraQuery), responses(
+    (status = 200, description = "Screenshot of a recent Tetra League game", body = openapi::TetraPacket),
+    (status = 404, description = "The user or the game doesn't exist", body = openapi::ErrorBody),
+    (status = 500, description = "The screenshot couldn't be taken", body = openapi::ErrorBody),
+))]
+async fn tetra(State(state): State<Arc<ApiV1State>>,
+ Query(query): Query<TetraQuery>) -> Result<impl IntoResponse, ApiError> {
+    let data = take_tetra_screenshot(&state, &query.user_id, query.game_num, query.stats.unwrap_or(false)).await?;
+
+    Ok(Json(TetraResponse {
+        success: true,
+        data: Some(data),
+        cache: None,
+        error: None
+    }))
 }
 
-async fn league_recent_test(State(state): State<Arc<ApiV1State<'_>>>,
- Query(query): Query<TetraTestParam>) -> impl IntoResponse {
-    let TetraTestParam { left_score, right_score } = query;
-    match take_tetra_league_test_screenshot(&state, left_score, right_score).await {    
-        Ok(data) => Json(TetraResponse {
-            success: true,
-            data: Some(data),
-            cache: None,
-            error: None
-        }).into_response(),
-        Err(err) => Json(TetraResponse {
-            success: true,
-            data: None,
-            cache: None,
-            error: Some(tetrio_api::models::packet::Error {msg: err.0 })
-        }).into_response()
-    }
+#[cfg(feature = "tetrio")]
+#[utoipa::path(get, path = "/tetra/stats", params(MatchStatsQuery), responses(
+    (status = 200, description = "Stats of a recent Tetra League game", body = openapi::MatchStatsPacket),
+    (status = 404, description = "The user or the game doesn't exist", body = openapi::ErrorBody),
+))]
+async fn tetra_stats(State(state): State<Arc<ApiV1State>>,
+ Query(query): Query<MatchStatsQuery>) -> Result<impl IntoResponse, ApiError> {
+    let data = LeagueStats::fetch_match_stats(&state, &query.user_id, query.game_num).await?;
+
+    Ok(Json(Packet::<MatchStats> {
+        success: true,
+        data: Some(data),
+        cache: None,
+        error: None
+    }))
 }
 
-async fn take_teto_screenshot(state: &ApiV1State<'_>, user: &str) -> Result<Vec<u8>, Error> {
-    log::debug!("made configuration");
-
-    let browser = create_browser(900, 500).map_err(|e| Error(format!("Couldn't create browser ! {e}")))?;
-
-    let tab = browser.new_tab().map_err(|e| Error(format!("Couldn't create new tab ! {e}")))?;
-
-    tab.set_transparent_background_color().map_err(|e| Error(format!("Couldn't set transparent background ! {e}")))?;
-
-    tab.navigate_to(&format!(
-        "{}/teto_test/{}",
-        state.html_server_url,
-        user.to_lowercase()
-    )).map_err(|e| Error(format!("Couldn't navigate to url ! {e}")))?;
-    log::debug!("navigated to tab");
-
-    tab.wait_until_navigated().map_err(|e| Error(format!("Couldn't wait for tab to finish navigating! {e}")))?;
-
-    tokio::time::sleep(Duration::from_millis(750)).await;
-
-    let element = tab.wait_for_element(".tetra_modal").map_err(|e| Error(format!("Couldn't find element to screenshot! {e}")))?;
-    log::debug!("waited for element");
+#[cfg(feature = "tetrio")]
+#[utoipa::path(get, path = "/league_recent_test", params(TetraTestParam), responses(
+    (status = 200, description = "Screenshot of the test page", body = openapi::TetraPacket),
+    (status = 500, description = "The screenshot couldn't be taken", body = openapi::ErrorBody),
+))]
+async fn league_recent_test(State(state): State<Arc<ApiV1State>>,
+ Query(query): Query<TetraTestParam>) -> Result<impl IntoResponse, ApiError> {
+    let TetraTestParam { left_score, right_score } = query;
+    let data = take_tetra_league_test_screenshot(&state, left_score, right_score).await?;
 
-    let viewport = element.get_box_model().map_err(|e| Error(format!("Couldn't find size of element ! {e}")))?;
-    let mut viewport = viewport.border_viewport();
-    viewport.x -= 16.0;
-    viewport.y -= 16.0;
-    viewport.width += 32.0;
-    viewport.height += 32.0;
+    Ok(Json(TetraResponse {
+        success: true,
+        data: Some(data),
+        cache: None,
+        error: None
+    }))
+}
 
-    let buffer = tab.capture_screenshot(
-        CaptureScreenshotFormatOption::Png,
-        None,
-        Some(viewport),
-        true,
-    ).map_err(|e| Error(format!("Couldn't take screenshot ! {e}")))?;
-    log::debug!("took screenshot");
-    Ok(buffer)
+#[cfg(feature = "tetrio")]
+async fn take_teto_screenshot(state: &ApiV1State, user: &str) -> Result<Vec<u8>, RenderError> {
+    let url = format!("{}/teto_test/{}", state.html_server_url, user.to_lowercase());
+    render_page(&state.browser_options, &url, (900, 500), ".tetra_modal").await
 }
 
 
-async fn teto(State(state): State<Arc<ApiV1State<'_>>>, Path(user): Path<String>) -> impl IntoResponse {
+#[cfg(feature = "tetrio")]
+#[utoipa::path(get, path = "/teto/{user}", params(("user" = String, Path, description = "tetr.io username or user id")), responses(
+    (status = 200, description = "PNG of the user's profile card", body = openapi::TetoPacket),
+    (status = 404, description = "The user doesn't exist", body = openapi::ErrorBody),
+    (status = 500, description = "The screenshot couldn't be taken", body = openapi::ErrorBody),
+))]
+async fn teto(State(state): State<Arc<ApiV1State>>, Path(user): Path<String>) -> Result<impl IntoResponse, ApiError> {
     let username = &user;
-    let url = format!("teto_image_cache/{user}");
-    if let Ok(Some(entry)) = state.http_client.get_from_cache::<Box<[u8]>>(&url, None).await {
-        return Json(entry).into_response()
+    if let Some(entry) = state.cache.get::<TetoResponse>(CacheNamespace::TetoImage, username).await {
+        return Ok(Json(entry).into_response())
     };
 
-    let user = match state.http_client.fetch_user_info(&username).await {
-        Ok(user) => user,
-        Err(err) => return Json(TetoResponse { 
-            cache: None,
-            data: None,
-            error: Some(tetrio_api::models::packet::Error {msg: format!("Couldn't fetch user from tetrio API! {err}")}),
-            success: false
-        }).into_response()
-    };
+    let user = StaleCache::fetch_user_info(&state, &username).await?;
 
-    if let None = user.data {
-        return Json(TetoResponse { 
-            cache: None,
-            data: None,
-            error: Some(tetrio_api::models::packet::Error {msg: format!("Couldn't find user")}),
-            success: false
-        }).into_response();
+    if user.packet.data.is_none() {
+        return Err(TetrioError::UserNotFound.into());
     }
 
 
 
 
-    let buffer = match take_teto_screenshot(&state, &username).await {
-        Ok(ok) => ok, 
-        Err(err) => return Json(TetoResponse { 
-            cache: None,
-            data: None,
-            error: Some(tetrio_api::models::packet::Error {msg: format!("Couldn't take teto screenshot {err}")}),
-            
-            success: false
-        }).into_response()
-    };
+    let buffer = Metrics::global().observe_render("teto", take_teto_screenshot(&state, &username)).await?;
 
 
-    let Some(cache) = &user.cache else {
-        return Json(TetoResponse { 
-            cache: None,
-            data: None,
-            error: Some(tetrio_api::models::packet::Error {msg: format!("Couldn't find user")}),
-            success: false
-        }).into_response();
+    let Some(cache) = user.packet.cache else {
+        return Err(TetrioError::Malformed("user cache information").into());
     };
     
     let entry = SuccessPacket {
     
         data: Some(buffer.into_boxed_slice()),
     
-        cache: cache.clone(),
+        cache,
     
         success: true
     
     };
 
-    let Ok(json) = serde_json::to_string(&entry) else {
-        return Json(TetoResponse { 
-            cache: None,
-            data: None,
-            error: Some(tetrio_api::models::packet::Error {msg: format!("Couldn't parse to json!")}),
-            success: false
-        }).into_response();
-    };
-    
-    let Ok(entry) = state.http_client.cache_tetrio_api_result_if_not_present::<serde_json::Value>(url, None, &json).await else {
-        return Json(TetoResponse { 
-            cache: None,
-            data: None,
-            error: Some(tetrio_api::models::packet::Error {msg: format!("Couldn't cache value!")}),
-            success: false
-        }).into_response();
-    };
+    // a screenshot of stale data isn't kept, the next request should pick up the refreshed user
+    let ttl = if user.stale { Some(Duration::ZERO) } else { tetrio_cache_ttl(&entry.cache) };
+    state.cache.set(CacheNamespace::TetoImage, username, &entry, ttl).await?;
 
-    return Json(entry).into_response()
+    return Ok(Json(entry).into_response())
         
     
 
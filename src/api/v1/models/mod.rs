@@ -1,2 +1 @@
-pub mod silly_command;
-pub mod user;
+pub use taka_the_discord_bot_api_models::*;
@@ -0,0 +1,282 @@
+use utoipa::{
+    openapi::{
+        security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme},
+        OpenApi as OpenApiDocument,
+    },
+    Modify, OpenApi, ToSchema,
+};
+#[cfg(feature = "tetrio")]
+use utoipa::openapi::{schema::{ArrayBuilder, ObjectBuilder}, RefOr, Schema};
+
+pub use super::error::{ErrorBody, ErrorCode};
+
+/// tetr.io's `Packet`, which the tetr.io routes answer with
+#[cfg(feature = "tetrio")]
+macro_rules! packet {
+    ($name:ident, $($data:tt)+) => {
+        #[derive(ToSchema)]
+        #[allow(dead_code)]
+        pub struct $name {
+            success: bool,
+            data: Option<$($data)+>,
+            /// tetr.io cache information, when the data comes from tetr.io
+            #[schema(value_type = Option<Object>)]
+            cache: Option<serde_json::Value>,
+            #[schema(value_type = Option<Object>)]
+            error: Option<serde_json::Value>,
+        }
+    };
+}
+
+/// `{"status": "success", "data": ...}`, which the admin and watchlist routes answer with
+#[cfg(feature = "database")]
+macro_rules! success {
+    ($name:ident, $($data:tt)+) => {
+        #[derive(ToSchema)]
+        #[allow(dead_code)]
+        pub struct $name {
+            /// Always `success`
+            status: String,
+            data: $($data)+,
+        }
+    };
+}
+
+#[cfg(feature = "tetrio")]
+packet!(TetoPacket, Vec<u8>);
+#[cfg(feature = "tetrio")]
+packet!(TetraPacket, super::models::tetra::TetraData);
+#[cfg(feature = "tetrio")]
+packet!(MatchStatsPacket, super::models::league_stats::MatchStats);
+#[cfg(feature = "tetrio")]
+packet!(HeadToHeadPacket, super::models::head_to_head::HeadToHead);
+#[cfg(feature = "tetrio")]
+packet!(PlayerRankPacket, super::models::leaderboard::PlayerRank);
+#[cfg(feature = "tetrio")]
+packet!(FullLeaderboardPacket, Vec<serde_json::Value>);
+#[cfg(all(feature = "database", feature = "tetrio"))]
+packet!(TetraHistoryPacket, super::models::tetra_history::TetraHistory);
+
+#[cfg(feature = "database")]
+success!(WatchlistSuccess, super::models::watchlist::WatchlistData);
+#[cfg(feature = "database")]
+success!(RateLimitOverrideSuccess, super::models::rate_limit::RateLimitOverride);
+#[cfg(feature = "database")]
+success!(CreatedWatchlistSuccess, super::models::watchlist::CreatedWatchlist);
+#[cfg(feature = "database")]
+success!(AnySuccess, serde_json::Value);
+
+/// `common::LeagueRecordRequest`, the shared crate doesn't depend on utoipa so its schema is written here
+#[cfg(feature = "tetrio")]
+pub struct LeagueRecordRequest;
+
+#[cfg(feature = "tetrio")]
+impl<'s> ToSchema<'s> for LeagueRecordRequest {
+    fn schema() -> (&'s str, RefOr<Schema>) {
+        let rounds = ArrayBuilder::new()
+            .items(RefOr::T(Schema::Object(ObjectBuilder::new().build())))
+            .max_items(Some(14))
+            .build();
+        let league_record = ObjectBuilder::new()
+            .description(Some("Tetra League record as tetr.io sends it, with at most 14 rounds"))
+            .property("rounds", RefOr::T(Schema::Array(rounds)))
+            .required("rounds")
+            .build();
+
+        ("LeagueRecordRequest", RefOr::T(Schema::Object(ObjectBuilder::new()
+            .property("league_record", RefOr::T(Schema::Object(league_record)))
+            .required("league_record")
+            .build())))
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Taka the discord bot API", description = "tetr.io renders and the discord bot's own data"),
+    servers((url = "/api/v1")),
+    paths(super::hello),
+    components(schemas(ErrorBody, ErrorCode)),
+)]
+struct CoreDoc;
+
+#[cfg(feature = "tetrio")]
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        super::teto,
+        super::tetra,
+        super::tetra_replay,
+        super::tetra_stats,
+        super::league_recent_test,
+        super::full_leaderboard,
+        super::controllers::records_controller::record,
+        super::controllers::head_to_head_controller::head_to_head,
+        super::controllers::leaderboard_controller::player_rank,
+        super::controllers::leaderboard_controller::leaderboard_events,
+    ),
+    components(schemas(
+        TetoPacket, TetraPacket, MatchStatsPacket, HeadToHeadPacket, PlayerRankPacket, FullLeaderboardPacket,
+        LeagueRecordRequest,
+        super::models::tetra::TetraData,
+        super::models::league_stats::MatchStats,
+        super::models::league_stats::PlayerMatchStats,
+        super::models::league_stats::DerivedStats,
+        super::models::records::RecordCard,
+        super::models::records::RecordStats,
+        super::models::records::RecordMode,
+        super::models::head_to_head::HeadToHead,
+        super::models::head_to_head::HeadToHeadPlayer,
+        super::models::head_to_head::HeadToHeadMatch,
+        super::models::leaderboard::PlayerRank,
+        super::models::leaderboard::RankSource,
+        super::models::leaderboard::LeaderboardNeighbour,
+        super::models::leaderboard::LeaderboardEvent,
+        super::models::leaderboard::LeaderboardEventPlayer,
+    )),
+)]
+struct TetrioDoc;
+
+#[cfg(feature = "database")]
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        super::controllers::silly_command_controller::get_commands,
+        super::controllers::cache_controller::list_namespaces,
+        super::controllers::cache_controller::key_info,
+        super::controllers::cache_controller::purge_key,
+        super::controllers::cache_controller::purge_user,
+        super::controllers::cache_controller::purge_namespace,
+        super::controllers::logs_controller::logs,
+        super::controllers::rate_limit_controller::list_overrides,
+        super::controllers::rate_limit_controller::set_override,
+        super::controllers::rate_limit_controller::delete_override,
+        super::controllers::watchlist_controller::get_watchlist,
+        super::controllers::watchlist_controller::create_watchlist,
+        super::controllers::watchlist_controller::delete_watchlist,
+        super::controllers::watchlist_controller::add_player,
+        super::controllers::watchlist_controller::remove_player,
+    ),
+    components(schemas(
+        WatchlistSuccess, CreatedWatchlistSuccess, RateLimitOverrideSuccess, AnySuccess,
+        super::models::cache::CacheKeyRequest,
+        super::models::cache::PurgeUserRequest,
+        super::models::cache::PurgeNamespaceRequest,
+        super::models::rate_limit::Quota,
+        super::models::rate_limit::RateLimitOverride,
+        super::models::rate_limit::DeleteRateLimitOverrideRequest,
+        super::models::watchlist::Watchlist,
+        super::models::watchlist::WatchedPlayer,
+        super::models::watchlist::WatchlistData,
+        super::models::watchlist::CreateWatchlistRequest,
+        super::models::watchlist::CreatedWatchlist,
+        super::models::watchlist::DeleteWatchlistRequest,
+        super::models::watchlist::WatchedPlayerRequest,
+        // the silly command and user routes are disabled, their bodies are still documented for the bot
+        super::models::silly_command::SillyCommandData,
+        super::models::silly_command::SillyCommandType,
+        super::models::silly_command::AddCommandRequest,
+        super::models::silly_command::AddTextRequest,
+        super::models::silly_command::AddTextAuthorRequest,
+        super::models::silly_command::AddPreferenceRequest,
+        super::models::user::FilteredUser,
+        super::models::user::RegisterUserSchema,
+        super::models::user::LoginUserSchema,
+        super::models::user::UpdatePasswordSchema,
+        super::models::user::UpdateUser,
+        super::models::user::UpdateUserData,
+        super::models::user::CreateUser,
+        super::models::user::ForceUpdateUser,
+    )),
+    modifiers(&SecuritySchemes),
+)]
+struct DatabaseDoc;
+
+#[cfg(all(feature = "database", feature = "tetrio"))]
+#[derive(OpenApi)]
+#[openapi(
+    paths(super::controllers::tetra_history_controller::tetra_history),
+    components(schemas(
+        TetraHistoryPacket,
+        super::models::tetra_history::TetraHistory,
+        super::models::tetra_history::TetraHistoryPoint,
+    )),
+)]
+struct HistoryDoc;
+
+/// The routes behind `auth` take the JWT as a bearer token or as the `token` cookie
+#[cfg_attr(not(feature = "database"), allow(dead_code))]
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut OpenApiDocument) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme("bearer", SecurityScheme::Http(
+            HttpBuilder::new().scheme(HttpAuthScheme::Bearer).bearer_format("JWT").build()
+        ));
+        components.add_security_scheme("cookie", SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::new("token"))));
+    }
+}
+
+/// Document of the routes built with the enabled features, served at `/api/v1/openapi.json`
+pub fn openapi() -> OpenApiDocument {
+    #[allow(unused_mut)]
+    let mut openapi = CoreDoc::openapi();
+
+    #[cfg(feature = "tetrio")]
+    openapi.merge(TetrioDoc::openapi());
+
+    #[cfg(feature = "database")]
+    {
+        let mut database = DatabaseDoc::openapi();
+        SecuritySchemes.modify(&mut database);
+        openapi.merge(database);
+    }
+
+    #[cfg(all(feature = "database", feature = "tetrio"))]
+    openapi.merge(HistoryDoc::openapi());
+
+    openapi
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use crate::api::api_v1::routes::{HEALTH_ROUTES, ROUTES};
+
+    fn documented_routes() -> BTreeSet<(String, String)> {
+        let openapi = serde_json::to_value(super::openapi()).expect("the document serializes");
+        let paths = openapi["paths"].as_object().expect("the document has paths");
+
+        paths.iter()
+            .flat_map(|(path, item)| {
+                item.as_object().into_iter().flatten()
+                    .filter(|(method, _)| ["get", "post", "put", "patch", "delete"].contains(&method.as_str()))
+                    .map(move |(method, _)| (method.clone(), path.clone()))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn every_route_is_documented() {
+        let routes = ROUTES.iter()
+            .map(|route| (route.method.name().to_string(), route.openapi_path()))
+            .collect::<BTreeSet<_>>();
+        let documented = documented_routes();
+
+        let undocumented = routes.difference(&documented).collect::<Vec<_>>();
+        let unrouted = documented.difference(&routes).collect::<Vec<_>>();
+
+        assert!(undocumented.is_empty(), "routes missing from the OpenAPI document: {undocumented:?}");
+        assert!(unrouted.is_empty(), "documented routes the router doesn't serve: {unrouted:?}");
+    }
+
+    #[test]
+    fn health_routes_are_not_documented() {
+        let documented = documented_routes();
+
+        for route in HEALTH_ROUTES {
+            assert!(!documented.contains(&(route.method.name().to_string(), route.openapi_path())), "{route:?} is documented");
+        }
+    }
+}
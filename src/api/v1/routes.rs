@@ -0,0 +1,200 @@
+use axum::{handler::Handler, routing::{get, post, MethodRouter}};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RouteMethod {
+    Get,
+    Post,
+}
+
+impl RouteMethod {
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Get => "get",
+            Self::Post => "post",
+        }
+    }
+}
+
+/// Method and path of a route. The router mounts its handlers from these, so the tests know what is served
+#[derive(Debug, Clone, Copy)]
+pub struct Route {
+    pub method: RouteMethod,
+    pub path: &'static str,
+}
+
+impl Route {
+    const fn get(path: &'static str) -> Self {
+        Self { method: RouteMethod::Get, path }
+    }
+
+    #[cfg_attr(not(any(feature = "tetrio", feature = "database")), allow(dead_code))]
+    const fn post(path: &'static str) -> Self {
+        Self { method: RouteMethod::Post, path }
+    }
+
+    /// `handler` answering the route's method
+    pub fn on<H, T, S>(&self, handler: H) -> MethodRouter<S>
+    where
+        H: Handler<T, S>,
+        T: 'static,
+        S: Clone + Send + Sync + 'static
+    {
+        match self.method {
+            RouteMethod::Get => get(handler),
+            RouteMethod::Post => post(handler),
+        }
+    }
+
+    /// The path as OpenAPI writes it, axum's `:param` being `{param}`
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn openapi_path(&self) -> String {
+        self.path.split('/')
+            .map(|segment| match segment.strip_prefix(':') {
+                Some(param) => format!("{{{param}}}"),
+                None => segment.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+pub const HELLO: Route = Route::get("/");
+
+#[cfg(feature = "tetrio")]
+pub const TETO: Route = Route::get("/teto/:user");
+#[cfg(feature = "tetrio")]
+pub const RECORD: Route = Route::get("/records/:user/:mode");
+#[cfg(feature = "tetrio")]
+pub const TETRA: Route = Route::get("/tetra");
+#[cfg(feature = "tetrio")]
+pub const TETRA_REPLAY: Route = Route::post("/tetra/replay");
+#[cfg(feature = "tetrio")]
+pub const TETRA_STATS: Route = Route::get("/tetra/stats");
+#[cfg(feature = "tetrio")]
+pub const HEAD_TO_HEAD: Route = Route::get("/tetra/h2h");
+#[cfg(feature = "tetrio")]
+pub const LEAGUE_RECENT_TEST: Route = Route::get("/league_recent_test");
+#[cfg(feature = "tetrio")]
+pub const FULL_LEADERBOARD: Route = Route::get("/full_leaderboard");
+#[cfg(feature = "tetrio")]
+pub const PLAYER_RANK: Route = Route::get("/leaderboard/rank/:user");
+#[cfg(feature = "tetrio")]
+pub const LEADERBOARD_EVENTS: Route = Route::get("/leaderboard/events");
+
+#[cfg(feature = "database")]
+pub const GET_COMMANDS: Route = Route::get("/get_commands");
+#[cfg(feature = "database")]
+pub const CACHE_NAMESPACES: Route = Route::get("/admin/cache");
+#[cfg(feature = "database")]
+pub const CACHE_KEY: Route = Route::get("/admin/cache/key");
+#[cfg(feature = "database")]
+pub const PURGE_CACHE_KEY: Route = Route::post("/admin/cache/purge_key");
+#[cfg(feature = "database")]
+pub const PURGE_CACHE_USER: Route = Route::post("/admin/cache/purge_user");
+#[cfg(feature = "database")]
+pub const PURGE_CACHE_NAMESPACE: Route = Route::post("/admin/cache/purge_namespace");
+#[cfg(feature = "database")]
+pub const LOGS: Route = Route::get("/admin/logs");
+#[cfg(feature = "database")]
+pub const RATE_LIMITS: Route = Route::get("/admin/rate_limits");
+#[cfg(feature = "database")]
+pub const SET_RATE_LIMIT: Route = Route::post("/admin/rate_limits/set");
+#[cfg(feature = "database")]
+pub const DELETE_RATE_LIMIT: Route = Route::post("/admin/rate_limits/delete");
+#[cfg(feature = "database")]
+pub const WATCHLIST: Route = Route::get("/watchlists/:guild_id");
+#[cfg(feature = "database")]
+pub const CREATE_WATCHLIST: Route = Route::post("/watchlists/create_watchlist");
+#[cfg(feature = "database")]
+pub const DELETE_WATCHLIST: Route = Route::post("/watchlists/delete_watchlist");
+#[cfg(feature = "database")]
+pub const ADD_WATCHED_PLAYER: Route = Route::post("/watchlists/add_player");
+#[cfg(feature = "database")]
+pub const REMOVE_WATCHED_PLAYER: Route = Route::post("/watchlists/remove_player");
+
+#[cfg(all(feature = "database", feature = "tetrio"))]
+pub const TETRA_HISTORY: Route = Route::get("/tetra/history/:user");
+
+pub const LIVE: Route = Route::get("/live");
+pub const READY: Route = Route::get("/ready");
+
+/// Every route mounted under `/api/v1` with the enabled features
+#[cfg_attr(not(test), allow(dead_code))]
+pub const ROUTES: &[Route] = &[
+    HELLO,
+    #[cfg(feature = "tetrio")] TETO,
+    #[cfg(feature = "tetrio")] RECORD,
+    #[cfg(feature = "tetrio")] TETRA,
+    #[cfg(feature = "tetrio")] TETRA_REPLAY,
+    #[cfg(feature = "tetrio")] TETRA_STATS,
+    #[cfg(feature = "tetrio")] HEAD_TO_HEAD,
+    #[cfg(feature = "tetrio")] LEAGUE_RECENT_TEST,
+    #[cfg(feature = "tetrio")] FULL_LEADERBOARD,
+    #[cfg(feature = "tetrio")] PLAYER_RANK,
+    #[cfg(feature = "tetrio")] LEADERBOARD_EVENTS,
+    #[cfg(feature = "database")] GET_COMMANDS,
+    #[cfg(feature = "database")] CACHE_NAMESPACES,
+    #[cfg(feature = "database")] CACHE_KEY,
+    #[cfg(feature = "database")] PURGE_CACHE_KEY,
+    #[cfg(feature = "database")] PURGE_CACHE_USER,
+    #[cfg(feature = "database")] PURGE_CACHE_NAMESPACE,
+    #[cfg(feature = "database")] LOGS,
+    #[cfg(feature = "database")] RATE_LIMITS,
+    #[cfg(feature = "database")] SET_RATE_LIMIT,
+    #[cfg(feature = "database")] DELETE_RATE_LIMIT,
+    #[cfg(feature = "database")] WATCHLIST,
+    #[cfg(feature = "database")] CREATE_WATCHLIST,
+    #[cfg(feature = "database")] DELETE_WATCHLIST,
+    #[cfg(feature = "database")] ADD_WATCHED_PLAYER,
+    #[cfg(feature = "database")] REMOVE_WATCHED_PLAYER,
+    #[cfg(all(feature = "database", feature = "tetrio"))] TETRA_HISTORY,
+];
+
+/// Routes mounted under `/health`, outside of the api and its OpenAPI document
+#[cfg_attr(not(test), allow(dead_code))]
+pub const HEALTH_ROUTES: &[Route] = &[LIVE, READY];
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::{Route, ROUTES};
+
+    fn mounted(path: &str) -> bool {
+        ROUTES.iter().any(|route| route.path == path)
+    }
+
+    #[test]
+    fn tetrio_routes_follow_the_feature() {
+        for path in ["/teto/:user", "/tetra", "/records/:user/:mode", "/leaderboard/events"] {
+            assert_eq!(mounted(path), cfg!(feature = "tetrio"), "{path}");
+        }
+    }
+
+    #[test]
+    fn database_routes_follow_the_feature() {
+        for path in ["/get_commands", "/admin/cache", "/admin/logs", "/watchlists/:guild_id"] {
+            assert_eq!(mounted(path), cfg!(feature = "database"), "{path}");
+        }
+    }
+
+    #[test]
+    fn history_needs_both_features() {
+        assert_eq!(mounted("/tetra/history/:user"), cfg!(all(feature = "database", feature = "tetrio")));
+    }
+
+    #[test]
+    fn routes_are_mounted_once() {
+        let mut seen = HashSet::new();
+        for route in ROUTES {
+            assert!(seen.insert((route.method, route.path)), "{route:?} is mounted twice");
+        }
+    }
+
+    #[test]
+    fn openapi_paths_use_braces() {
+        assert_eq!(Route::get("/records/:user/:mode").openapi_path(), "/records/{user}/{mode}");
+        assert_eq!(Route::get("/").openapi_path(), "/");
+    }
+}
@@ -0,0 +1,154 @@
+#![cfg(feature = "tetrio")]
+
+use std::collections::HashSet;
+
+use tetrio_api::http::parameters::personal_user_records::PersonalLeaderboard;
+
+use crate::api::api_v1::{metrics::Metrics, models::head_to_head::{HeadToHead, HeadToHeadMatch, HeadToHeadPlayer}, services::{league_records::{LeagueRecords, PAGE_SIZE}, render::{render_page, RenderError}, tetrio::TetrioError}, ApiV1State};
+
+// how many pages of recent games are scanned for encounters
+const MAX_RECENT_PAGES: usize = 5;
+
+// matches drawn on the card, the url carrying them would get too long otherwise
+const MAX_RENDERED_MATCHES: usize = 50;
+
+/// Running sums used to average a player's stats over the matches
+#[derive(Default)]
+struct StatTotals {
+    apm: f64,
+    pps: f64,
+    vs: f64,
+    games: usize,
+}
+
+impl StatTotals {
+    fn add(&mut self, entry: &serde_json::Value) {
+        let stat = |key: &str| entry.pointer(&format!("/stats/{key}")).and_then(|value| value.as_f64()).unwrap_or_default();
+        self.apm += stat("apm");
+        self.pps += stat("pps");
+        self.vs += stat("vsscore");
+        self.games += 1;
+    }
+
+    fn apply(&self, player: &mut HeadToHeadPlayer) {
+        if self.games == 0 {
+            return;
+        }
+        let games = self.games as f64;
+        player.average_apm = Some(self.apm / games);
+        player.average_pps = Some(self.pps / games);
+        player.average_vs = Some(self.vs / games);
+    }
+}
+
+pub struct HeadToHeadService;
+
+impl HeadToHeadService {
+    fn is_player(entry: &serde_json::Value, name: &str) -> bool {
+        let matches = |key: &str| entry.get(key)
+            .and_then(|value| value.as_str())
+            .is_some_and(|value| value.eq_ignore_ascii_case(name));
+        matches("username") || matches("id")
+    }
+
+    /// Games of `user` found in recent and top records, without duplicates
+    async fn fetch_records(context: &ApiV1State, user: &str) -> Result<Vec<serde_json::Value>, TetrioError> {
+        let mut records = LeagueRecords::fetch_page(context, user, PersonalLeaderboard::Top, None).await?;
+
+        let mut before = None;
+        for _ in 0..MAX_RECENT_PAGES {
+            let page = LeagueRecords::fetch_page(context, user, PersonalLeaderboard::Recent, before).await?;
+            if page.len() != PAGE_SIZE {
+                records.extend(page);
+                break;
+            }
+
+            before = page.last().map(LeagueRecords::cursor).transpose()?;
+            records.extend(page);
+        }
+
+        let mut seen = HashSet::new();
+        records.retain(|record| {
+            record.get("replayid")
+                .and_then(|id| id.as_str())
+                .is_some_and(|id| seen.insert(id.to_string()))
+        });
+
+        Ok(records)
+    }
+
+    pub async fn fetch_head_to_head(context: &ApiV1State, user: &str, opponent: &str, render: bool) -> Result<HeadToHead, TetrioError> {
+        let user = user.to_lowercase();
+        let opponent = opponent.to_lowercase();
+        if user == opponent {
+            return Err(TetrioError::InvalidRequest("A player can't face themselves".to_string()));
+        }
+
+        let records = Self::fetch_records(context, &user).await?;
+
+        let mut user_player = HeadToHeadPlayer { username: user.clone(), ..Default::default() };
+        let mut opponent_player = HeadToHeadPlayer { username: opponent.clone(), ..Default::default() };
+        let mut user_totals = StatTotals::default();
+        let mut opponent_totals = StatTotals::default();
+        let mut matches = vec![];
+
+        for record in &records {
+            let Some(leaderboard) = record.pointer("/results/leaderboard").and_then(|leaderboard| leaderboard.as_array()) else {
+                continue;
+            };
+            let user_entry = leaderboard.iter().find(|entry| Self::is_player(entry, &user));
+            let opponent_entry = leaderboard.iter().find(|entry| Self::is_player(entry, &opponent));
+            let (Some(user_entry), Some(opponent_entry)) = (user_entry, opponent_entry) else {
+                continue;
+            };
+
+            let wins = |entry: &serde_json::Value| entry.get("wins").and_then(|wins| wins.as_u64()).unwrap_or_default();
+            let user_score = wins(user_entry);
+            let opponent_score = wins(opponent_entry);
+
+            let id = |entry: &serde_json::Value| entry.get("id").and_then(|id| id.as_str()).map(|id| id.to_string());
+            user_player.user_id = user_player.user_id.take().or(id(user_entry));
+            opponent_player.user_id = opponent_player.user_id.take().or(id(opponent_entry));
+
+            if user_score > opponent_score {
+                user_player.wins += 1;
+            } else if opponent_score > user_score {
+                opponent_player.wins += 1;
+            }
+            user_player.rounds_won += user_score;
+            opponent_player.rounds_won += opponent_score;
+            user_totals.add(user_entry);
+            opponent_totals.add(opponent_entry);
+
+            matches.push(HeadToHeadMatch {
+                replay_id: record.get("replayid").and_then(|id| id.as_str()).unwrap_or_default().to_string(),
+                played_at: record.get("ts").and_then(|ts| ts.as_str()).map(|ts| ts.to_string()),
+                user_score,
+                opponent_score,
+            });
+        }
+
+        user_totals.apply(&mut user_player);
+        opponent_totals.apply(&mut opponent_player);
+
+        let mut head_to_head = HeadToHead { user: user_player, opponent: opponent_player, matches, buffer: None };
+
+        // without shared matches the card shows that the players haven't met recently
+        if render {
+            head_to_head.buffer = Some(Metrics::global().observe_render("head_to_head", Self::take_head_to_head_screenshot(context, &head_to_head)).await?.into_boxed_slice());
+        }
+
+        Ok(head_to_head)
+    }
+
+    async fn take_head_to_head_screenshot(context: &ApiV1State, head_to_head: &HeadToHead) -> Result<Vec<u8>, RenderError> {
+        let obj_string = serde_json::to_string(&serde_json::json!({
+            "user": head_to_head.user,
+            "opponent": head_to_head.opponent,
+            "matches": &head_to_head.matches[..head_to_head.matches.len().min(MAX_RENDERED_MATCHES)],
+        })).map_err(RenderError::Serialize)?;
+
+        let url = format!("{}/tetra_h2h?data={}", context.html_server_url, urlencoding::encode(&obj_string));
+        render_page(&context.browser_options, &url, (900, 500), ".h2h_card").await
+    }
+}
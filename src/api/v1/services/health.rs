@@ -0,0 +1,92 @@
+use std::{collections::BTreeMap, future::Future, time::{Duration, Instant}};
+
+use crate::api::{api_v1::{create_browser, models::health::{ComponentHealth, ComponentStatus, Readiness}, ApiV1State, BrowserOptions}, Error};
+
+#[cfg(feature = "tetrio")]
+const TETRIO_STATUS_URL: &str = "https://ch.tetr.io/api/general/stats";
+
+pub struct HealthService;
+
+impl HealthService {
+    async fn check(timeout: Duration, check: impl Future<Output = Result<(), Error>>) -> ComponentHealth {
+        let start = Instant::now();
+        let result = match tokio::time::timeout(timeout, check).await {
+            Ok(result) => result,
+            Err(_) => Err(Error(format!("Timed out after {timeout:?}"))),
+        };
+
+        ComponentHealth {
+            status: if result.is_ok() { ComponentStatus::Up } else { ComponentStatus::Down },
+            latency_ms: start.elapsed().as_millis() as u64,
+            error: result.err().map(|Error(message)| message),
+        }
+    }
+
+    #[cfg(feature = "database")]
+    async fn check_database(context: &ApiV1State) -> Result<(), Error> {
+        sqlx::query("SELECT 1")
+            .execute(&context.sql_connection)
+            .await
+            .map_err(|e| Error(format!("Couldn't query database! {e}")))?;
+        Ok(())
+    }
+
+    async fn check_browser(options: BrowserOptions) -> Result<(), Error> {
+        // launching chrome blocks, keep it off the runtime thread
+        tokio::task::spawn_blocking(move || {
+            let browser = create_browser(&options, 200, 200)?;
+            let tab = browser.new_tab().map_err(|e| Error(format!("Couldn't create new tab! {e}")))?;
+            tab.close(true).map_err(|e| Error(format!("Couldn't close tab {e}")))?;
+            Ok(())
+        })
+        .await
+        .map_err(|e| Error(format!("Browser check panicked! {e}")))?
+    }
+
+    async fn check_url(url: &str, timeout: Duration) -> Result<(), Error> {
+        let response = reqwest::Client::new()
+            .get(url)
+            .timeout(timeout)
+            .send()
+            .await
+            .map_err(|e| Error(format!("Couldn't reach {url}! {e}")))?;
+
+        if response.status().is_server_error() {
+            return Err(Error(format!("{url} answered {}", response.status())));
+        }
+        Ok(())
+    }
+
+    pub async fn readiness(context: &ApiV1State) -> Readiness {
+        let timeout = context.health_check_timeout;
+        let mut components = BTreeMap::new();
+
+        let (redis, browser) = tokio::join!(
+            Self::check(timeout, context.cache.ping()),
+            Self::check(timeout, Self::check_browser(context.browser_options.clone())),
+        );
+        components.insert("redis".to_string(), redis);
+        components.insert("browser".to_string(), browser);
+
+        #[cfg(feature = "database")]
+        components.insert("database".to_string(), Self::check(timeout, Self::check_database(context)).await);
+
+        #[cfg(feature = "tetrio")]
+        {
+            let (html_server, tetrio) = tokio::join!(
+                Self::check(timeout, Self::check_url(&context.html_server_url, timeout)),
+                Self::check(timeout, Self::check_url(TETRIO_STATUS_URL, timeout)),
+            );
+            components.insert("html_server".to_string(), html_server);
+            components.insert("tetrio".to_string(), tetrio);
+        }
+
+        let status = if components.values().all(|component| component.status == ComponentStatus::Up) {
+            ComponentStatus::Up
+        } else {
+            ComponentStatus::Down
+        };
+
+        Readiness { status, components }
+    }
+}
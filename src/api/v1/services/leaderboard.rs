@@ -0,0 +1,785 @@
+#![cfg(feature = "tetrio")]
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tetrio_api::{http::parameters::value_bound_query::{Prisecter, ValueBoundQuery}, models::{cache::Cache, packet::Packet}};
+
+use crate::{api::{api_v1::{cache::{now_millis, tetrio_cache_ttl, CacheNamespace, TieredCache}, metrics::Metrics, models::leaderboard::{LeaderboardEvent, LeaderboardEventPlayer, LeaderboardNeighbour, PlayerRank, RankSource}, services::tetrio::{TetrioClient, TetrioError, TetrioLookup}, ApiV1State}, Error}, config::ConfigSource};
+
+// tetr.io returns at most 100 entries per page
+const PAGE_SIZE: usize = 100;
+
+// how long an interrupted crawl can be resumed before starting over
+const CHECKPOINT_TTL: u64 = 60 * 60 * 6;
+
+// how long a crawl keeps its lock without progressing, so a crashed crawl doesn't block the leaderboard forever
+const CRAWL_LOCK_TTL: Duration = Duration::from_secs(120);
+
+// how often a request waiting for another crawl checks whether it finished
+const CRAWL_LOCK_POLL: Duration = Duration::from_secs(2);
+
+// longest wait between two retries of a page, however many retries are configured
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(300);
+
+const RELEASE_LOCK_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("DEL", KEYS[1])
+end
+return 0
+"#;
+
+const EXTEND_LOCK_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("PEXPIRE", KEYS[1], ARGV[2])
+end
+return 0
+"#;
+
+#[derive(Clone)]
+pub struct LeaderboardCrawlerOptions {
+    /// Pause between two page requests, to stay under tetr.io rate limits
+    pub request_delay: Duration,
+    /// Number of times a single page is retried before the crawl is aborted
+    pub max_retries: u32,
+    /// Base delay of the exponential backoff between retries of a page
+    pub retry_backoff: Duration,
+    /// How long a finished snapshot is served from the cache
+    pub cache_duration: Duration,
+    /// Refresh the global leaderboard in the background so change events keep flowing without requests
+    pub refresh_interval: Option<Duration>,
+    /// Rank ups and downs smaller than this many places aren't published at all
+    pub event_min_change: usize,
+}
+
+impl Default for LeaderboardCrawlerOptions {
+    fn default() -> Self {
+        Self {
+            request_delay: Duration::from_millis(1000),
+            max_retries: 5,
+            retry_backoff: Duration::from_millis(2000),
+            cache_duration: Duration::from_secs(3600),
+            refresh_interval: None,
+            event_min_change: 1,
+        }
+    }
+}
+
+impl LeaderboardCrawlerOptions {
+    pub fn from_config(source: &mut ConfigSource) -> Self {
+        let default = Self::default();
+        Self {
+            request_delay: Duration::from_millis(source.parse("LEADERBOARD_REQUEST_DELAY_MS", default.request_delay.as_millis() as u64)),
+            max_retries: source.parse("LEADERBOARD_MAX_RETRIES", default.max_retries),
+            retry_backoff: Duration::from_millis(source.parse("LEADERBOARD_RETRY_BACKOFF_MS", default.retry_backoff.as_millis() as u64)),
+            cache_duration: Duration::from_secs(source.parse("LEADERBOARD_CACHE_SECONDS", default.cache_duration.as_secs())),
+            refresh_interval: source.seconds_or_disabled("LEADERBOARD_REFRESH_INTERVAL_SECONDS", 0),
+            event_min_change: source.parse("LEADERBOARD_EVENT_MIN_CHANGE", default.event_min_change),
+        }
+    }
+}
+
+/// Progress of a crawl, persisted after every page so an aborted crawl can pick up where it stopped.
+/// The pages themselves are kept in a separate redis list to avoid rewriting the whole ladder on every page.
+#[derive(Serialize, Deserialize)]
+struct CrawlCheckpoint {
+    session_id: String,
+    after: Option<serde_json::Value>,
+}
+
+/// Exponential backoff before retrying a page, capped at `MAX_RETRY_BACKOFF`
+fn retry_backoff(base: Duration, attempt: u32) -> Duration {
+    base.saturating_mul(2_u32.saturating_pow(attempt)).min(MAX_RETRY_BACKOFF)
+}
+
+/// Redis lock on the crawl of one leaderboard, so two crawls never append pages to the same checkpoint
+enum CrawlLock {
+    Held { key: String, token: String },
+    /// Redis is down, there is no checkpoint to protect either
+    Unavailable,
+}
+
+impl CrawlLock {
+    fn key(cache_key: &str) -> String {
+        TieredCache::key(CacheNamespace::Leaderboard, &format!("{cache_key}/lock"))
+    }
+
+    /// Takes the lock of `cache_key`, `None` while another crawl holds it
+    async fn acquire(context: &ApiV1State, cache_key: &str) -> Result<Option<Self>, Error> {
+        let Some(mut connection) = context.cache.redis_connection().await else {
+            return Ok(Some(Self::Unavailable));
+        };
+
+        let key = Self::key(cache_key);
+        let token = uuid::Uuid::new_v4().to_string();
+        let acquired: Option<String> = redis::cmd("SET")
+            .arg(&key)
+            .arg(&token)
+            .arg("NX")
+            .arg("PX")
+            .arg(CRAWL_LOCK_TTL.as_millis() as u64)
+            .query_async(&mut connection)
+            .await
+            .map_err(|e| Error(format!("Couldn't lock leaderboard crawl! {e}")))?;
+
+        Ok(acquired.map(|_| Self::Held { key, token }))
+    }
+
+    /// Keeps the lock for `ttl` more, called whenever the crawl progresses or waits
+    async fn extend(&self, context: &ApiV1State, ttl: Duration) {
+        let Self::Held { key, token } = self else {
+            return;
+        };
+        let Some(mut connection) = context.cache.redis_connection().await else {
+            return;
+        };
+
+        let result = redis::Script::new(EXTEND_LOCK_SCRIPT)
+            .key(key)
+            .arg(token)
+            .arg(ttl.as_millis() as u64)
+            .invoke_async::<_, i64>(&mut connection)
+            .await;
+        if let Err(e) = result {
+            log::warn!("Couldn't extend leaderboard crawl lock {key}: {e}");
+        }
+    }
+
+    /// Gives the lock back, unless it expired and another crawl took it meanwhile
+    async fn release(self, context: &ApiV1State) {
+        let Self::Held { key, token } = self else {
+            return;
+        };
+        let Some(mut connection) = context.cache.redis_connection().await else {
+            return;
+        };
+
+        let result = redis::Script::new(RELEASE_LOCK_SCRIPT)
+            .key(&key)
+            .arg(token)
+            .invoke_async::<_, i64>(&mut connection)
+            .await;
+        if let Err(e) = result {
+            log::warn!("Couldn't release leaderboard crawl lock {key}: {e}");
+        }
+    }
+}
+
+fn entry_str(entry: &serde_json::Value, key: &str) -> Option<String> {
+    entry.get(key).and_then(|value| value.as_str()).map(|value| value.to_string())
+}
+
+/// Full leaderboard indexed by user id and username so single players can be looked up without a scan
+pub struct LeaderboardSnapshot {
+    pub entries: Vec<serde_json::Value>,
+    /// Milliseconds since the unix epoch when the crawl finished
+    pub fetched_at: u64,
+    by_user_id: HashMap<String, usize>,
+    by_username: HashMap<String, usize>,
+    country_ranks: Vec<Option<usize>>,
+}
+
+impl LeaderboardSnapshot {
+    pub fn from_entries(entries: Vec<serde_json::Value>, fetched_at: u64) -> Self {
+        let mut by_user_id = HashMap::with_capacity(entries.len());
+        let mut by_username = HashMap::with_capacity(entries.len());
+        let mut country_counts = HashMap::<String, usize>::new();
+        let mut country_ranks = Vec::with_capacity(entries.len());
+
+        for (index, entry) in entries.iter().enumerate() {
+            if let Some(user_id) = entry_str(entry, "_id") {
+                by_user_id.insert(user_id, index);
+            }
+            if let Some(username) = entry_str(entry, "username") {
+                by_username.insert(username.to_lowercase(), index);
+            }
+
+            let country_rank = entry_str(entry, "country").map(|country| {
+                let count = country_counts.entry(country.to_uppercase()).or_default();
+                *count += 1;
+                *count
+            });
+            country_ranks.push(country_rank);
+        }
+
+        Self { entries, fetched_at, by_user_id, by_username, country_ranks }
+    }
+
+    /// Whether the snapshot is older than `max_age`, however long it has been kept in memory
+    pub fn is_stale(&self, max_age: Duration) -> bool {
+        now_millis().saturating_sub(self.fetched_at) >= max_age.as_millis() as u64
+    }
+
+    /// Index of a player in the leaderboard, by user id or username
+    pub fn position(&self, user: &str) -> Option<usize> {
+        self.by_user_id.get(user)
+            .or_else(|| self.by_username.get(&user.to_lowercase()))
+            .copied()
+    }
+
+    pub fn neighbour(&self, index: usize) -> Option<LeaderboardNeighbour> {
+        let entry = self.entries.get(index)?;
+        Some(LeaderboardNeighbour {
+            rank: index + 1,
+            user_id: entry_str(entry, "_id"),
+            username: entry_str(entry, "username"),
+            entry: entry.clone()
+        })
+    }
+
+    fn event_player(entry: &serde_json::Value) -> Option<LeaderboardEventPlayer> {
+        Some(LeaderboardEventPlayer {
+            user_id: entry_str(entry, "_id")?,
+            username: entry_str(entry, "username"),
+            country: entry_str(entry, "country"),
+        })
+    }
+
+    fn rank_letter(entry: &serde_json::Value) -> Option<String> {
+        entry.get("league").and_then(|league| entry_str(league, "rank"))
+    }
+
+    /// Whether the player's own Tetra League stats changed. Players only pushed around by the games of others didn't move.
+    fn played(previous: &serde_json::Value, current: &serde_json::Value) -> bool {
+        let stats = |entry: &serde_json::Value| entry.get("league")
+            .map(|league| (league.get("gamesplayed").cloned(), league.get("tr").cloned()))
+            .filter(|stats| *stats != (None, None));
+
+        match (stats(previous), stats(current)) {
+            (Some(previous), Some(current)) => previous != current,
+            // without stats to compare, every change of position counts
+            _ => true,
+        }
+    }
+
+    /// Events describing how the ladder moved from `self` to `current`. Only players who played move up or down,
+    /// and only by at least `min_change` places.
+    pub fn diff(&self, current: &LeaderboardSnapshot, min_change: usize) -> Vec<LeaderboardEvent> {
+        let mut events = vec![];
+
+        for (index, entry) in current.entries.iter().enumerate() {
+            let Some(player) = Self::event_player(entry) else {
+                continue;
+            };
+            let rank = index + 1;
+
+            let Some(&previous_index) = self.by_user_id.get(&player.user_id) else {
+                events.push(LeaderboardEvent::NewEntrant { player, rank });
+                continue;
+            };
+            let previous = &self.entries[previous_index];
+            let previous_rank = previous_index + 1;
+
+            if let (Some(from), Some(to)) = (Self::rank_letter(previous), Self::rank_letter(entry)) {
+                if from != to {
+                    events.push(LeaderboardEvent::RankBoundary { player: player.clone(), from, to });
+                }
+            }
+
+            if !Self::played(previous, entry) || rank.abs_diff(previous_rank) < min_change.max(1) {
+                continue;
+            }
+
+            if rank < previous_rank {
+                events.push(LeaderboardEvent::RankUp { player, from: previous_rank, to: rank });
+            } else {
+                events.push(LeaderboardEvent::RankDown { player, from: previous_rank, to: rank });
+            }
+        }
+
+        for (index, entry) in self.entries.iter().enumerate() {
+            let Some(player) = Self::event_player(entry) else {
+                continue;
+            };
+            if !current.by_user_id.contains_key(&player.user_id) {
+                events.push(LeaderboardEvent::Dropped { player, rank: index + 1 });
+            }
+        }
+
+        events
+    }
+
+    pub fn player_rank(&self, index: usize) -> Option<PlayerRank> {
+        let entry = self.entries.get(index)?;
+        let global_rank = index + 1;
+
+        Some(PlayerRank {
+            user_id: entry_str(entry, "_id"),
+            username: entry_str(entry, "username"),
+            country: entry_str(entry, "country"),
+            global_rank: Some(global_rank),
+            country_rank: self.country_ranks[index],
+            percentile: Some(global_rank as f64 / self.entries.len() as f64 * 100.0),
+            above: index.checked_sub(1).and_then(|index| self.neighbour(index)),
+            below: self.neighbour(index + 1),
+            source: RankSource::Snapshot
+        })
+    }
+}
+
+pub struct LeaderboardCrawler;
+
+impl LeaderboardCrawler {
+    /// Uppercases the country code and treats an empty one as the global leaderboard
+    pub fn normalize_country(country: Option<&str>) -> Option<String> {
+        country
+            .map(|country| country.trim().to_uppercase())
+            .filter(|country| !country.is_empty())
+    }
+
+    pub fn cache_key(country: Option<&str>) -> String {
+        match Self::normalize_country(country) {
+            Some(country) => format!("full_leaderboard/country/{country}"),
+            None => "full_leaderboard/global".to_string()
+        }
+    }
+
+    fn checkpoint_key(cache_key: &str) -> String {
+        TieredCache::key(CacheNamespace::Leaderboard, &format!("{cache_key}/checkpoint"))
+    }
+
+    fn checkpoint_pages_key(cache_key: &str) -> String {
+        TieredCache::key(CacheNamespace::Leaderboard, &format!("{cache_key}/checkpoint/pages"))
+    }
+
+    pub async fn fetch_full_leaderboard(context: &ApiV1State, country: Option<String>) -> Result<Packet<Vec<serde_json::Value>>, TetrioError> {
+        let country = Self::normalize_country(country.as_deref());
+        let cache_key = Self::cache_key(country.as_deref());
+
+        if let Some(packet) = context.cache.get::<Packet<Vec<serde_json::Value>>>(CacheNamespace::Leaderboard, &cache_key).await {
+            return Ok(packet);
+        }
+
+        let lock = loop {
+            if let Some(lock) = CrawlLock::acquire(context, &cache_key).await? {
+                break lock;
+            }
+
+            // another crawl of this leaderboard is running, serve its result once it's cached
+            tokio::time::sleep(CRAWL_LOCK_POLL).await;
+            if let Some(packet) = context.cache.get::<Packet<Vec<serde_json::Value>>>(CacheNamespace::Leaderboard, &cache_key).await {
+                return Ok(packet);
+            }
+        };
+
+        let result = Self::crawl_and_store(context, &cache_key, country, &lock).await;
+        lock.release(context).await;
+        result
+    }
+
+    async fn crawl_and_store(context: &ApiV1State, cache_key: &str, country: Option<String>, lock: &CrawlLock) -> Result<Packet<Vec<serde_json::Value>>, TetrioError> {
+        // the crawl holding the lock before may have finished while this one was waiting for it
+        if let Some(packet) = context.cache.get::<Packet<Vec<serde_json::Value>>>(CacheNamespace::Leaderboard, cache_key).await {
+            return Ok(packet);
+        }
+
+        let entries = Self::crawl(context, cache_key, country.clone(), lock).await?;
+
+        let result = Packet {
+            success: true,
+            error: None,
+            data: Some(entries),
+            cache: Some(Cache::cached_for(context.leaderboard_options.cache_duration))
+        };
+
+        context.cache.set(CacheNamespace::Leaderboard, cache_key, &result, Some(context.leaderboard_options.cache_duration)).await?;
+
+        if let Err(Error(message)) = Self::clear_checkpoint(context, cache_key).await {
+            log::warn!("{message}");
+        }
+
+        if country.is_none() {
+            if let Some(entries) = &result.data {
+                Self::publish_snapshot(context, Arc::new(LeaderboardSnapshot::from_entries(entries.clone(), now_millis()))).await;
+            }
+        } else {
+            context.leaderboard_snapshots.invalidate(cache_key).await;
+        }
+
+        Ok(result)
+    }
+
+    /// Makes `snapshot` the current global leaderboard and broadcasts what changed since the previous one
+    async fn publish_snapshot(context: &ApiV1State, snapshot: Arc<LeaderboardSnapshot>) {
+        context.leaderboard_snapshots.insert(Self::cache_key(None), Arc::clone(&snapshot)).await;
+
+        let previous = context.last_leaderboard.write().await.replace(Arc::clone(&snapshot));
+        let Some(previous) = previous else {
+            return;
+        };
+
+        let events = previous.diff(&snapshot, context.leaderboard_options.event_min_change);
+        log::info!("Leaderboard refreshed, publishing {} events", events.len());
+        for event in events {
+            // sending only fails when nobody is listening
+            let _ = context.leaderboard_events.send(Arc::new(event));
+        }
+    }
+
+    /// Keeps the global leaderboard fresh so `/leaderboard/events` subscribers get updates without anyone polling
+    pub async fn refresh_periodically(context: Arc<ApiV1State>, interval: Duration) {
+        let mut interval = tokio::time::interval(interval);
+        loop {
+            interval.tick().await;
+            if let Err(err) = Self::fetch_full_leaderboard(&context, None).await {
+                log::error!("Couldn't refresh leaderboard: {err}");
+            }
+        }
+    }
+
+    /// Global leaderboard as currently cached, without crawling tetr.io when it has expired.
+    /// A snapshot older than the cache duration is never returned, even when it's still in a cache.
+    pub async fn cached_snapshot(context: &ApiV1State) -> Result<Option<Arc<LeaderboardSnapshot>>, Error> {
+        let cache_key = Self::cache_key(None);
+        let max_age = context.leaderboard_options.cache_duration;
+
+        if let Some(snapshot) = context.leaderboard_snapshots.get(&cache_key).await {
+            if !snapshot.is_stale(max_age) {
+                return Ok(Some(snapshot));
+            }
+            context.leaderboard_snapshots.invalidate(&cache_key).await;
+        }
+
+        let packet = context.cache.get::<Packet<Vec<serde_json::Value>>>(CacheNamespace::Leaderboard, &cache_key).await;
+
+        let Some(packet) = packet else {
+            return Ok(None);
+        };
+        let fetched_at = Self::fetched_at(&packet, max_age);
+        let Some(entries) = packet.data else {
+            return Ok(None);
+        };
+
+        let snapshot = Arc::new(LeaderboardSnapshot::from_entries(entries, fetched_at));
+        if snapshot.is_stale(max_age) {
+            return Ok(None);
+        }
+        context.leaderboard_snapshots.insert(cache_key, Arc::clone(&snapshot)).await;
+
+        // seed the change feed so the next refresh after a restart can be diffed
+        let mut last_leaderboard = context.last_leaderboard.write().await;
+        if last_leaderboard.is_none() {
+            *last_leaderboard = Some(Arc::clone(&snapshot));
+        }
+
+        Ok(Some(snapshot))
+    }
+
+    /// When a cached leaderboard was crawled, worked out from how long its packet is still cached for
+    fn fetched_at(packet: &Packet<Vec<serde_json::Value>>, cache_duration: Duration) -> u64 {
+        let remaining = packet.cache.as_ref().and_then(tetrio_cache_ttl).unwrap_or(cache_duration);
+        now_millis().saturating_sub(cache_duration.saturating_sub(remaining).as_millis() as u64)
+    }
+
+    pub async fn fetch_player_rank(context: &ApiV1State, user: &str) -> Result<PlayerRank, TetrioError> {
+        if let Some(snapshot) = Self::cached_snapshot(context).await? {
+            if let Some(rank) = snapshot.position(user).and_then(|index| snapshot.player_rank(index)) {
+                return Ok(rank);
+            }
+        }
+
+        Self::fetch_live_player_rank(context, user).await
+    }
+
+    async fn fetch_live_player_rank(context: &ApiV1State, user: &str) -> Result<PlayerRank, TetrioError> {
+        let username = user.to_lowercase();
+        let user_info = TetrioLookup::user_info(context, &username).await?;
+
+        if user_info.data.is_none() {
+            return Err(TetrioError::UserNotFound);
+        }
+
+        let summary = TetrioLookup::summary(context, &username, "league").await?;
+
+        let Some(summary) = summary.data else {
+            return Err(TetrioError::NotFound("User does not have a league summary".to_string()));
+        };
+
+        // tetr.io reports -1 for unranked players
+        let standing = |key: &str| summary.get(key)
+            .and_then(|value| value.as_i64())
+            .filter(|value| *value > 0)
+            .map(|value| value as usize);
+
+        Ok(PlayerRank {
+            user_id: None,
+            username: Some(username),
+            country: None,
+            global_rank: standing("standing"),
+            country_rank: standing("standing_local"),
+            percentile: summary.get("percentile").and_then(|value| value.as_f64()).map(|value| value * 100.0),
+            above: None,
+            below: None,
+            source: RankSource::Live
+        })
+    }
+
+    async fn crawl(context: &ApiV1State, cache_key: &str, country: Option<String>, lock: &CrawlLock) -> Result<Vec<serde_json::Value>, TetrioError> {
+        let options = &context.leaderboard_options;
+        let (checkpoint, mut entries) = match Self::load_checkpoint(context, cache_key).await? {
+            Some(resumed) => {
+                log::info!("Resuming leaderboard crawl {cache_key} from {} entries", resumed.1.len());
+                resumed
+            },
+            None => (CrawlCheckpoint { session_id: uuid::Uuid::new_v4().to_string(), after: None }, vec![])
+        };
+
+        let CrawlCheckpoint { session_id, mut after } = checkpoint;
+
+        loop {
+            let query = match &after {
+                None => ValueBoundQuery::NotBound { limit: Some(100), country: country.clone() },
+                Some(after) => {
+                    let after = serde_json::from_value::<Prisecter>(after.clone())
+                        .map_err(|e| Error(format!("Invalid leaderboard checkpoint! {e}")))?;
+                    ValueBoundQuery::After { after, limit: Some(100), country: country.clone() }
+                }
+            };
+
+            let page = Self::fetch_page_with_retry(context, &query, &session_id, lock).await?;
+            lock.extend(context, CRAWL_LOCK_TTL).await;
+
+            let Some(last) = page.last() else {
+                break;
+            };
+
+            let default_p = json!(Prisecter { pri: 0., sec: 0., ter: 0. });
+            after = Some(last.get("p").unwrap_or(&default_p).clone());
+
+            if let Err(Error(message)) = Self::save_checkpoint(context, cache_key, &CrawlCheckpoint { session_id: session_id.clone(), after: after.clone() }, &page).await {
+                log::warn!("{message}");
+            }
+
+            let page_len = page.len();
+            entries.extend(page);
+
+            if page_len != PAGE_SIZE {
+                break;
+            }
+
+            tokio::time::sleep(options.request_delay).await;
+        }
+
+        Ok(entries)
+    }
+
+    async fn fetch_page_with_retry(context: &ApiV1State, query: &ValueBoundQuery, session_id: &str, lock: &CrawlLock) -> Result<Vec<serde_json::Value>, TetrioError> {
+        let options = &context.leaderboard_options;
+        let mut attempt = 0;
+        loop {
+            match Self::fetch_page(context, query, session_id).await {
+                Ok(page) => return Ok(page),
+                Err(err) if attempt < options.max_retries => {
+                    let backoff = retry_backoff(options.retry_backoff, attempt);
+                    log::warn!("Couldn't fetch leaderboard page (attempt {}/{}), retrying in {backoff:?}: {err}", attempt + 1, options.max_retries);
+                    lock.extend(context, backoff + CRAWL_LOCK_TTL).await;
+                    tokio::time::sleep(backoff).await;
+                    attempt += 1;
+                },
+                Err(err) => return Err(err)
+            }
+        }
+    }
+
+    async fn fetch_page(context: &ApiV1State, query: &ValueBoundQuery, session_id: &str) -> Result<Vec<serde_json::Value>, TetrioError> {
+        let url = format!("users/by/{}", "league");
+        let request = context.http_client.make_tetrio_api_request::<serde_json::Value>(TetrioClient::make_url(&url, &query.as_query_params()), Some(session_id));
+        let result = Metrics::global().observe_tetrio("leaderboard", request)
+            .await
+            .map_err(TetrioError::request("Couldn't fetch leaderboard!"))?;
+
+        if !result.success {
+            let message = result.error.map(|error| error.msg).unwrap_or_default();
+            return Err(TetrioError::Request { action: "Couldn't fetch leaderboard!", message });
+        }
+
+        let result = result.data.unwrap_or(json! ({
+            "entries": []
+        }));
+
+        result.get("entries")
+            .and_then(|entries| entries.as_array())
+            .cloned()
+            .ok_or(TetrioError::Malformed("leaderboard entries"))
+    }
+
+    async fn load_checkpoint(context: &ApiV1State, cache_key: &str) -> Result<Option<(CrawlCheckpoint, Vec<serde_json::Value>)>, Error> {
+        // without redis the crawl simply starts over
+        let Some(mut connection) = context.cache.redis_connection().await else {
+            return Ok(None);
+        };
+
+        let checkpoint: Option<String> = connection.get(Self::checkpoint_key(cache_key))
+            .await
+            .map_err(|e| Error(format!("Couldn't load leaderboard checkpoint! {e}")))?;
+
+        let Some(checkpoint) = checkpoint else {
+            return Ok(None);
+        };
+
+        let Ok(checkpoint) = serde_json::from_str::<CrawlCheckpoint>(&checkpoint) else {
+            log::warn!("Discarding unreadable leaderboard checkpoint {cache_key}");
+            Self::clear_checkpoint(context, cache_key).await?;
+            return Ok(None);
+        };
+
+        let pages: Vec<String> = connection.lrange(Self::checkpoint_pages_key(cache_key), 0, -1)
+            .await
+            .map_err(|e| Error(format!("Couldn't load leaderboard checkpoint! {e}")))?;
+
+        let mut entries = vec![];
+        for page in pages {
+            let page = serde_json::from_str::<Vec<serde_json::Value>>(&page)
+                .map_err(|e| Error(format!("Invalid leaderboard checkpoint! {e}")))?;
+            entries.extend(page);
+        }
+
+        Ok(Some((checkpoint, entries)))
+    }
+
+    async fn save_checkpoint(context: &ApiV1State, cache_key: &str, checkpoint: &CrawlCheckpoint, page: &[serde_json::Value]) -> Result<(), Error> {
+        let Some(mut connection) = context.cache.redis_connection().await else {
+            return Ok(());
+        };
+
+        let checkpoint = serde_json::to_string(checkpoint)
+            .map_err(|e| Error(format!("Couldn't serialize leaderboard checkpoint! {e}")))?;
+        let page = serde_json::to_string(page)
+            .map_err(|e| Error(format!("Couldn't serialize leaderboard page! {e}")))?;
+
+        let pages_key = Self::checkpoint_pages_key(cache_key);
+        redis::pipe()
+            .atomic()
+            .rpush(&pages_key, page).ignore()
+            .expire(&pages_key, CHECKPOINT_TTL as i64).ignore()
+            .set_ex(Self::checkpoint_key(cache_key), checkpoint, CHECKPOINT_TTL).ignore()
+            .query_async::<_, ()>(&mut connection)
+            .await
+            .map_err(|e| Error(format!("Couldn't save leaderboard checkpoint! {e}")))
+    }
+
+    async fn clear_checkpoint(context: &ApiV1State, cache_key: &str) -> Result<(), Error> {
+        let Some(mut connection) = context.cache.redis_connection().await else {
+            return Ok(());
+        };
+
+        connection.del::<_, ()>(&[Self::checkpoint_key(cache_key), Self::checkpoint_pages_key(cache_key)])
+            .await
+            .map_err(|e| Error(format!("Couldn't clear leaderboard checkpoint! {e}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::json;
+
+    use crate::api::api_v1::{cache::now_millis, models::leaderboard::{LeaderboardEvent, RankSource}};
+
+    use super::{retry_backoff, LeaderboardCrawler, LeaderboardSnapshot, MAX_RETRY_BACKOFF};
+
+    fn entry(user_id: &str, username: &str, country: &str, rank: &str) -> serde_json::Value {
+        json!({ "_id": user_id, "username": username, "country": country, "league": { "rank": rank } })
+    }
+
+    fn played_entry(user_id: &str, gamesplayed: u64, tr: f64) -> serde_json::Value {
+        json!({ "_id": user_id, "username": user_id, "league": { "rank": "x", "gamesplayed": gamesplayed, "tr": tr } })
+    }
+
+    fn snapshot(entries: Vec<serde_json::Value>) -> LeaderboardSnapshot {
+        LeaderboardSnapshot::from_entries(entries, now_millis())
+    }
+
+    #[test]
+    fn normalize_country_uppercases_and_drops_empty_codes() {
+        assert_eq!(LeaderboardCrawler::normalize_country(Some(" fr ")), Some("FR".to_string()));
+        assert_eq!(LeaderboardCrawler::normalize_country(Some("  ")), None);
+        assert_eq!(LeaderboardCrawler::normalize_country(None), None);
+    }
+
+    #[test]
+    fn cache_key_is_the_same_for_equivalent_countries() {
+        assert_eq!(LeaderboardCrawler::cache_key(None), "full_leaderboard/global");
+        assert_eq!(LeaderboardCrawler::cache_key(Some("")), "full_leaderboard/global");
+        assert_eq!(LeaderboardCrawler::cache_key(Some("us")), "full_leaderboard/country/US");
+        assert_eq!(LeaderboardCrawler::cache_key(Some("us")), LeaderboardCrawler::cache_key(Some(" US")));
+    }
+
+    #[test]
+    fn retry_backoff_doubles_and_stays_capped() {
+        let base = Duration::from_secs(2);
+        assert_eq!(retry_backoff(base, 0), Duration::from_secs(2));
+        assert_eq!(retry_backoff(base, 3), Duration::from_secs(16));
+        assert_eq!(retry_backoff(base, 40), MAX_RETRY_BACKOFF);
+        assert_eq!(retry_backoff(base, u32::MAX), MAX_RETRY_BACKOFF);
+    }
+
+    #[test]
+    fn from_entries_finds_players_by_id_and_username() {
+        let snapshot = snapshot(vec![
+            entry("1", "osk", "JP", "x+"),
+            entry("2", "Czsmall", "US", "x+"),
+        ]);
+
+        assert_eq!(snapshot.position("1"), Some(0));
+        assert_eq!(snapshot.position("czsmall"), Some(1));
+        assert_eq!(snapshot.position("CZSMALL"), Some(1));
+        assert_eq!(snapshot.position("unknown"), None);
+    }
+
+    #[test]
+    fn player_rank_counts_country_ranks_and_neighbours() {
+        let snapshot = snapshot(vec![
+            entry("1", "a", "US", "x+"),
+            entry("2", "b", "JP", "x+"),
+            entry("3", "c", "us", "x"),
+            entry("4", "d", "FR", "x"),
+        ]);
+
+        let rank = snapshot.player_rank(2).expect("the player is on the ladder");
+        assert_eq!(rank.global_rank, Some(3));
+        assert_eq!(rank.country_rank, Some(2));
+        assert_eq!(rank.percentile, Some(75.0));
+        assert_eq!(rank.above.map(|above| above.rank), Some(2));
+        assert_eq!(rank.below.and_then(|below| below.username).as_deref(), Some("d"));
+        assert_eq!(rank.source, RankSource::Snapshot);
+
+        let first = snapshot.player_rank(0).expect("the player is on the ladder");
+        assert!(first.above.is_none());
+        assert!(snapshot.player_rank(4).is_none());
+    }
+
+    #[test]
+    fn snapshot_goes_stale_from_its_crawl_time() {
+        let fresh = snapshot(vec![]);
+        assert!(!fresh.is_stale(Duration::from_secs(60)));
+
+        let old = LeaderboardSnapshot::from_entries(vec![], now_millis() - 61_000);
+        assert!(old.is_stale(Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn diff_only_moves_players_who_played() {
+        let previous = snapshot(vec![played_entry("a", 10, 24000.), played_entry("b", 10, 23900.), played_entry("c", 10, 23800.)]);
+        // c played and passed both a and b, who only shifted down
+        let current = snapshot(vec![played_entry("c", 11, 24100.), played_entry("a", 10, 24000.), played_entry("b", 10, 23900.)]);
+
+        let events = previous.diff(&current, 1);
+        assert_eq!(events.len(), 1);
+        assert!(matches!(&events[0], LeaderboardEvent::RankUp { player, from: 3, to: 1 } if player.user_id == "c"));
+    }
+
+    #[test]
+    fn diff_skips_small_changes_and_reports_dropped_players() {
+        let previous = snapshot(vec![played_entry("a", 10, 24000.), played_entry("b", 10, 23900.), played_entry("c", 10, 23800.)]);
+        let current = snapshot(vec![played_entry("b", 11, 24100.), played_entry("a", 10, 24000.), played_entry("d", 1, 23000.)]);
+
+        let events = previous.diff(&current, 2);
+        assert_eq!(events.len(), 2);
+        assert!(matches!(&events[0], LeaderboardEvent::NewEntrant { player, rank: 3 } if player.user_id == "d"));
+        assert!(matches!(&events[1], LeaderboardEvent::Dropped { player, rank: 3 } if player.user_id == "c"));
+    }
+}
@@ -0,0 +1,47 @@
+#![cfg(feature = "tetrio")]
+
+use tetrio_api::http::parameters::{personal_user_records::{PersonalLeaderboard, PersonalRecordsQuery}, value_bound_query::Prisecter};
+
+use crate::api::api_v1::{cache::CacheNamespace, services::tetrio::{TetrioError, TetrioLookup}, ApiV1State};
+
+// tetr.io returns at most 100 records per page
+pub const PAGE_SIZE: usize = 100;
+
+pub struct LeagueRecords;
+
+impl LeagueRecords {
+    /// One page of the user's league records as raw json, `before` being the cursor of the last record of the previous page
+    pub async fn fetch_page(context: &ApiV1State, user: &str, leaderboard: PersonalLeaderboard, before: Option<Prisecter>) -> Result<Vec<serde_json::Value>, TetrioError> {
+        let user = user.to_lowercase();
+        let cursor = match &before {
+            Some(before) => serde_json::to_string(before)?,
+            None => "latest".to_string(),
+        };
+        let key = format!("{}/{cursor}/{user}", format!("{leaderboard:?}").to_lowercase());
+
+        let query = match before {
+            Some(before) => PersonalRecordsQuery::Before { before, limit: Some(100) },
+            None => PersonalRecordsQuery::NotBound { limit: Some(100) },
+        };
+
+        let request = context.http_client.fetch_user_personal_league_records(&user, leaderboard, query);
+        let packet = TetrioLookup::cached(context, CacheNamespace::TetrioLeagueRecords, &key, "league_records", request)
+            .await
+            .map_err(TetrioError::request("Couldn't fetch tetra league records:"))?;
+
+        let Some(data) = packet.data else {
+            return Ok(vec![]);
+        };
+
+        match serde_json::to_value(&data.entries)? {
+            serde_json::Value::Array(entries) => Ok(entries),
+            _ => Err(TetrioError::Malformed("tetra league records")),
+        }
+    }
+
+    /// Cursor of `record`, to fetch the page of the records older than it
+    pub fn cursor(record: &serde_json::Value) -> Result<Prisecter, TetrioError> {
+        let p = record.get("p").cloned().ok_or(TetrioError::Malformed("tetra league record cursor"))?;
+        serde_json::from_value(p).map_err(|_| TetrioError::Malformed("tetra league record cursor"))
+    }
+}
@@ -0,0 +1,114 @@
+#[cfg(feature = "tetrio")]
+use tetrio_api::http::parameters::personal_user_records::PersonalLeaderboard;
+
+use crate::api::api_v1::models::league_stats::{DerivedStats, MatchStats, PlayerMatchStats};
+#[cfg(feature = "tetrio")]
+use crate::api::api_v1::{services::{league_records::LeagueRecords, tetrio::TetrioError}, ApiV1State};
+
+pub struct LeagueStats;
+
+impl LeagueStats {
+    /// Per player and per round stats of a league record, as returned by `users/:user/records/league/*`
+    pub fn match_stats(record: &serde_json::Value) -> Option<MatchStats> {
+        let leaderboard = record.pointer("/results/leaderboard")?.as_array()?;
+        let rounds = record.pointer("/results/rounds")
+            .and_then(|rounds| rounds.as_array())
+            .cloned()
+            .unwrap_or_default();
+
+        let players = leaderboard.iter()
+            .map(|entry| {
+                let user_id = entry.get("id").and_then(|id| id.as_str()).map(|id| id.to_string());
+
+                let player_rounds = rounds.iter()
+                    .filter_map(|round| round.as_array()?.iter().find(|round_entry| {
+                        round_entry.get("id").and_then(|id| id.as_str()) == user_id.as_deref()
+                    }))
+                    .filter_map(DerivedStats::from_entry)
+                    .collect();
+
+                PlayerMatchStats {
+                    user_id,
+                    username: entry.get("username").and_then(|username| username.as_str()).map(|username| username.to_string()),
+                    wins: entry.get("wins").and_then(|wins| wins.as_u64()).unwrap_or_default(),
+                    overall: DerivedStats::from_entry(entry).unwrap_or_default(),
+                    rounds: player_rounds,
+                }
+            })
+            .collect();
+
+        Some(MatchStats {
+            replay_id: record.get("replayid").and_then(|id| id.as_str()).map(|id| id.to_string()),
+            players,
+        })
+    }
+
+    /// Stats of the `game_num`th most recent league game of `user`, starting at 1
+    #[cfg(feature = "tetrio")]
+    pub async fn fetch_match_stats(context: &ApiV1State, user: &str, game_num: u32) -> Result<MatchStats, TetrioError> {
+        let records = LeagueRecords::fetch_page(context, &user.to_lowercase(), PersonalLeaderboard::Recent, None).await?;
+
+        let game_num = if game_num == 0 { 1 } else { game_num };
+        let Some(record) = records.get((game_num - 1) as usize) else {
+            return Err(TetrioError::NotFound("Tetra league game not found".to_string()))
+        };
+
+        Self::record_stats(record)
+    }
+
+    /// Stats of a league record fetched for something else, like the screenshot of `/tetra`
+    #[cfg(feature = "tetrio")]
+    pub fn record_stats(record: &serde_json::Value) -> Result<MatchStats, TetrioError> {
+        Self::match_stats(record).ok_or(TetrioError::Malformed("tetra league game stats"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::LeagueStats;
+
+    #[test]
+    fn match_stats_splits_players_and_rounds() {
+        let record = json!({
+            "replayid": "replay",
+            "results": {
+                "leaderboard": [
+                    { "id": "a", "username": "alice", "wins": 7, "stats": { "apm": 60.0, "pps": 1.0, "vsscore": 120.0 } },
+                    { "id": "b", "username": "bob", "wins": 3, "stats": { "apm": 30.0, "pps": 1.5, "vsscore": 50.0 } },
+                ],
+                "rounds": [
+                    [
+                        { "id": "b", "stats": { "apm": 40.0, "pps": 2.0, "vsscore": 70.0 } },
+                        { "id": "a", "stats": { "apm": 50.0, "pps": 1.0, "vsscore": 100.0 } },
+                    ],
+                    [
+                        { "id": "a", "stats": { "apm": 70.0, "pps": 1.0, "vsscore": 140.0 } },
+                        { "id": "b" },
+                    ],
+                ],
+            },
+        });
+
+        let stats = LeagueStats::match_stats(&record).expect("the record has a leaderboard");
+        assert_eq!(stats.replay_id.as_deref(), Some("replay"));
+        assert_eq!(stats.players.len(), 2);
+
+        let alice = &stats.players[0];
+        assert_eq!(alice.username.as_deref(), Some("alice"));
+        assert_eq!(alice.wins, 7);
+        assert_eq!(alice.overall.app, 1.0);
+        assert_eq!(alice.rounds.iter().map(|round| round.apm).collect::<Vec<_>>(), vec![50.0, 70.0]);
+
+        // rounds without stats are left out
+        let bob = &stats.players[1];
+        assert_eq!(bob.rounds.len(), 1);
+        assert_eq!(bob.rounds[0].pps, 2.0);
+    }
+
+    #[test]
+    fn match_stats_needs_a_leaderboard() {
+        assert!(LeagueStats::match_stats(&json!({ "results": {} })).is_none());
+    }
+}
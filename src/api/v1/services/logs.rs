@@ -0,0 +1,220 @@
+use std::{collections::VecDeque, path::{Path, PathBuf}, str::FromStr, time::SystemTime};
+
+use chrono::{DateTime, FixedOffset, Utc};
+use log::Level;
+use tokio::{fs::File, io::{AsyncBufReadExt, BufReader}, sync::mpsc};
+use tokio_stream::wrappers::ReceiverStream;
+
+use crate::api::{api_v1::models::logs::LogQuery, Error};
+
+pub const LOG_DIRECTORY: &str = "./logs";
+
+// text format timestamps, flexi_logger's TS_DASHES_BLANK_COLONS_DOT_BLANK, e.g. `2024-01-31 18:02:03.123456 +01:00`
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.6f %:z";
+const TIMESTAMP_LENGTH: usize = 33;
+
+pub struct LogFilter {
+    level: Option<Level>,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    contains: Option<String>,
+}
+
+impl TryFrom<&LogQuery> for LogFilter {
+    type Error = Error;
+
+    fn try_from(query: &LogQuery) -> Result<Self, Self::Error> {
+        let level = query.level.as_deref()
+            .map(|level| Level::from_str(level).map_err(|_| Error(format!("Unknown log level {level}"))))
+            .transpose()?;
+
+        Ok(Self {
+            level,
+            since: query.since,
+            until: query.until,
+            contains: query.contains.as_ref().map(|contains| contains.to_lowercase()),
+        })
+    }
+}
+
+impl LogFilter {
+    fn matches(&self, entry: &LogEntry) -> bool {
+        // `Level` orders errors lowest, so more severe entries compare smaller
+        if let Some(level) = self.level {
+            if !entry.level.is_some_and(|entry_level| entry_level <= level) {
+                return false;
+            }
+        }
+
+        let timestamp = entry.timestamp.map(|timestamp| timestamp.with_timezone(&Utc));
+        if self.since.is_some_and(|since| !timestamp.is_some_and(|timestamp| timestamp >= since)) {
+            return false;
+        }
+        if self.until.is_some_and(|until| !timestamp.is_some_and(|timestamp| timestamp <= until)) {
+            return false;
+        }
+
+        match &self.contains {
+            Some(contains) => entry.text.to_lowercase().contains(contains),
+            None => true
+        }
+    }
+}
+
+/// One log record, spanning several lines when its message does
+struct LogEntry {
+    timestamp: Option<DateTime<FixedOffset>>,
+    level: Option<Level>,
+    text: String,
+}
+
+impl LogEntry {
+    /// Parses the first line of a record, `None` for continuation lines
+    fn parse(line: &str) -> Option<Self> {
+        if line.starts_with('{') {
+            return Self::parse_json(line);
+        }
+
+        let timestamp = DateTime::parse_from_str(line.get(..TIMESTAMP_LENGTH)?, TIMESTAMP_FORMAT).ok()?;
+        let level = line.split_once("Severity ")
+            .and_then(|(_, rest)| rest.split_once(','))
+            .and_then(|(level, _)| Level::from_str(level).ok());
+
+        Some(Self { timestamp: Some(timestamp), level, text: line.to_string() })
+    }
+
+    /// Lines written by the json format, one record per line
+    fn parse_json(line: &str) -> Option<Self> {
+        let record = serde_json::from_str::<serde_json::Value>(line).ok()?;
+        let timestamp = record.get("timestamp")
+            .and_then(|timestamp| timestamp.as_str())
+            .and_then(|timestamp| DateTime::parse_from_rfc3339(timestamp).ok());
+        let level = record.get("level")
+            .and_then(|level| level.as_str())
+            .and_then(|level| Level::from_str(level).ok());
+
+        Some(Self { timestamp, level, text: line.to_string() })
+    }
+
+    fn continuation(line: &str) -> Self {
+        Self { timestamp: None, level: None, text: line.to_string() }
+    }
+
+    fn push_line(&mut self, line: &str) {
+        self.text.push('\n');
+        self.text.push_str(line);
+    }
+}
+
+/// Forwards matching entries to the response, holding back the last `tail` ones when asked to
+struct LogSink {
+    filter: LogFilter,
+    sender: mpsc::Sender<Result<String, std::io::Error>>,
+    tail: Option<(usize, VecDeque<String>)>,
+}
+
+impl LogSink {
+    /// Returns false once the client went away
+    async fn push(&mut self, entry: LogEntry) -> bool {
+        if !self.filter.matches(&entry) {
+            return true;
+        }
+
+        match &mut self.tail {
+            Some((size, buffer)) => {
+                buffer.push_back(entry.text);
+                if buffer.len() > *size {
+                    buffer.pop_front();
+                }
+                true
+            },
+            None => self.sender.send(Ok(entry.text + "\n")).await.is_ok()
+        }
+    }
+
+    async fn finish(self) {
+        if let Some((_, buffer)) = self.tail {
+            for text in buffer {
+                if self.sender.send(Ok(text + "\n")).await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+pub struct LogReader;
+
+impl LogReader {
+    /// Every file of the log directory, rotated ones included, oldest first
+    pub fn log_files(directory: &Path) -> Result<Vec<PathBuf>, Error> {
+        let entries = std::fs::read_dir(directory)
+            .map_err(|e| Error(format!("Couldn't read log directory {}! {e}", directory.display())))?;
+
+        let mut files = entries
+            .flatten()
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok()?;
+                metadata.is_file().then(|| (metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH), entry.path()))
+            })
+            .collect::<Vec<_>>();
+        files.sort();
+
+        Ok(files.into_iter().map(|(_, path)| path).collect())
+    }
+
+    pub fn stream(files: Vec<PathBuf>, filter: LogFilter, tail: Option<usize>) -> ReceiverStream<Result<String, std::io::Error>> {
+        let (sender, receiver) = mpsc::channel(64);
+
+        tokio::spawn(async move {
+            let mut sink = LogSink { filter, sender, tail: tail.map(|size| (size, VecDeque::new())) };
+
+            for path in files {
+                let file = match File::open(&path).await {
+                    Ok(file) => file,
+                    Err(e) => {
+                        log::warn!("Couldn't open log file {}: {e}", path.display());
+                        continue;
+                    }
+                };
+
+                let mut lines = BufReader::new(file).lines();
+                let mut entry: Option<LogEntry> = None;
+                loop {
+                    let line = match lines.next_line().await {
+                        Ok(Some(line)) => line,
+                        Ok(None) => break,
+                        Err(e) => {
+                            let _ = sink.sender.send(Err(e)).await;
+                            return;
+                        }
+                    };
+
+                    let Some(next) = LogEntry::parse(&line) else {
+                        match &mut entry {
+                            Some(current) => current.push_line(&line),
+                            None => entry = Some(LogEntry::continuation(&line)),
+                        }
+                        continue;
+                    };
+
+                    if let Some(done) = entry.replace(next) {
+                        if !sink.push(done).await {
+                            return;
+                        }
+                    }
+                }
+
+                if let Some(done) = entry {
+                    if !sink.push(done).await {
+                        return;
+                    }
+                }
+            }
+
+            sink.finish().await;
+        });
+
+        ReceiverStream::new(receiver)
+    }
+}
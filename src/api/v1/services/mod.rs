@@ -1,2 +1,16 @@
 pub mod silly_command;
-pub mod users;
\ No newline at end of file
+pub mod users;
+pub mod leaderboard;
+pub mod watchlist;
+pub mod watchlist_poller;
+pub mod records;
+pub mod league_records;
+pub mod league_stats;
+pub mod tetra_history;
+pub mod head_to_head;
+pub mod stale_cache;
+pub mod logs;
+pub mod health;
+pub mod rate_limit;
+pub mod render;
+pub mod tetrio;
\ No newline at end of file
@@ -0,0 +1,265 @@
+use std::{collections::HashMap, sync::OnceLock, time::Duration};
+
+use redis::AsyncCommands;
+
+#[cfg(feature = "database")]
+use crate::api::Error;
+use crate::{api::api_v1::{cache::now_millis, models::rate_limit::{Quota, RateLimitOverride}, ApiV1State}, config::ConfigSource};
+
+const OVERRIDES_KEY: &str = "taka:rate_limit:overrides";
+
+// refills the bucket for the time elapsed since the last request, then takes a token when there is one.
+// Returns whether the request is allowed, the tokens left, and the milliseconds until the bucket is full
+// and until the next token
+const TAKE_TOKEN: &str = r#"
+local capacity = tonumber(ARGV[1])
+local refill = tonumber(ARGV[2])
+local now = tonumber(ARGV[3])
+
+local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'at')
+local tokens = tonumber(bucket[1]) or capacity
+local at = tonumber(bucket[2]) or now
+tokens = math.min(capacity, tokens + math.max(0, now - at) * refill)
+
+local allowed = 0
+if tokens >= 1 then
+    tokens = tokens - 1
+    allowed = 1
+end
+
+local until_full = math.ceil((capacity - tokens) / refill)
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'at', now)
+redis.call('PEXPIRE', KEYS[1], until_full + 1000)
+
+return {allowed, math.floor(tokens), until_full, math.ceil(math.max(0, 1 - tokens) / refill)}
+"#;
+
+fn take_token_script() -> &'static redis::Script {
+    static SCRIPT: OnceLock<redis::Script> = OnceLock::new();
+    SCRIPT.get_or_init(|| redis::Script::new(TAKE_TOKEN))
+}
+
+#[derive(Clone)]
+pub struct RateLimitOptions {
+    pub enabled: bool,
+    /// Quota of the routes without one of their own
+    pub default_quota: Quota,
+    /// Quotas by route template, e.g. `/api/v1/teto/:user`
+    pub route_quotas: HashMap<String, Quota>,
+    /// Proxies in front of the api that append to `X-Forwarded-For`, the header is ignored when 0
+    pub trusted_proxies: usize,
+}
+
+impl RateLimitOptions {
+    // renders launch chrome, head-to-head and history page through tetr.io records and the full leaderboard
+    // crawls tetr.io with our token
+    const DEFAULT_ROUTE_QUOTAS: [(&'static str, Quota); 8] = [
+        ("/api/v1/full_leaderboard", Quota { capacity: 5, period_seconds: 60 }),
+        ("/api/v1/teto/:user", Quota { capacity: 10, period_seconds: 60 }),
+        ("/api/v1/tetra", Quota { capacity: 10, period_seconds: 60 }),
+        ("/api/v1/tetra/replay", Quota { capacity: 10, period_seconds: 60 }),
+        ("/api/v1/league_recent_test", Quota { capacity: 10, period_seconds: 60 }),
+        ("/api/v1/records/:user/:mode", Quota { capacity: 10, period_seconds: 60 }),
+        ("/api/v1/tetra/h2h", Quota { capacity: 10, period_seconds: 60 }),
+        ("/api/v1/tetra/history/:user", Quota { capacity: 10, period_seconds: 60 }),
+    ];
+
+    pub fn from_config(source: &mut ConfigSource) -> Self {
+        let mut route_quotas: HashMap<String, Quota> = Self::DEFAULT_ROUTE_QUOTAS.iter()
+            .map(|(route, quota)| (route.to_string(), *quota))
+            .collect();
+
+        for entry in source.list("RATE_LIMIT_ROUTES") {
+            let parsed = entry.split_once('=')
+                .ok_or("expected route=requests/seconds".to_string())
+                .and_then(|(route, quota)| Ok((route.trim().to_string(), quota.parse::<Quota>()?)));
+            match parsed {
+                Ok((route, quota)) => { route_quotas.insert(route, quota); },
+                Err(e) => source.error(format!("Invalid value {entry} in RATE_LIMIT_ROUTES: {e}")),
+            }
+        }
+
+        Self {
+            enabled: source.parse("RATE_LIMIT_ENABLED", true),
+            default_quota: source.parse("RATE_LIMIT_DEFAULT", Quota { capacity: 60, period_seconds: 60 }),
+            route_quotas,
+            trusted_proxies: source.parse("RATE_LIMIT_TRUSTED_PROXIES", 0),
+        }
+    }
+
+    pub fn quota(&self, route: &str) -> Quota {
+        self.route_quotas.get(route).copied().unwrap_or(self.default_quota)
+    }
+
+    /// Client address in an `X-Forwarded-For` value. Clients can send the header themselves, so only the
+    /// entries appended by our own proxies are believed, the one added by the outermost being the client
+    pub fn forwarded_client<'a>(&self, forwarded_for: &'a str) -> Option<&'a str> {
+        if self.trusted_proxies == 0 {
+            return None;
+        }
+
+        forwarded_for.rsplit(',')
+            .nth(self.trusted_proxies - 1)
+            .map(|address| address.trim())
+            .filter(|address| !address.is_empty())
+    }
+}
+
+pub struct RateLimitDecision {
+    pub allowed: bool,
+    pub remaining: u64,
+    /// Time until the bucket is full again
+    pub reset: Duration,
+    /// Time until the next request would be allowed
+    pub retry_after: Duration,
+}
+
+/// Token buckets kept in redis so every instance shares the same limits
+pub struct RateLimiter;
+
+impl RateLimiter {
+    fn override_field(client: &str, route: Option<&str>) -> String {
+        match route {
+            Some(route) => format!("{client} {route}"),
+            None => client.to_string(),
+        }
+    }
+
+    /// Whether `client` has a client wide override, which is what makes an api key known
+    #[tracing::instrument(level = "debug", skip_all, fields(client = %client))]
+    pub async fn is_known_client(context: &ApiV1State, client: &str) -> bool {
+        let Some(mut connection) = context.cache.redis_connection().await else {
+            return false;
+        };
+
+        connection.hexists(OVERRIDES_KEY, client).await.unwrap_or(false)
+    }
+
+    /// Override of `client` on `route`, one made for the route winning over a client wide one
+    #[tracing::instrument(level = "debug", skip_all, fields(client = %client, route = %route))]
+    pub async fn find_override(context: &ApiV1State, client: &str, route: &str) -> Option<Quota> {
+        let mut connection = context.cache.redis_connection().await?;
+        let fields = [Self::override_field(client, Some(route)), Self::override_field(client, None)];
+        let (route_override, client_override): (Option<String>, Option<String>) = connection.hget(OVERRIDES_KEY, &fields).await.ok()?;
+
+        route_override.or(client_override)
+            .and_then(|entry| serde_json::from_str::<RateLimitOverride>(&entry).ok())
+            .map(|entry| entry.quota)
+    }
+
+    /// Takes a token from the bucket of `client` on `route`, `None` when redis can't be reached
+    #[tracing::instrument(level = "debug", skip_all, fields(client = %client, route = %route))]
+    pub async fn take(context: &ApiV1State, client: &str, route: &str, quota: Quota) -> Option<RateLimitDecision> {
+        let mut connection = context.cache.redis_connection().await?;
+
+        let result: Result<(u8, u64, u64, u64), _> = take_token_script()
+            .key(format!("taka:rate_limit:bucket:{client}:{route}"))
+            .arg(quota.capacity)
+            .arg(quota.refill_per_ms())
+            .arg(now_millis())
+            .invoke_async(&mut connection)
+            .await;
+
+        match result {
+            Ok((allowed, remaining, until_full, until_next)) => Some(RateLimitDecision {
+                allowed: allowed == 1,
+                remaining,
+                reset: Duration::from_millis(until_full),
+                retry_after: Duration::from_millis(until_next),
+            }),
+            Err(e) => {
+                log::warn!("Couldn't take rate limit token for {client} on {route}: {e}");
+                None
+            }
+        }
+    }
+
+    #[cfg(feature = "database")]
+    pub async fn list_overrides(context: &ApiV1State) -> Result<Vec<RateLimitOverride>, Error> {
+        let mut connection = context.cache.redis_connection().await.ok_or(Error("Couldn't connect to redis".to_string()))?;
+        let entries: Vec<String> = connection.hvals(OVERRIDES_KEY).await.map_err(|e| Error(format!("Couldn't list rate limit overrides! {e}")))?;
+
+        Ok(entries.iter().filter_map(|entry| serde_json::from_str(entry).ok()).collect())
+    }
+
+    #[cfg(feature = "database")]
+    #[tracing::instrument(level = "debug", skip_all, fields(client = %entry.client, route = ?entry.route))]
+    pub async fn set_override(context: &ApiV1State, entry: &RateLimitOverride) -> Result<(), Error> {
+        let mut connection = context.cache.redis_connection().await.ok_or(Error("Couldn't connect to redis".to_string()))?;
+        let value = serde_json::to_string(entry).map_err(|e| Error(format!("Couldn't serialize rate limit override! {e}")))?;
+
+        connection.hset::<_, _, _, ()>(OVERRIDES_KEY, Self::override_field(&entry.client, entry.route.as_deref()), value)
+            .await
+            .map_err(|e| Error(format!("Couldn't save rate limit override! {e}")))
+    }
+
+    /// Returns whether there was an override to delete
+    #[cfg(feature = "database")]
+    #[tracing::instrument(level = "debug", skip_all, fields(client = %client, route = ?route))]
+    pub async fn delete_override(context: &ApiV1State, client: &str, route: Option<&str>) -> Result<bool, Error> {
+        let mut connection = context.cache.redis_connection().await.ok_or(Error("Couldn't connect to redis".to_string()))?;
+        let deleted: u32 = connection.hdel(OVERRIDES_KEY, Self::override_field(client, route))
+            .await
+            .map_err(|e| Error(format!("Couldn't delete rate limit override! {e}")))?;
+
+        Ok(deleted > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{api::api_v1::models::rate_limit::Quota, config::ConfigSource};
+
+    use super::RateLimitOptions;
+
+    fn options(toml: &str) -> (RateLimitOptions, ConfigSource) {
+        let mut source = ConfigSource::from_toml(toml);
+        (RateLimitOptions::from_config(&mut source), source)
+    }
+
+    #[test]
+    fn route_quotas_add_to_the_defaults() {
+        let (options, source) = options(r#"
+            [rate_limit]
+            routes = ["/api/v1/tetra = 3/30", "/api/v1/records/:user/:mode=20/60"]
+        "#);
+
+        assert_eq!(options.quota("/api/v1/tetra"), Quota { capacity: 3, period_seconds: 30 });
+        assert_eq!(options.quota("/api/v1/records/:user/:mode"), Quota { capacity: 20, period_seconds: 60 });
+        assert_eq!(options.quota("/api/v1/full_leaderboard"), Quota { capacity: 5, period_seconds: 60 });
+        assert_eq!(options.quota("/api/v1/tetra/history/:user"), Quota { capacity: 10, period_seconds: 60 });
+        assert_eq!(options.quota("/api/v1/"), options.default_quota);
+        assert!(source.finish().is_ok());
+    }
+
+    #[test]
+    fn invalid_route_quotas_are_reported() {
+        let (options, source) = options(r#"
+            [rate_limit]
+            routes = ["/api/v1/tetra", "/api/v1/teto/:user=ten/60", "/api/v1/=1/0"]
+        "#);
+
+        assert_eq!(options.quota("/api/v1/tetra"), Quota { capacity: 10, period_seconds: 60 });
+        let message = source.finish().expect_err("the quotas are invalid").0;
+        assert_eq!(message.matches("in RATE_LIMIT_ROUTES").count(), 3, "{message}");
+    }
+
+    #[test]
+    fn forwarded_for_is_ignored_without_trusted_proxies() {
+        let (options, _) = options("");
+
+        assert_eq!(options.forwarded_client("1.1.1.1"), None);
+    }
+
+    #[test]
+    fn forwarded_for_is_read_from_the_right() {
+        let (one, _) = options("[rate_limit]\ntrusted_proxies = 1");
+        let (two, _) = options("[rate_limit]\ntrusted_proxies = 2");
+        let spoofed = "6.6.6.6, 1.1.1.1, 10.0.0.2";
+
+        assert_eq!(one.forwarded_client(spoofed), Some("10.0.0.2"));
+        assert_eq!(two.forwarded_client(spoofed), Some("1.1.1.1"));
+        assert_eq!(two.forwarded_client("10.0.0.2"), None);
+        assert_eq!(one.forwarded_client(" "), None);
+    }
+}
@@ -0,0 +1,88 @@
+#![cfg(feature = "tetrio")]
+
+use tetrio_api::models::packet::Packet;
+
+use crate::api::api_v1::{cache::{tetrio_cache_ttl, CacheNamespace}, metrics::Metrics, models::records::{RecordCard, RecordMode, RecordStats}, services::{render::{render_page, RenderError}, tetrio::{TetrioError, TetrioLookup}}, ApiV1State};
+
+pub struct TetrioRecords;
+
+impl TetrioRecords {
+    fn cache_key(user: &str, mode: RecordMode) -> String {
+        format!("{}/{}", mode.name(), user.to_lowercase())
+    }
+
+    /// Reads the stats out of a `users/:user/summaries/{40l,blitz}` payload
+    pub fn stats_from_summary(mode: RecordMode, summary: &serde_json::Value) -> Option<RecordStats> {
+        let record = summary.get("record").filter(|record| !record.is_null())?;
+        let stats = record.pointer("/results/stats")?;
+
+        let final_time = stats.get("finaltime").and_then(|time| time.as_f64());
+        let pieces_placed = stats.get("piecesplaced").and_then(|pieces| pieces.as_i64());
+        let perfect_pieces = stats.pointer("/finesse/perfectpieces").and_then(|pieces| pieces.as_i64());
+
+        Some(RecordStats {
+            mode,
+            replay_id: record.get("replayid").and_then(|id| id.as_str()).map(|id| id.to_string()),
+            time: final_time.filter(|_| mode == RecordMode::Sprint),
+            score: stats.get("score").and_then(|score| score.as_i64()).filter(|_| mode == RecordMode::Blitz),
+            pieces_placed,
+            pps: match (pieces_placed, final_time) {
+                (Some(pieces), Some(time)) if time > 0.0 => Some(pieces as f64 / (time / 1000.0)),
+                _ => None
+            },
+            finesse_faults: stats.pointer("/finesse/faults").and_then(|faults| faults.as_i64()),
+            finesse_percentage: match (perfect_pieces, pieces_placed) {
+                (Some(perfect), Some(pieces)) if pieces > 0 => Some(perfect as f64 / pieces as f64 * 100.0),
+                _ => None
+            },
+            // tetr.io reports -1 when the record isn't placed
+            global_rank: summary.get("rank").and_then(|rank| rank.as_i64()).filter(|rank| *rank > 0),
+            country_rank: summary.get("rank_local").and_then(|rank| rank.as_i64()).filter(|rank| *rank > 0),
+        })
+    }
+
+    pub async fn fetch_record_card(context: &ApiV1State, user: &str, mode: RecordMode, render: bool) -> Result<Packet<RecordCard>, TetrioError> {
+        let username = user.to_lowercase();
+        let namespace = if render { CacheNamespace::RecordCard } else { CacheNamespace::RecordStats };
+        let cache_key = Self::cache_key(&username, mode);
+        if let Some(entry) = context.cache.get::<Packet<RecordCard>>(namespace, &cache_key).await {
+            return Ok(entry);
+        }
+
+        let summary = TetrioLookup::summary(context, &username, mode.name()).await?;
+
+        let Some(cache) = summary.cache.clone() else {
+            return Err(TetrioError::UserNotFound);
+        };
+
+        let stats = summary.data
+            .as_ref()
+            .and_then(|summary| Self::stats_from_summary(mode, summary))
+            .ok_or_else(|| TetrioError::NotFound(format!("User does not have a {} record", mode.name())))?;
+
+        let buffer = if render {
+            Some(Metrics::global().observe_render("record", Self::take_record_screenshot(context, &username, mode)).await?.into_boxed_slice())
+        } else {
+            None
+        };
+
+        let ttl = tetrio_cache_ttl(&cache);
+        let entry = Packet {
+            success: true,
+            error: None,
+            data: Some(RecordCard { stats, buffer }),
+            cache: Some(cache)
+        };
+
+        if let Err(err) = context.cache.set(namespace, &cache_key, &entry, ttl).await {
+            log::warn!("Couldn't cache {} record of {username}: {err}", mode.name());
+        }
+
+        Ok(entry)
+    }
+
+    async fn take_record_screenshot(context: &ApiV1State, user: &str, mode: RecordMode) -> Result<Vec<u8>, RenderError> {
+        let url = format!("{}/records/{}/{}", context.html_server_url, mode.name(), user);
+        render_page(&context.browser_options, &url, (900, 500), ".record_card").await
+    }
+}
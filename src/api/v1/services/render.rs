@@ -0,0 +1,116 @@
+use std::fmt::Display;
+#[cfg(feature = "tetrio")]
+use std::time::Duration;
+
+#[cfg(feature = "tetrio")]
+use headless_chrome::protocol::cdp::Page::CaptureScreenshotFormatOption;
+
+use crate::api::Error;
+#[cfg(feature = "tetrio")]
+use crate::api::api_v1::{create_browser, BrowserOptions};
+
+// time the page gets to load its fonts and finish its animations once the card is there
+#[cfg(feature = "tetrio")]
+const SETTLE_DELAY: Duration = Duration::from_millis(750);
+
+// transparent space kept around the captured card
+#[cfg(feature = "tetrio")]
+const CAPTURE_MARGIN: f64 = 16.0;
+
+/// Failure of a chrome render
+#[derive(Debug)]
+pub enum RenderError {
+    /// Chrome couldn't be configured or launched
+    Launch(anyhow::Error),
+    /// The page failed at `step`, like loading or finding the element to capture
+    Page { step: &'static str, source: anyhow::Error },
+    /// The data handed to the page couldn't be serialized
+    #[cfg_attr(not(feature = "tetrio"), allow(dead_code))]
+    Serialize(serde_json::Error),
+}
+
+impl RenderError {
+    /// Wraps a failure of the page at `step`, for `map_err`
+    #[cfg_attr(not(feature = "tetrio"), allow(dead_code))]
+    pub fn page(step: &'static str) -> impl FnOnce(anyhow::Error) -> Self {
+        move |source| Self::Page { step, source }
+    }
+}
+
+impl Display for RenderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Launch(source) => write!(f, "Couldn't launch browser! {source}"),
+            Self::Page { step, source } => write!(f, "{step} {source}"),
+            Self::Serialize(source) => write!(f, "Couldn't serialize data! {source}"),
+        }
+    }
+}
+
+impl std::error::Error for RenderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Launch(source) | Self::Page { source, .. } => Some(&**source),
+            Self::Serialize(source) => Some(source),
+        }
+    }
+}
+
+/// Startup and the health checks only report what went wrong
+impl From<RenderError> for Error {
+    fn from(error: RenderError) -> Self {
+        Error(error.to_string())
+    }
+}
+
+/// Runs `render` on the blocking pool. headless_chrome blocks its thread, on the runtime it would keep the
+/// route's timeout from firing until chrome is done
+#[cfg(feature = "tetrio")]
+pub async fn blocking<T: Send + 'static>(render: impl FnOnce() -> Result<T, RenderError> + Send + 'static) -> Result<T, RenderError> {
+    let span = tracing::Span::current();
+    tokio::task::spawn_blocking(move || span.in_scope(render))
+        .await
+        .map_err(|e| RenderError::page("Render panicked!")(e.into()))?
+}
+
+/// PNG of the element matching `selector` on the page at `url`, rendered in a `width`x`height` window
+/// with a transparent background
+#[cfg(feature = "tetrio")]
+pub async fn render_page(options: &BrowserOptions, url: &str, size: (u32, u32), selector: &str) -> Result<Vec<u8>, RenderError> {
+    let (options, url, selector) = (options.clone(), url.to_string(), selector.to_string());
+    blocking(move || render_page_blocking(&options, &url, size, &selector)).await
+}
+
+#[cfg(feature = "tetrio")]
+fn render_page_blocking(options: &BrowserOptions, url: &str, (width, height): (u32, u32), selector: &str) -> Result<Vec<u8>, RenderError> {
+    let browser = tracing::info_span!("launch").in_scope(|| create_browser(options, width, height))?;
+    let tab = browser.new_tab().map_err(RenderError::page("Couldn't create new tab!"))?;
+
+    tab.set_transparent_background_color().map_err(RenderError::page("Couldn't set transparent background!"))?;
+
+    tracing::info_span!("navigate", url).in_scope(|| {
+        tab.navigate_to(url).map_err(RenderError::page("Couldn't navigate to url!"))?;
+        tab.wait_until_navigated().map_err(RenderError::page("Couldn't wait for tab to finish navigating!"))
+    })?;
+
+    let element = tracing::info_span!("wait_for_element", selector).in_scope(|| {
+        tab.wait_for_element(selector).map_err(RenderError::page("Couldn't find element to screenshot!"))
+    })?;
+
+    std::thread::sleep(SETTLE_DELAY);
+
+    let buffer = tracing::info_span!("capture").in_scope(|| {
+        let viewport = element.get_box_model().map_err(RenderError::page("Couldn't find size of element!"))?;
+        let mut viewport = viewport.border_viewport();
+        viewport.x -= CAPTURE_MARGIN;
+        viewport.y -= CAPTURE_MARGIN;
+        viewport.width += 2.0 * CAPTURE_MARGIN;
+        viewport.height += 2.0 * CAPTURE_MARGIN;
+
+        tab.capture_screenshot(CaptureScreenshotFormatOption::Png, None, Some(viewport), true)
+            .map_err(RenderError::page("Couldn't take screenshot!"))
+    })?;
+
+    tab.close(true).map_err(RenderError::page("Couldn't close tab"))?;
+    Ok(buffer)
+}
@@ -2,10 +2,9 @@
 
 #![allow(unused)]
 
-use std::{fs::File, io::Write};
+use std::{fmt::Display, fs::File, io::Write};
 
 
-use anyhow::anyhow;
 use sqlx::FromRow;
 
 use crate::api::api_v1::{models::silly_command::{RawSillyCommandData, Usages, SillyCommandType, SillyCommandData, self, CommandUsage, CommandId, RandomImage, CommandTextId, CommandSelfActionTextId, CommandImageId, CommandSelfActionImageId}, ApiV1State};
@@ -16,45 +15,95 @@ use crate::api::api_v1::{models::silly_command::{RawSillyCommandData, Usages, Si
 
 
 
+/// Failure of the silly command service
+#[derive(Debug)]
+pub enum SillyCommandError {
+    Database(sqlx::Error),
+    /// The uploaded image couldn't be written to the assets
+    Io(std::io::Error),
+    CommandNotFound(String),
+    /// The command has no image for the preference
+    ImageNotFound,
+}
+
+impl Display for SillyCommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Database(source) => write!(f, "Database error! {source}"),
+            Self::Io(source) => write!(f, "Couldn't save image! {source}"),
+            Self::CommandNotFound(name) => write!(f, "Couldn't find command {name}"),
+            Self::ImageNotFound => f.write_str("Couldn't find an image for this preference"),
+        }
+    }
+}
+
+impl std::error::Error for SillyCommandError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Database(source) => Some(source),
+            Self::Io(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<sqlx::Error> for SillyCommandError {
+    fn from(error: sqlx::Error) -> Self {
+        Self::Database(error)
+    }
+}
+
+impl From<std::io::Error> for SillyCommandError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
 pub struct SillyCommandPDO;
 impl SillyCommandPDO {
     
-    pub async fn fetch_silly_commands(context: &ApiV1State<'_>) -> Vec<SillyCommandData> {
-        let Ok(silly_commands) = sqlx::query_as::<_, RawSillyCommandData>(include_str!("../sql/silly_commands/fetch_silly_commands.sql"))
+    pub async fn fetch_silly_commands(context: &ApiV1State) -> Result<Vec<SillyCommandData>, SillyCommandError> {
+        let silly_commands = sqlx::query_as::<_, RawSillyCommandData>(include_str!("../sql/silly_commands/fetch_silly_commands.sql"))
         .fetch_all(&context.sql_connection)
-        .await else {
-            return vec![]
-        };
+        .await?;
 
-        silly_commands
+        Ok(silly_commands
             .into_iter()
-            .filter_map(RawSillyCommandData::into_silly_command_data)
-            .collect()
+            .filter_map(|silly_command| {
+                let id = silly_command.id_silly_command;
+                let data = silly_command.into_silly_command_data();
+                if data.is_none() {
+                    log::warn!("Skipping incomplete silly command {id:?}");
+                }
+                data
+            })
+            .collect())
     }
 
+    #[tracing::instrument(level = "debug", skip_all, fields(command = command, author = author, user = user))]
     pub async fn fetch_command_usage(
-        context: &ApiV1State<'_>,
+        context: &ApiV1State,
         command: i32,
         author: u64,
         user: u64,
-    ) -> Option<CommandUsage> {
+    ) -> Result<Option<CommandUsage>, SillyCommandError> {
         let record = sqlx::query_as::<_, CommandUsage>(include_str!("../sql/silly_commands/fetch_command_usage.sql"))
         .bind(author.to_string())
         .bind(user.to_string())
         .bind(command)
-        .fetch_one(&context.sql_connection)
-        .await
-        .ok();
+        .fetch_optional(&context.sql_connection)
+        .await?;
 
-        record
+        Ok(record)
     }
 
+    #[tracing::instrument(level = "debug", skip_all, fields(command = command, author = author, user = user))]
     pub async fn increment_command_usage(
-        context: &ApiV1State<'_>,
+        context: &ApiV1State,
         command: i32,
         author: u64,
         user: u64,
-    ) -> anyhow::Result<i32> {
+    ) -> Result<i32, SillyCommandError> {
         Ok(sqlx::query_as::<_, Usages>(include_str!("../sql/silly_commands/increment_command_usage.sql"))
             .bind(author.to_string())
             .bind(user.to_string()) 
@@ -64,12 +113,13 @@ impl SillyCommandPDO {
         )
     }
 
+    #[tracing::instrument(level = "debug", skip_all, fields(command = command, author = author, user = user))]
     pub async fn create_command_usage(
-        context: &ApiV1State<'_>,
+        context: &ApiV1State,
         command: i32,
         author: u64,
         user: u64,
-    ) -> anyhow::Result<i32> {
+    ) -> Result<i32, SillyCommandError> {
         Ok(sqlx::query_as::<_, CommandUsage>(include_str!("../sql/silly_commands/create_command_usage.sql"))
         .bind(command) 
         .bind(author.to_string())
@@ -78,13 +128,14 @@ impl SillyCommandPDO {
         .await.map(|usage| usage.usages)?)
     }
 
+    #[tracing::instrument(level = "debug", skip_all, fields(command = %command_name))]
     pub async fn create_command(
-        context: &ApiV1State<'_>,
+        context: &ApiV1State,
         command_name: &str,
         description: &str,
         footer_text: &str,
         command_type: SillyCommandType,
-    ) -> anyhow::Result<i32> {
+    ) -> Result<i32, SillyCommandError> {
         let id = sqlx::query_as::<_, CommandId>(include_str!("../sql/silly_commands/create_command.sql"))
         .bind(command_name)
         .bind(description)
@@ -96,12 +147,13 @@ impl SillyCommandPDO {
         Ok(id.id_silly_command)
     }
 
+    #[tracing::instrument(level = "debug", skip_all, fields(command = %command, preference = %preference))]
     pub async fn add_preference(
-        context: &ApiV1State<'_>,
+        context: &ApiV1State,
         preference: &str,
         command: &str
     )
-    -> anyhow::Result<()> {
+    -> Result<(), SillyCommandError> {
         sqlx::query(include_str!("../sql/silly_commands/add_preference.sql"))
         .bind(preference)
         .bind(command)
@@ -112,48 +164,50 @@ impl SillyCommandPDO {
     }
     
 
+    #[tracing::instrument(level = "debug", skip_all, fields(command = %name))]
     pub async fn fetch_silly_command_by_name(
-        context: &ApiV1State<'_>,
+        context: &ApiV1State,
         name: &str,
-    ) -> Option<SillyCommandData> {
+    ) -> Result<Option<SillyCommandData>, SillyCommandError> {
 
-        sqlx::query_as::<_, RawSillyCommandData>(include_str!(
+        Ok(sqlx::query_as::<_, RawSillyCommandData>(include_str!(
         "../sql/silly_commands/fetch_silly_command_by_name.sql"))
         .bind(name)
         .fetch_optional(&context.sql_connection)
-        .await
-        .ok()
-        .flatten()
-        .and_then(|silly_command| silly_command.into_silly_command_data())
+        .await?
+        .and_then(|silly_command| silly_command.into_silly_command_data()))
         
     }
 
+    #[tracing::instrument(level = "debug", skip_all, fields(command = command, preference = %preference))]
     pub async fn fetch_random_silly_image_by_name_and_preference(
-        context: &ApiV1State<'_>,
+        context: &ApiV1State,
         command: i32,
         preference: &str
-    ) -> anyhow::Result<String> {
+    ) -> Result<String, SillyCommandError> {
         let result =
         
         sqlx::query_as::<_, RandomImage>(include_str!(
             "../sql/silly_commands/fetch_random_silly_image_by_name_and_preference.sql"))
         .bind(command)
         .bind( preference)
-        .fetch_one(&context.sql_connection)
-        .await?;
+        .fetch_optional(&context.sql_connection)
+        .await?
+        .ok_or(SillyCommandError::ImageNotFound)?;
 
         Ok(result.image)
     }
 
 
+    #[tracing::instrument(level = "debug", skip_all, fields(command = %command_name))]
     pub async fn add_text(
-        context: &ApiV1State<'_>,
+        context: &ApiV1State,
         command_name: &str,
         content: &str,
-    ) -> anyhow::Result<i32> {
+    ) -> Result<i32, SillyCommandError> {
         let command = Self::fetch_silly_command_by_name(&context, command_name)
-            .await
-            .ok_or(anyhow!("Couldn't find command!"))?;
+            .await?
+            .ok_or_else(|| SillyCommandError::CommandNotFound(command_name.to_string()))?;
         
         let id = sqlx::query_as::<_, CommandTextId>(include_str!("../sql/silly_commands/add_text.sql"))
         .bind(command.id_silly_command)
@@ -164,14 +218,15 @@ impl SillyCommandPDO {
         Ok(id.id_silly_command_text)
     }
 
+    #[tracing::instrument(level = "debug", skip_all, fields(command = %command_name))]
     pub async fn add_text_author(
-        context: &ApiV1State<'_>,
+        context: &ApiV1State,
         command_name: &str,
         content: &str,
-    ) -> anyhow::Result<i32> {
+    ) -> Result<i32, SillyCommandError> {
         let command = Self::fetch_silly_command_by_name(&context, command_name)
-            .await
-            .ok_or(anyhow!("Couldn't find command!"))?;
+            .await?
+            .ok_or_else(|| SillyCommandError::CommandNotFound(command_name.to_string()))?;
 
         let id = CommandSelfActionTextId::from_row(&sqlx::query(include_str!("../sql/silly_commands/add_author_text.sql"))
         .bind(command.id_silly_command)
@@ -182,16 +237,17 @@ impl SillyCommandPDO {
         Ok(id.id_silly_command_self_action_text)
     }
 
+    #[tracing::instrument(level = "debug", skip_all, fields(command = %command_name, extension = %extension))]
     pub async fn add_image(
-        context: &ApiV1State<'_>,
+        context: &ApiV1State,
         command_name: &str,
         image: Vec<u8>,
         extension: &str,
         preference: Option<String>
-    ) -> anyhow::Result<i32> {
+    ) -> Result<i32, SillyCommandError> {
         let command = Self::fetch_silly_command_by_name(&context, command_name)
-            .await
-            .ok_or(anyhow!("Couldn't find command!"))?;
+            .await?
+            .ok_or_else(|| SillyCommandError::CommandNotFound(command_name.to_string()))?;
 
         if matches!(command.command_type, SillyCommandType::AuthorOnly) {
             return Self::add_image_author(context, command_name, image, extension).await;
@@ -217,15 +273,16 @@ impl SillyCommandPDO {
 
         
 
+    #[tracing::instrument(level = "debug", skip_all, fields(command = %command_name, extension = %extension))]
     pub async fn add_image_author(
-        context: &ApiV1State<'_>,
+        context: &ApiV1State,
         command_name: &str,
         image: Vec<u8>,
         extension: &str,
-    ) -> anyhow::Result<i32> {
+    ) -> Result<i32, SillyCommandError> {
         let command = Self::fetch_silly_command_by_name(&context, command_name)
-            .await
-            .ok_or(anyhow!("Couldn't find command!"))?;
+            .await?
+            .ok_or_else(|| SillyCommandError::CommandNotFound(command_name.to_string()))?;
 
         let file_name = uuid::Uuid::new_v4().to_string();
         let file_path = format!("./assets/{file_name}.{extension}");
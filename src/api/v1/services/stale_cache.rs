@@ -0,0 +1,115 @@
+#![cfg(feature = "tetrio")]
+
+use std::{future::Future, sync::Arc};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tetrio_api::{http::parameters::personal_user_records::{PersonalLeaderboard, PersonalRecordsQuery}, models::packet::Packet};
+
+use crate::api::{api_v1::{cache::{now_millis, tetrio_cache_ttl, CacheNamespace}, metrics::Metrics, middlewares::request_id::propagate, services::tetrio::TetrioError, ApiV1State}, Error};
+
+/// Upstream packet along with the moment it stops being fresh
+#[derive(Serialize, Deserialize)]
+struct StaleEnvelope<T> {
+    fresh_until: u64,
+    packet: Packet<T>,
+}
+
+/// Packet served by [`StaleCache`]
+pub struct Served<T> {
+    pub packet: Packet<T>,
+    /// The packet expired and couldn't be refreshed yet
+    pub stale: bool,
+}
+
+pub struct StaleCache;
+
+impl StaleCache {
+    async fn store<T: Serialize>(context: &ApiV1State, namespace: CacheNamespace, key: &str, packet: Packet<T>) -> Packet<T> {
+        // an error packet must not replace data we can still serve
+        if !packet.success {
+            return packet;
+        }
+
+        let fresh_for = packet.cache.as_ref()
+            .and_then(tetrio_cache_ttl)
+            .unwrap_or(context.cache.ttl(namespace));
+
+        let envelope = StaleEnvelope {
+            fresh_until: now_millis() + fresh_for.as_millis() as u64,
+            packet,
+        };
+
+        let ttl = fresh_for + context.cache.max_stale(namespace);
+        if let Err(Error(message)) = context.cache.set(namespace, key, &envelope, Some(ttl)).await {
+            log::warn!("Couldn't cache {key}: {message}");
+        }
+        envelope.packet
+    }
+
+    /// Serves the cached packet while fresh, refreshes it in the background once expired and keeps serving
+    /// the expired copy, flagged as stale, until `max_stale` has passed
+    pub async fn fetch<T, F, Fut>(context: &Arc<ApiV1State>, namespace: CacheNamespace, key: &str, refresh: F) -> Result<Served<T>, TetrioError>
+    where
+        T: Serialize + DeserializeOwned + Send + 'static,
+        F: FnOnce(Arc<ApiV1State>) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<Packet<T>, TetrioError>> + Send + 'static
+    {
+        let envelope = context.cache.get::<StaleEnvelope<T>>(namespace, key).await;
+
+        let Some(envelope) = envelope else {
+            let packet = refresh(Arc::clone(context)).await?;
+            let packet = Self::store(context, namespace, key, packet).await;
+            return Ok(Served { packet, stale: false });
+        };
+
+        if now_millis() < envelope.fresh_until {
+            return Ok(Served { packet: envelope.packet, stale: false });
+        }
+
+        if let Some(guard) = context.cache.begin_refresh(namespace, key) {
+            let context = Arc::clone(context);
+            let key = key.to_string();
+            tokio::spawn(propagate(async move {
+                // released however the refresh ends, even when the task is aborted
+                let _guard = guard;
+                match refresh(Arc::clone(&context)).await {
+                    Ok(packet) => { Self::store(&context, namespace, &key, packet).await; },
+                    Err(err) => log::warn!("Couldn't refresh {key}, serving stale data: {err}")
+                }
+            }));
+        }
+
+        Ok(Served { packet: envelope.packet, stale: true })
+    }
+
+    pub async fn fetch_user_info(context: &Arc<ApiV1State>, user: &str) -> Result<Served<impl Serialize + DeserializeOwned + Send + 'static>, TetrioError> {
+        let user = user.to_lowercase();
+        let refreshed_user = user.clone();
+        Self::fetch(context, CacheNamespace::TetrioUser, &user, move |context| async move {
+            Metrics::global().observe_tetrio("user_info", context.http_client.fetch_user_info(&refreshed_user))
+                .await
+                .map_err(TetrioError::request("Couldn't fetch user from tetrio API!"))
+        }).await
+    }
+
+    /// The user's most recent league records, as raw json
+    pub async fn fetch_recent_league_records(context: &Arc<ApiV1State>, user: &str) -> Result<Served<Vec<serde_json::Value>>, TetrioError> {
+        let user = user.to_lowercase();
+        let refreshed_user = user.clone();
+        Self::fetch(context, CacheNamespace::TetrioLeagueRecords, &user, move |context| async move {
+            let request = context.http_client
+                .fetch_user_personal_league_records(&refreshed_user, PersonalLeaderboard::Recent, PersonalRecordsQuery::None);
+            let packet = Metrics::global().observe_tetrio("league_records", request)
+                .await
+                .map_err(TetrioError::request("Couldn't fetch tetra league game:"))?;
+
+            let entries = match packet.data.as_ref().map(|data| serde_json::to_value(&data.entries)).transpose()? {
+                Some(serde_json::Value::Array(entries)) => Some(entries),
+                Some(_) => return Err(TetrioError::Malformed("tetra league records")),
+                None => None,
+            };
+
+            Ok(Packet { success: packet.success, error: packet.error, data: entries, cache: packet.cache })
+        }).await
+    }
+}
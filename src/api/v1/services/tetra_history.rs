@@ -0,0 +1,228 @@
+#![cfg(all(feature = "database", feature = "tetrio"))]
+
+use std::collections::HashSet;
+
+use chrono::{DateTime, Utc};
+use tetrio_api::http::parameters::{personal_user_records::PersonalLeaderboard, value_bound_query::Prisecter};
+
+use crate::api::api_v1::{metrics::Metrics, models::tetra_history::{TetraHistory, TetraHistoryPoint}, services::{league_records::{LeagueRecords, PAGE_SIZE}, render::{render_page, RenderError}, tetrio::TetrioError}, ApiV1State};
+
+// bounds how many pages a single update fetches, a longer history is stored over several updates
+const MAX_PAGES: usize = 10;
+
+// only the most recent games are drawn, the url carrying them would get too long otherwise
+const MAX_RENDERED_POINTS: usize = 500;
+
+/// How far back the stored history of a player is known to be complete
+#[derive(Default, sqlx::FromRow)]
+pub struct HistoryBackfill {
+    /// Cursor below which the records still have to be stored, serialized
+    pub before_cursor: Option<String>,
+    /// Every record tetr.io returns for the player is stored
+    pub complete: bool,
+}
+
+pub struct TetraHistoryPDO;
+
+impl TetraHistoryPDO {
+    #[tracing::instrument(level = "debug", skip_all, fields(user_id = %user_id))]
+    pub async fn fetch_points(context: &ApiV1State, user_id: &str) -> Result<Vec<TetraHistoryPoint>, sqlx::Error> {
+        sqlx::query_as::<_, TetraHistoryPoint>(include_str!("../sql/tetra_history/fetch_points.sql"))
+            .bind(user_id)
+            .fetch_all(&context.sql_connection)
+            .await
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(user_id = %user_id))]
+    pub async fn fetch_backfill(context: &ApiV1State, user_id: &str) -> Result<Option<HistoryBackfill>, sqlx::Error> {
+        sqlx::query_as::<_, HistoryBackfill>(include_str!("../sql/tetra_history/fetch_backfill.sql"))
+            .bind(user_id)
+            .fetch_optional(&context.sql_connection)
+            .await
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(user_id = %user_id))]
+    pub async fn save_backfill(context: &ApiV1State, user_id: &str, backfill: &HistoryBackfill) -> Result<(), sqlx::Error> {
+        sqlx::query(include_str!("../sql/tetra_history/save_backfill.sql"))
+            .bind(user_id)
+            .bind(&backfill.before_cursor)
+            .bind(backfill.complete)
+            .execute(&context.sql_connection)
+            .await?;
+        Ok(())
+    }
+
+    /// Stores a page of points in one statement, returns the replay ids of the points that weren't stored yet
+    #[tracing::instrument(level = "debug", skip_all, fields(points = points.len()))]
+    pub async fn insert_points(context: &ApiV1State, points: &[TetraHistoryPoint]) -> Result<HashSet<String>, sqlx::Error> {
+        if points.is_empty() {
+            return Ok(HashSet::new());
+        }
+
+        let column = |value: fn(&TetraHistoryPoint) -> Option<f64>| points.iter().map(value).collect::<Vec<_>>();
+        let inserted: Vec<(String,)> = sqlx::query_as(include_str!("../sql/tetra_history/insert_points.sql"))
+            .bind(points.iter().map(|point| point.tetrio_user_id.clone()).collect::<Vec<_>>())
+            .bind(points.iter().map(|point| point.replay_id.clone()).collect::<Vec<_>>())
+            .bind(points.iter().map(|point| point.played_at).collect::<Vec<_>>())
+            .bind(column(|point| point.tr))
+            .bind(column(|point| point.glicko))
+            .bind(column(|point| point.rd))
+            .bind(points.iter().map(|point| point.rank.clone()).collect::<Vec<_>>())
+            .fetch_all(&context.sql_connection)
+            .await?;
+
+        Ok(inserted.into_iter().map(|(replay_id,)| replay_id).collect())
+    }
+}
+
+pub struct TetraHistoryService;
+
+impl TetraHistoryService {
+    /// Rating of the record owner after the game, read from the record's `extras.league`
+    pub fn point_from_record(record: &serde_json::Value) -> Option<TetraHistoryPoint> {
+        let user_id = record.pointer("/user/id").and_then(|id| id.as_str())?;
+        let replay_id = record.get("replayid").and_then(|id| id.as_str())?;
+        let played_at = record.get("ts")
+            .and_then(|ts| ts.as_str())
+            .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok())?
+            .with_timezone(&Utc);
+
+        // extras.league maps each player id to their rating [before, after] the game
+        let after = record.pointer("/extras/league")
+            .and_then(|league| league.get(user_id))
+            .and_then(|ratings| ratings.get(1));
+
+        let number = |key: &str| after.and_then(|after| after.get(key)).and_then(|value| value.as_f64());
+
+        Some(TetraHistoryPoint {
+            tetrio_user_id: user_id.to_string(),
+            replay_id: replay_id.to_string(),
+            played_at,
+            tr: number("tr"),
+            glicko: number("glicko"),
+            rd: number("rd"),
+            rank: after.and_then(|after| after.get("rank")).and_then(|rank| rank.as_str()).map(|rank| rank.to_string()),
+        })
+    }
+
+    /// Stores the games played since the last update, then keeps filling in older games where the previous updates stopped.
+    /// The backfill cursor is saved after every page, so neither the page limit nor an error leaves a gap behind.
+    async fn update(context: &ApiV1State, user: &str) -> Result<Option<String>, TetrioError> {
+        let mut pages = 0;
+        let mut before = None;
+        // the user id and what was stored before this update, known once the first record is read
+        let mut stored: Option<(String, HistoryBackfill)> = None;
+
+        // newest games first, until reaching one that is already stored
+        let joined_at = 'pages: loop {
+            if pages == MAX_PAGES {
+                break None;
+            }
+            let page = LeagueRecords::fetch_page(context, user, PersonalLeaderboard::Recent, before.take()).await?;
+            pages += 1;
+
+            let (records, points): (Vec<_>, Vec<_>) = page.iter()
+                .filter_map(|record| Some((record, Self::point_from_record(record)?)))
+                .unzip();
+
+            if let (None, Some(point)) = (&stored, points.first()) {
+                let backfill = TetraHistoryPDO::fetch_backfill(context, &point.tetrio_user_id).await?.unwrap_or_default();
+                stored = Some((point.tetrio_user_id.clone(), backfill));
+            }
+
+            // the first game that was already stored is where the previous updates start
+            let inserted = TetraHistoryPDO::insert_points(context, &points).await?;
+            if let Some(index) = points.iter().position(|point| !inserted.contains(&point.replay_id)) {
+                break 'pages Some(LeagueRecords::cursor(records[index])?);
+            }
+
+            let Some((user_id, _)) = &stored else {
+                return Ok(None);
+            };
+
+            if page.len() != PAGE_SIZE {
+                // the oldest game tetr.io has is stored
+                TetraHistoryPDO::save_backfill(context, user_id, &HistoryBackfill { before_cursor: None, complete: true }).await?;
+                return Ok(Some(user_id.clone()));
+            }
+
+            // until the new games reach the stored ones, everything older than this page has to be looked at again
+            let Some(last) = page.last() else {
+                return Ok(Some(user_id.clone()));
+            };
+            let cursor = LeagueRecords::cursor(last)?;
+            TetraHistoryPDO::save_backfill(context, user_id, &HistoryBackfill { before_cursor: Some(serde_json::to_string(&cursor)?), complete: false }).await?;
+            before = Some(cursor);
+        };
+
+        let Some((user_id, backfill)) = stored else {
+            return Ok(None);
+        };
+        let Some(joined_at) = joined_at else {
+            return Ok(Some(user_id));
+        };
+
+        // the new games reached the stored ones, the older backfill is what's left
+        TetraHistoryPDO::save_backfill(context, &user_id, &backfill).await?;
+        if backfill.complete {
+            return Ok(Some(user_id));
+        }
+
+        // history stored before backfills were tracked is walked again from the first known game
+        let mut before = match &backfill.before_cursor {
+            Some(cursor) => serde_json::from_str::<Prisecter>(cursor)?,
+            None => joined_at,
+        };
+
+        while pages < MAX_PAGES {
+            let page = LeagueRecords::fetch_page(context, user, PersonalLeaderboard::Recent, Some(before)).await?;
+            pages += 1;
+
+            let points = page.iter().filter_map(Self::point_from_record).collect::<Vec<_>>();
+            TetraHistoryPDO::insert_points(context, &points).await?;
+
+            let last = match page.last() {
+                Some(last) if page.len() == PAGE_SIZE => last,
+                _ => {
+                    TetraHistoryPDO::save_backfill(context, &user_id, &HistoryBackfill { before_cursor: None, complete: true }).await?;
+                    break;
+                }
+            };
+
+            before = LeagueRecords::cursor(last)?;
+            TetraHistoryPDO::save_backfill(context, &user_id, &HistoryBackfill { before_cursor: Some(serde_json::to_string(&before)?), complete: false }).await?;
+        }
+
+        Ok(Some(user_id))
+    }
+
+    pub async fn fetch_history(context: &ApiV1State, user: &str, render: bool) -> Result<TetraHistory, TetrioError> {
+        let user = user.to_lowercase();
+        let user_id = Self::update(context, &user)
+            .await?
+            .ok_or(TetrioError::NotFound("User does not have tetra league records".to_string()))?;
+
+        let points = TetraHistoryPDO::fetch_points(context, &user_id).await?;
+
+        let buffer = if render {
+            Some(Metrics::global().observe_render("tetra_history", Self::take_history_screenshot(context, &user, &points)).await?.into_boxed_slice())
+        } else {
+            None
+        };
+
+        Ok(TetraHistory { user_id, points, buffer })
+    }
+
+    async fn take_history_screenshot(context: &ApiV1State, user: &str, points: &[TetraHistoryPoint]) -> Result<Vec<u8>, RenderError> {
+        let rendered = &points[points.len().saturating_sub(MAX_RENDERED_POINTS)..];
+        let series = rendered.iter()
+            .filter_map(|point| Some((point.played_at.timestamp_millis(), point.tr?)))
+            .collect::<Vec<_>>();
+
+        let obj_string = serde_json::to_string(&serde_json::json!({ "user": user, "series": series }))
+            .map_err(RenderError::Serialize)?;
+
+        let url = format!("{}/tetra_history?data={}", context.html_server_url, urlencoding::encode(&obj_string));
+        render_page(&context.browser_options, &url, (1000, 500), "#tr_history").await
+    }
+}
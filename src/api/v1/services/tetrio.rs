@@ -0,0 +1,135 @@
+#![cfg(feature = "tetrio")]
+
+use std::{fmt::Display, future::Future};
+
+use serde::{de::DeserializeOwned, Serialize};
+use tetrio_api::{http::{cached_client::CachedClient, caches::moka::MokaCache, clients::reqwest_client::ReqwestClient}, models::packet::Packet};
+
+use crate::api::{api_v1::{cache::{tetrio_cache_ttl, CacheNamespace}, metrics::Metrics, services::render::RenderError, ApiV1State}, Error};
+
+/// tetr.io client caching in process memory only, shared responses go through [`TetrioLookup`] and the tiered cache
+pub type TetrioClient = CachedClient<ReqwestClient, MokaCache>;
+
+/// Failure of a route backed by tetr.io
+#[derive(Debug)]
+pub enum TetrioError {
+    /// tetr.io couldn't be reached or refused the request
+    Request { action: &'static str, message: String },
+    /// tetr.io answered something we couldn't read
+    Malformed(&'static str),
+    UserNotFound,
+    /// The user exists but has no such record or game
+    NotFound(String),
+    /// The request can't be answered whatever tetr.io says
+    InvalidRequest(String),
+    /// The cache couldn't store or load a result
+    Cache(Error),
+    #[cfg(feature = "database")]
+    Database(sqlx::Error),
+    Serialize(serde_json::Error),
+    Render(RenderError),
+}
+
+impl TetrioError {
+    /// Wraps an error of the tetr.io client while doing `action`, for `map_err`.
+    /// The client's error type is only displayable, so its message is what we keep
+    pub fn request<E: Display>(action: &'static str) -> impl FnOnce(E) -> Self {
+        move |e| Self::Request { action, message: e.to_string() }
+    }
+}
+
+impl Display for TetrioError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Request { action, message } => write!(f, "{action} {message}"),
+            Self::Malformed(what) => write!(f, "Couldn't read {what} sent by tetr.io"),
+            Self::UserNotFound => f.write_str("Couldn't find user"),
+            Self::NotFound(message) | Self::InvalidRequest(message) => f.write_str(message),
+            Self::Cache(source) => write!(f, "Couldn't use the cache! {source}"),
+            #[cfg(feature = "database")]
+            Self::Database(source) => write!(f, "Database error! {source}"),
+            Self::Serialize(source) => write!(f, "Couldn't serialize data! {source}"),
+            Self::Render(source) => write!(f, "{source}"),
+        }
+    }
+}
+
+impl std::error::Error for TetrioError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Cache(source) => Some(source),
+            #[cfg(feature = "database")]
+            Self::Database(source) => Some(source),
+            Self::Serialize(source) => Some(source),
+            Self::Render(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<Error> for TetrioError {
+    fn from(error: Error) -> Self {
+        Self::Cache(error)
+    }
+}
+
+#[cfg(feature = "database")]
+impl From<sqlx::Error> for TetrioError {
+    fn from(error: sqlx::Error) -> Self {
+        Self::Database(error)
+    }
+}
+
+impl From<serde_json::Error> for TetrioError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Serialize(error)
+    }
+}
+
+impl From<RenderError> for TetrioError {
+    fn from(error: RenderError) -> Self {
+        Self::Render(error)
+    }
+}
+
+pub struct TetrioLookup;
+
+impl TetrioLookup {
+    /// Answers `request` from the tiered cache when it can and caches successful packets for as long as tetr.io does.
+    /// Only misses reach tetr.io, so lookups keep being served from memory while redis is down
+    pub async fn cached<T, E, Fut>(context: &ApiV1State, namespace: CacheNamespace, key: &str, endpoint: &'static str, request: Fut) -> Result<Packet<T>, E>
+    where
+        T: Serialize + DeserializeOwned,
+        Fut: Future<Output = Result<Packet<T>, E>>
+    {
+        if let Some(packet) = context.cache.get::<Packet<T>>(namespace, key).await {
+            return Ok(packet);
+        }
+
+        let packet = Metrics::global().observe_tetrio(endpoint, request).await?;
+        if packet.success {
+            let ttl = packet.cache.as_ref().and_then(tetrio_cache_ttl);
+            if let Err(Error(message)) = context.cache.set(namespace, key, &packet, ttl).await {
+                log::warn!("Couldn't cache {key}: {message}");
+            }
+        }
+
+        Ok(packet)
+    }
+
+    pub async fn user_info(context: &ApiV1State, user: &str) -> Result<Packet<impl Serialize + DeserializeOwned>, TetrioError> {
+        let user = user.to_lowercase();
+        Self::cached(context, CacheNamespace::TetrioUser, &format!("info/{user}"), "user_info", context.http_client.fetch_user_info(&user))
+            .await
+            .map_err(TetrioError::request("Couldn't fetch user from tetrio API!"))
+    }
+
+    /// Summary of `user` for `mode`, like `league` or `40l`, as raw json
+    pub async fn summary(context: &ApiV1State, user: &str, mode: &str) -> Result<Packet<serde_json::Value>, TetrioError> {
+        let user = user.to_lowercase();
+        let request = context.http_client.make_tetrio_api_request::<serde_json::Value>(format!("users/{user}/summaries/{mode}"), None);
+        Self::cached(context, CacheNamespace::TetrioUser, &format!("summaries/{mode}/{user}"), "summary", request)
+            .await
+            .map_err(TetrioError::request("Couldn't fetch user summary from tetrio API!"))
+    }
+}
@@ -2,7 +2,7 @@
 #![allow(unused)]
 // create service to handle user functions
 
-use std::time::SystemTime;
+use std::{fmt::Display, time::SystemTime};
 
 use argon2::{password_hash::SaltString, Argon2, PasswordHasher, PasswordHash, PasswordVerifier};
 use chrono::{DateTime, Utc};
@@ -10,6 +10,50 @@ use rand_core::OsRng;
 
 use crate::api::api_v1::{ApiV1State, models::user::{User, RegisterUserSchema, LoginUserSchema, CreateUser, UpdateUserData}};
 
+/// Failure of the user service
+#[derive(Debug)]
+pub enum UserError {
+    Database(sqlx::Error),
+    PasswordHash(argon2::password_hash::Error),
+    AlreadyExists,
+    NotFound,
+    /// Unknown email or wrong password, deliberately not told apart
+    InvalidCredentials,
+}
+
+impl Display for UserError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Database(source) => write!(f, "Database error! {source}"),
+            Self::PasswordHash(source) => write!(f, "Couldn't hash or read password hash! {source}"),
+            Self::AlreadyExists => f.write_str("User already exists"),
+            Self::NotFound => f.write_str("Couldn't find user"),
+            Self::InvalidCredentials => f.write_str("Invalid email or password"),
+        }
+    }
+}
+
+impl std::error::Error for UserError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Database(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<sqlx::Error> for UserError {
+    fn from(error: sqlx::Error) -> Self {
+        Self::Database(error)
+    }
+}
+
+impl From<argon2::password_hash::Error> for UserError {
+    fn from(error: argon2::password_hash::Error) -> Self {
+        Self::PasswordHash(error)
+    }
+}
+
 pub struct UserPDO;
 
 impl UserPDO {
@@ -22,7 +66,7 @@ impl UserPDO {
     }
 
     // get all users
-    pub async fn fetch_users(context: &ApiV1State<'_>) -> anyhow::Result<Vec<User>> {
+    pub async fn fetch_users(context: &ApiV1State) -> Result<Vec<User>, UserError> {
         let users = sqlx::query_as::<_, User>(include_str!("../sql/users/fetch_users.sql"))
             .fetch_all(&context.sql_connection)
             .await?;
@@ -30,7 +74,8 @@ impl UserPDO {
     }
 
     // get user by id
-    pub async fn fetch_user_by_id(context: &ApiV1State<'_>, user_id: &str) -> anyhow::Result<Option<User>> {
+    #[tracing::instrument(level = "debug", skip_all, fields(user_id = %user_id))]
+    pub async fn fetch_user_by_id(context: &ApiV1State, user_id: &str) -> Result<Option<User>, UserError> {
         let user = sqlx::query_as::<_, User>(include_str!("../sql/users/fetch_user_by_id.sql"))
             .bind(user_id)
             .fetch_optional(&context.sql_connection)
@@ -39,20 +84,15 @@ impl UserPDO {
     }
 
     // verify user password 
-    pub async fn verify_user_password(context: &ApiV1State<'_>, user: &User, password: &str) -> bool {
-
-        let result = match PasswordHash::new(&user.password) {
-            Ok(parsed_hash) => Argon2::default()
-                .verify_password(password.as_bytes(), &parsed_hash)
-                .map_or(false, |_| true),
-            Err(_) => false,
-        };
-        
-        result
+    #[tracing::instrument(level = "debug", skip_all, fields(user_id = %user.id))]
+    // a stored hash that can't be parsed is corrupt, not a wrong password
+    pub async fn verify_user_password(context: &ApiV1State, user: &User, password: &str) -> Result<bool, UserError> {
+        let parsed_hash = PasswordHash::new(&user.password)?;
+        Ok(Argon2::default().verify_password(password.as_bytes(), &parsed_hash).is_ok())
     }
 
     // get user by email
-    pub async fn fetch_user_by_email(context: &ApiV1State<'_>, email: &str) -> anyhow::Result<Option<User>> {
+    pub async fn fetch_user_by_email(context: &ApiV1State, email: &str) -> Result<Option<User>, UserError> {
         let user = sqlx::query_as::<_, User>(include_str!("../sql/users/fetch_user_by_email.sql"))
             .bind(email)
             .fetch_optional(&context.sql_connection)
@@ -61,22 +101,22 @@ impl UserPDO {
     }
 
     // login user
-    pub async fn login_user(context: &ApiV1State<'_>, LoginUserSchema {email, password}: &LoginUserSchema) -> anyhow::Result<User> {
-        let user = Self::fetch_user_by_email(context, &email).await?.ok_or( anyhow::anyhow!("Invald email or password"))?;
-        let password_verified = Self::verify_user_password(context, &user, password).await;
+    pub async fn login_user(context: &ApiV1State, LoginUserSchema {email, password}: &LoginUserSchema) -> Result<User, UserError> {
+        let user = Self::fetch_user_by_email(context, &email).await?.ok_or(UserError::InvalidCredentials)?;
+        let password_verified = Self::verify_user_password(context, &user, password).await?;
         if !password_verified {
-            return Err(anyhow::anyhow!("Invalid password"));
+            return Err(UserError::InvalidCredentials);
         }
         Ok(user)
     }
 
 
     // register user
-    pub async fn register_user(context: &ApiV1State<'_>, RegisterUserSchema {
+    pub async fn register_user(context: &ApiV1State, RegisterUserSchema {
         name,
         email,
         password,
-    }: &RegisterUserSchema, role: Option<&str>) -> anyhow::Result<User> {
+    }: &RegisterUserSchema, role: Option<&str>) -> Result<User, UserError> {
         let user = Self::create_user(context, &CreateUser {
             name: name.clone(),
             email: email.clone(),
@@ -88,14 +128,14 @@ impl UserPDO {
     }
 
     // create user
-    pub async fn create_user(context: &ApiV1State<'_>, CreateUser {name, email, password, role, verified, ..}: &CreateUser) -> anyhow::Result<User> {
+    pub async fn create_user(context: &ApiV1State, CreateUser {name, email, password, role, verified, ..}: &CreateUser) -> Result<User, UserError> {
         // check if user exists
         let user_exists = Self::user_exists(context, email).await?;
         if user_exists {
-            return Err(anyhow::anyhow!("User already exists"));
+            return Err(UserError::AlreadyExists);
         }
         
-        let password = Self::hash_password(password).await.map_err(|e| anyhow::anyhow!("Failed to hash password\n{e}"))?;
+        let password = Self::hash_password(password).await?;
         Ok(sqlx::query_as::<_, User>(include_str!("../sql/users/create_user.sql"))
             .bind(name)
             .bind(email)
@@ -107,7 +147,7 @@ impl UserPDO {
     }
 
     // user_exists
-    pub async fn user_exists(context: &ApiV1State<'_>, email: &str) -> anyhow::Result<bool> {
+    pub async fn user_exists(context: &ApiV1State, email: &str) -> Result<bool, UserError> {
         let user = sqlx::query_as::<_, User>(include_str!("../sql/users/user_exists.sql"))
             .bind(email)
             .fetch_optional(&context.sql_connection)
@@ -116,25 +156,34 @@ impl UserPDO {
     }
 
     // delete user
-    pub async fn delete_user(context: &ApiV1State<'_>, user_id: &str) -> anyhow::Result<()> {
-        sqlx::query(include_str!("../sql/users/delete_user.sql"))
+    #[tracing::instrument(level = "debug", skip_all, fields(user_id = %user_id))]
+    pub async fn delete_user(context: &ApiV1State, user_id: &str) -> Result<(), UserError> {
+        let result = sqlx::query(include_str!("../sql/users/delete_user.sql"))
             .bind(user_id)
             .execute(&context.sql_connection)
             .await?;
+        if result.rows_affected() == 0 {
+            return Err(UserError::NotFound);
+        }
         Ok(())
     }
 
-    pub async fn update_user_password(context: &ApiV1State<'_>, user_id: &str, password: &str) -> anyhow::Result<()> {
-        let password = Self::hash_password(password).await.map_err(|e| anyhow::anyhow!("Failed to hash password\n{e}"))?;
-        sqlx::query(include_str!("../sql/users/update_user_password.sql"))
+    #[tracing::instrument(level = "debug", skip_all, fields(user_id = %user_id))]
+    pub async fn update_user_password(context: &ApiV1State, user_id: &str, password: &str) -> Result<(), UserError> {
+        let password = Self::hash_password(password).await?;
+        let result = sqlx::query(include_str!("../sql/users/update_user_password.sql"))
             .bind(user_id)
             .bind(password)
             .execute(&context.sql_connection)
             .await?;
+        if result.rows_affected() == 0 {
+            return Err(UserError::NotFound);
+        }
         Ok(())
     }
 
-    pub async fn update_user(context: &ApiV1State<'_>, UpdateUserData {name, email, role, verified, id, ..}: &UpdateUserData) -> anyhow::Result<User> {
+    #[tracing::instrument(level = "debug", skip_all, fields(user_id = %id))]
+    pub async fn update_user(context: &ApiV1State, UpdateUserData {name, email, role, verified, id, ..}: &UpdateUserData) -> Result<User, UserError> {
         Ok(sqlx::query_as::<_, User>(include_str!("../sql/users/update_user.sql"))
             .bind(name)
             .bind(email)
@@ -142,8 +191,9 @@ impl UserPDO {
             .bind(verified)
             .bind(DateTime::<Utc>::from(SystemTime::now()))
             .bind(id)
-            .fetch_one(&context.sql_connection)
-            .await?)
+            .fetch_optional(&context.sql_connection)
+            .await?
+            .ok_or(UserError::NotFound)?)
     }
 }
 
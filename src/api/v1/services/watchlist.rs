@@ -0,0 +1,135 @@
+#![cfg(feature = "database")]
+
+use std::fmt::Display;
+
+use crate::api::api_v1::{models::watchlist::{WatchedPlayer, WatchedPlayerTarget, Watchlist, WatchlistData}, ApiV1State};
+
+/// Failure of the watchlist service
+#[derive(Debug)]
+pub enum WatchlistError {
+    Database(sqlx::Error),
+    /// Webhooks are called with the notifications, they must be https
+    InsecureWebhook,
+    /// The guild has no watchlist
+    NotFound,
+}
+
+impl Display for WatchlistError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Database(source) => write!(f, "Database error! {source}"),
+            Self::InsecureWebhook => f.write_str("Webhook url must use https"),
+            Self::NotFound => f.write_str("Couldn't find watchlist!"),
+        }
+    }
+}
+
+impl std::error::Error for WatchlistError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Database(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<sqlx::Error> for WatchlistError {
+    fn from(error: sqlx::Error) -> Self {
+        Self::Database(error)
+    }
+}
+
+pub struct WatchlistPDO;
+
+impl WatchlistPDO {
+    #[tracing::instrument(level = "debug", skip_all, fields(guild_id = %guild_id))]
+    pub async fn create_watchlist(context: &ApiV1State, guild_id: &str, webhook_url: &str) -> Result<Watchlist, WatchlistError> {
+        if !webhook_url.starts_with("https://") {
+            return Err(WatchlistError::InsecureWebhook);
+        }
+
+        Ok(sqlx::query_as::<_, Watchlist>(include_str!("../sql/watchlists/create_watchlist.sql"))
+            .bind(guild_id)
+            .bind(webhook_url)
+            .fetch_one(&context.sql_connection)
+            .await?)
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(guild_id = %guild_id))]
+    pub async fn fetch_watchlist_by_guild(context: &ApiV1State, guild_id: &str) -> Result<Option<Watchlist>, WatchlistError> {
+        Ok(sqlx::query_as::<_, Watchlist>(include_str!("../sql/watchlists/fetch_watchlist_by_guild.sql"))
+            .bind(guild_id)
+            .fetch_optional(&context.sql_connection)
+            .await?)
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(guild_id = %guild_id))]
+    pub async fn fetch_watchlist_data(context: &ApiV1State, guild_id: &str) -> Result<Option<WatchlistData>, WatchlistError> {
+        let Some(watchlist) = Self::fetch_watchlist_by_guild(context, guild_id).await? else {
+            return Ok(None);
+        };
+
+        let players = sqlx::query_as::<_, WatchedPlayer>(include_str!("../sql/watchlists/fetch_watched_players.sql"))
+            .bind(watchlist.id_watchlist)
+            .fetch_all(&context.sql_connection)
+            .await?;
+
+        Ok(Some(WatchlistData { watchlist, players }))
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(guild_id = %guild_id))]
+    pub async fn delete_watchlist(context: &ApiV1State, guild_id: &str) -> Result<(), WatchlistError> {
+        sqlx::query(include_str!("../sql/watchlists/delete_watchlist.sql"))
+            .bind(guild_id)
+            .execute(&context.sql_connection)
+            .await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(guild_id = %guild_id, user = %user))]
+    pub async fn add_watched_player(context: &ApiV1State, guild_id: &str, user: &str) -> Result<(), WatchlistError> {
+        let watchlist = Self::fetch_watchlist_by_guild(context, guild_id)
+            .await?
+            .ok_or(WatchlistError::NotFound)?;
+
+        sqlx::query(include_str!("../sql/watchlists/add_watched_player.sql"))
+            .bind(watchlist.id_watchlist)
+            .bind(user.to_lowercase())
+            .execute(&context.sql_connection)
+            .await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(guild_id = %guild_id, user = %user))]
+    pub async fn remove_watched_player(context: &ApiV1State, guild_id: &str, user: &str) -> Result<(), WatchlistError> {
+        let watchlist = Self::fetch_watchlist_by_guild(context, guild_id)
+            .await?
+            .ok_or(WatchlistError::NotFound)?;
+
+        sqlx::query(include_str!("../sql/watchlists/remove_watched_player.sql"))
+            .bind(watchlist.id_watchlist)
+            .bind(user.to_lowercase())
+            .execute(&context.sql_connection)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn fetch_all_watched_players(context: &ApiV1State) -> Result<Vec<WatchedPlayerTarget>, WatchlistError> {
+        Ok(sqlx::query_as::<_, WatchedPlayerTarget>(include_str!("../sql/watchlists/fetch_all_watched_players.sql"))
+            .fetch_all(&context.sql_connection)
+            .await?)
+    }
+
+    #[tracing::instrument(level = "debug", skip_all, fields(user = %player.tetrio_user))]
+    pub async fn update_watched_player_state(context: &ApiV1State, player: &WatchedPlayer) -> Result<(), WatchlistError> {
+        sqlx::query(include_str!("../sql/watchlists/update_watched_player_state.sql"))
+            .bind(&player.last_game_id)
+            .bind(&player.last_rank)
+            .bind(player.best_40l)
+            .bind(player.best_blitz)
+            .bind(player.id_watched_player)
+            .execute(&context.sql_connection)
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,180 @@
+#![cfg(all(feature = "database", feature = "tetrio"))]
+
+use std::{sync::Arc, time::Duration};
+
+use itertools::Itertools;
+use rand_core::{OsRng, RngCore};
+use tetrio_api::http::parameters::personal_user_records::PersonalLeaderboard;
+
+use crate::{api::{api_v1::{models::watchlist::{WatchedPlayer, WatchedPlayerTarget}, services::{league_records::LeagueRecords, tetrio::TetrioLookup, watchlist::WatchlistPDO}, ApiV1State}, Error}, config::ConfigSource};
+
+#[derive(Clone)]
+pub struct WatchlistPollerOptions {
+    /// Time between two polls of every watched player, `None` disables the poller
+    pub interval: Option<Duration>,
+    /// Random delay added on top of the interval so instances don't hit tetr.io in lockstep
+    pub jitter: Duration,
+    /// Pause between two watched players within a poll
+    pub request_delay: Duration,
+}
+
+impl WatchlistPollerOptions {
+    pub fn from_config(source: &mut ConfigSource) -> Self {
+        Self {
+            interval: source.seconds_or_disabled("WATCHLIST_POLL_INTERVAL_SECONDS", 0),
+            jitter: Duration::from_secs(source.parse("WATCHLIST_POLL_JITTER_SECONDS", 60)),
+            request_delay: Duration::from_millis(source.parse("WATCHLIST_REQUEST_DELAY_MS", 1000)),
+        }
+    }
+}
+
+/// What tetr.io currently reports for a watched player
+struct PlayerObservation {
+    last_game_id: Option<String>,
+    rank: Option<String>,
+    best_40l: Option<f64>,
+    best_blitz: Option<i64>,
+}
+
+pub struct WatchlistPoller;
+
+impl WatchlistPoller {
+    pub async fn run(context: Arc<ApiV1State>, interval: Duration) {
+        loop {
+            let jitter = match context.watchlist_options.jitter.as_millis() as u64 {
+                0 => 0,
+                max => OsRng.next_u64() % max
+            };
+            tokio::time::sleep(interval + Duration::from_millis(jitter)).await;
+
+            if let Err(Error(message)) = Self::poll(&context).await {
+                log::error!("Couldn't poll watchlists: {message}");
+            }
+        }
+    }
+
+    async fn poll(context: &ApiV1State) -> Result<(), Error> {
+        let targets = WatchlistPDO::fetch_all_watched_players(context)
+            .await
+            .map_err(|e| Error(format!("Couldn't fetch watched players! {e}")))?;
+
+        // several guilds may watch the same player, only ask tetr.io once
+        let targets = targets.into_iter().into_group_map_by(|target| target.player.tetrio_user.clone());
+
+        for (user, targets) in targets {
+            match Self::observe(context, &user).await {
+                Ok(observation) => {
+                    for target in targets {
+                        if let Err(Error(message)) = Self::process(context, &target, &observation).await {
+                            log::error!("Couldn't process watched player {user} for guild {}: {message}", target.guild_id);
+                        }
+                    }
+                },
+                Err(Error(message)) => log::warn!("Couldn't observe watched player {user}: {message}")
+            }
+
+            tokio::time::sleep(context.watchlist_options.request_delay).await;
+        }
+
+        Ok(())
+    }
+
+    async fn observe(context: &ApiV1State, user: &str) -> Result<PlayerObservation, Error> {
+        let records = LeagueRecords::fetch_page(context, user, PersonalLeaderboard::Recent, None)
+            .await
+            .map_err(|e| Error(format!("{e}")))?;
+
+        let last_game_id = records.first()
+            .and_then(|record| record.get("replayid"))
+            .and_then(|id| id.as_str())
+            .map(|id| id.to_string());
+
+        let league = Self::fetch_summary(context, user, "league").await?;
+        let sprint = Self::fetch_summary(context, user, "40l").await?;
+        let blitz = Self::fetch_summary(context, user, "blitz").await?;
+
+        Ok(PlayerObservation {
+            last_game_id,
+            rank: league.as_ref()
+                .and_then(|league| league.get("rank"))
+                .and_then(|rank| rank.as_str())
+                .map(|rank| rank.to_string()),
+            best_40l: sprint.as_ref()
+                .and_then(|sprint| sprint.pointer("/record/results/stats/finaltime"))
+                .and_then(|time| time.as_f64()),
+            best_blitz: blitz.as_ref()
+                .and_then(|blitz| blitz.pointer("/record/results/stats/score"))
+                .and_then(|score| score.as_i64()),
+        })
+    }
+
+    async fn fetch_summary(context: &ApiV1State, user: &str, summary: &str) -> Result<Option<serde_json::Value>, Error> {
+        let packet = TetrioLookup::summary(context, user, summary)
+            .await
+            .map_err(|e| Error(format!("Couldn't fetch {summary} summary: {e}")))?;
+
+        Ok(packet.data)
+    }
+
+    async fn process(context: &ApiV1State, target: &WatchedPlayerTarget, observation: &PlayerObservation) -> Result<(), Error> {
+        let previous = &target.player;
+        let user = &previous.tetrio_user;
+
+        // the first poll only records a baseline
+        let mut notifications = vec![];
+        if previous.checked_at.is_some() {
+            if observation.last_game_id.is_some() && observation.last_game_id != previous.last_game_id {
+                notifications.push(format!("**{user}** played a new Tetra League game"));
+            }
+
+            if let (Some(from), Some(to)) = (&previous.last_rank, &observation.rank) {
+                if from != to {
+                    notifications.push(format!("**{user}** went from rank {} to rank {}", from.to_uppercase(), to.to_uppercase()));
+                }
+            }
+
+            if let Some(time) = observation.best_40l {
+                if previous.best_40l.map_or(true, |best| time < best) {
+                    notifications.push(format!("**{user}** set a new 40L personal best: {:.3}s", time / 1000.0));
+                }
+            }
+
+            if let Some(score) = observation.best_blitz {
+                if previous.best_blitz.map_or(true, |best| score > best) {
+                    notifications.push(format!("**{user}** set a new Blitz personal best: {score}"));
+                }
+            }
+        }
+
+        // saved before notifying, a failing webhook must not make every following poll send the same notifications
+        WatchlistPDO::update_watched_player_state(context, &WatchedPlayer {
+            last_game_id: observation.last_game_id.clone().or(previous.last_game_id.clone()),
+            last_rank: observation.rank.clone().or(previous.last_rank.clone()),
+            best_40l: observation.best_40l.or(previous.best_40l),
+            best_blitz: observation.best_blitz.or(previous.best_blitz),
+            ..previous.clone()
+        })
+        .await
+        .map_err(|e| Error(format!("Couldn't save watched player state! {e}")))?;
+
+        for notification in notifications {
+            if let Err(Error(message)) = Self::notify(context, &target.webhook_url, &notification).await {
+                log::warn!("Couldn't notify guild {} about {user}: {message}", target.guild_id);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn notify(context: &ApiV1State, webhook_url: &str, message: &str) -> Result<(), Error> {
+        context.webhook_client
+            .post(webhook_url)
+            .json(&serde_json::json!({ "content": message }))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| Error(format!("Couldn't send webhook notification! {e}")))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,20 @@
+CREATE TABLE IF NOT EXISTS tetra_league_history (
+	"id_tetra_league_history" SERIAL PRIMARY KEY,
+	"tetrio_user_id" VARCHAR(32) NOT NULL,
+	"replay_id" VARCHAR(64) NOT NULL,
+	"played_at" TIMESTAMPTZ NOT NULL,
+	"tr" DOUBLE PRECISION NULL,
+	"glicko" DOUBLE PRECISION NULL,
+	"rd" DOUBLE PRECISION NULL,
+	"rank" VARCHAR(8) NULL
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS "unique_tetra_league_history_replay" ON tetra_league_history("tetrio_user_id", "replay_id");
+CREATE INDEX IF NOT EXISTS "tetra_league_history_played_at" ON tetra_league_history("tetrio_user_id", "played_at");
+
+
+CREATE TABLE IF NOT EXISTS tetra_league_history_backfill (
+	"tetrio_user_id" VARCHAR(32) PRIMARY KEY,
+	"before_cursor" TEXT NULL,
+	"complete" BOOLEAN NOT NULL DEFAULT FALSE
+);
@@ -0,0 +1,3 @@
+SELECT before_cursor, complete 
+FROM tetra_league_history_backfill 
+WHERE tetrio_user_id = $1;
\ No newline at end of file
@@ -0,0 +1,4 @@
+SELECT tetrio_user_id, replay_id, played_at, tr, glicko, rd, rank 
+FROM tetra_league_history 
+WHERE tetrio_user_id = $1 
+ORDER BY played_at;
\ No newline at end of file
@@ -0,0 +1,5 @@
+INSERT INTO tetra_league_history 
+(tetrio_user_id, replay_id, played_at, tr, glicko, rd, rank) 
+SELECT * FROM UNNEST($1::VARCHAR[], $2::VARCHAR[], $3::TIMESTAMPTZ[], $4::DOUBLE PRECISION[], $5::DOUBLE PRECISION[], $6::DOUBLE PRECISION[], $7::VARCHAR[]) 
+ON CONFLICT (tetrio_user_id, replay_id) DO NOTHING 
+RETURNING replay_id;
\ No newline at end of file
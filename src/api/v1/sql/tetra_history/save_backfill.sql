@@ -0,0 +1,4 @@
+INSERT INTO tetra_league_history_backfill 
+(tetrio_user_id, before_cursor, complete) 
+VALUES ($1, $2, $3) 
+ON CONFLICT (tetrio_user_id) DO UPDATE SET before_cursor = EXCLUDED.before_cursor, complete = EXCLUDED.complete;
\ No newline at end of file
@@ -0,0 +1,4 @@
+INSERT INTO tetrio_watched_player 
+(id_watchlist, tetrio_user) 
+VALUES ($1, $2) 
+ON CONFLICT (id_watchlist, tetrio_user) DO NOTHING;
\ No newline at end of file
@@ -0,0 +1,22 @@
+CREATE TABLE IF NOT EXISTS tetrio_watchlist (
+	"id_watchlist" SERIAL PRIMARY KEY,
+	"guild_id" VARCHAR(32) NOT NULL,
+	"webhook_url" TEXT NOT NULL,
+	"created_at" TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS "unique_tetrio_watchlist_guild" ON tetrio_watchlist("guild_id");
+
+CREATE TABLE IF NOT EXISTS tetrio_watched_player (
+	"id_watched_player" SERIAL PRIMARY KEY,
+	"id_watchlist" INTEGER NOT NULL,
+	"tetrio_user" VARCHAR(64) NOT NULL,
+	"last_game_id" VARCHAR(64) NULL,
+	"last_rank" VARCHAR(8) NULL,
+	"best_40l" DOUBLE PRECISION NULL,
+	"best_blitz" BIGINT NULL,
+	"checked_at" TIMESTAMPTZ NULL,
+	CONSTRAINT "fk_id_watchlist" FOREIGN KEY ("id_watchlist") REFERENCES "tetrio_watchlist" ("id_watchlist") ON UPDATE NO ACTION ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS "unique_tetrio_watched_player" ON tetrio_watched_player("id_watchlist", "tetrio_user");
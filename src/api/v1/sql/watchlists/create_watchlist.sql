@@ -0,0 +1,5 @@
+INSERT INTO tetrio_watchlist 
+(guild_id, webhook_url) 
+VALUES ($1, $2) 
+ON CONFLICT (guild_id) DO UPDATE SET webhook_url = EXCLUDED.webhook_url 
+RETURNING *;
\ No newline at end of file
@@ -0,0 +1,5 @@
+SELECT 
+tetrio_watched_player.*, tetrio_watchlist.guild_id, tetrio_watchlist.webhook_url 
+FROM tetrio_watched_player 
+INNER JOIN tetrio_watchlist USING (id_watchlist) 
+ORDER BY tetrio_user;
\ No newline at end of file
@@ -0,0 +1,3 @@
+SELECT * FROM tetrio_watched_player 
+WHERE id_watchlist = $1 
+ORDER BY tetrio_user;
\ No newline at end of file
@@ -0,0 +1 @@
+SELECT * FROM tetrio_watchlist WHERE guild_id = $1;
\ No newline at end of file
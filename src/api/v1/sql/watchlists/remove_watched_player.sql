@@ -0,0 +1,3 @@
+DELETE FROM tetrio_watched_player 
+WHERE id_watchlist = $1 
+AND tetrio_user = $2;
\ No newline at end of file
@@ -0,0 +1,8 @@
+UPDATE tetrio_watched_player 
+SET 
+last_game_id = $1, 
+last_rank = $2, 
+best_40l = $3, 
+best_blitz = $4, 
+checked_at = NOW() 
+WHERE id_watched_player = $5;
\ No newline at end of file
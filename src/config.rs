@@ -0,0 +1,280 @@
+use std::{cell::RefCell, collections::{HashMap, HashSet}, fmt::Display, path::Path, str::FromStr, time::Duration};
+
+use axum::http::{HeaderName, HeaderValue, Method};
+
+use crate::{api::{api_v1::ApiV1Options, Error}, logging::LoggingOptions};
+
+// read when present, CONFIG_FILE points somewhere else
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+/// Settings read from the optional TOML file, overridden by the environment.
+///
+/// File keys map to the variable names, `[database] max_connections = 25` is `DATABASE_MAX_CONNECTIONS`.
+/// Problems are collected instead of returned so every one of them is reported at once, including file keys no option reads.
+pub struct ConfigSource {
+    values: HashMap<String, String>,
+    // names of the settings coming from the file, the environment holds far more than our settings
+    file_keys: HashSet<String>,
+    read: RefCell<HashSet<String>>,
+    errors: Vec<String>,
+}
+
+impl ConfigSource {
+    pub fn load() -> Self {
+        let mut source = Self { values: HashMap::new(), file_keys: HashSet::new(), read: RefCell::new(HashSet::new()), errors: vec![] };
+
+        let (file, explicit) = match std::env::var("CONFIG_FILE") {
+            Ok(file) => (file, true),
+            Err(_) => (DEFAULT_CONFIG_FILE.to_string(), false),
+        };
+
+        if Path::new(&file).exists() {
+            match std::fs::read_to_string(&file).map_err(|e| e.to_string()).and_then(|content| content.parse::<toml::Table>().map_err(|e| e.to_string())) {
+                Ok(table) => {
+                    Self::flatten("", &table, &mut source.values);
+                    source.file_keys = source.values.keys().cloned().collect();
+                },
+                Err(e) => source.error(format!("Couldn't read config file {file}: {e}")),
+            }
+        } else if explicit {
+            source.error(format!("Config file {file} doesn't exist"));
+        }
+
+        source.values.extend(std::env::vars());
+        source
+    }
+
+    fn flatten(prefix: &str, table: &toml::Table, values: &mut HashMap<String, String>) {
+        for (key, value) in table {
+            let name = format!("{prefix}{}", key.to_uppercase());
+            let value = match value {
+                toml::Value::Table(table) => {
+                    Self::flatten(&format!("{name}_"), table, values);
+                    continue;
+                },
+                toml::Value::String(value) => value.clone(),
+                toml::Value::Array(items) => items.iter()
+                    .map(|item| item.as_str().map(|item| item.to_string()).unwrap_or(item.to_string()))
+                    .collect::<Vec<_>>()
+                    .join(","),
+                value => value.to_string(),
+            };
+            values.insert(name, value);
+        }
+    }
+
+    /// Source reading only `toml`, for the tests of the options
+    #[cfg(test)]
+    pub fn from_toml(toml: &str) -> Self {
+        let mut values = HashMap::new();
+        Self::flatten("", &toml.parse().expect("the test config is valid toml"), &mut values);
+        let file_keys = values.keys().cloned().collect();
+        Self { values, file_keys, read: RefCell::new(HashSet::new()), errors: vec![] }
+    }
+
+    pub fn error(&mut self, message: String) {
+        self.errors.push(message);
+    }
+
+    pub fn optional(&self, name: &str) -> Option<String> {
+        self.read.borrow_mut().insert(name.to_string());
+        self.values.get(name).filter(|value| !value.is_empty()).cloned()
+    }
+
+    /// Accepts the file keys starting with `prefix` without reading them, for the options of a feature left out of this build
+    pub fn skip(&self, prefix: &str) {
+        let keys = self.file_keys.iter().filter(|key| key.starts_with(prefix)).cloned().collect::<Vec<_>>();
+        self.read.borrow_mut().extend(keys);
+    }
+
+    pub fn required(&mut self, name: &str) -> String {
+        self.optional(name).unwrap_or_else(|| {
+            self.error(format!("Missing required setting {name}"));
+            String::new()
+        })
+    }
+
+    pub fn parse<T: FromStr>(&mut self, name: &str, default: T) -> T where T::Err: Display {
+        match self.optional(name).map(|value| value.parse::<T>()) {
+            Some(Ok(value)) => value,
+            Some(Err(e)) => {
+                self.error(format!("Invalid value for {name}: {e}"));
+                default
+            },
+            None => default
+        }
+    }
+
+    /// A number of seconds, `0` meaning disabled
+    pub fn seconds_or_disabled(&mut self, name: &str, default: u64) -> Option<Duration> {
+        match self.parse(name, default) {
+            0 => None,
+            seconds => Some(Duration::from_secs(seconds))
+        }
+    }
+
+    /// Comma separated values, empty when unset
+    pub fn list(&self, name: &str) -> Vec<String> {
+        self.optional(name)
+            .map(|value| value.split(',').map(|item| item.trim().to_string()).filter(|item| !item.is_empty()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Comma separated values parsed one by one, `default` when unset
+    pub fn parse_list<T: FromStr>(&mut self, name: &str, default: &[&str]) -> Vec<T> where T::Err: Display {
+        let items = match self.list(name) {
+            items if items.is_empty() => default.iter().map(|item| item.to_string()).collect(),
+            items => items,
+        };
+
+        items.into_iter()
+            .filter_map(|item| match item.parse::<T>() {
+                Ok(value) => Some(value),
+                Err(e) => {
+                    self.error(format!("Invalid value {item} in {name}: {e}"));
+                    None
+                }
+            })
+            .collect()
+    }
+
+    pub fn finish(mut self) -> Result<(), Error> {
+        let mut unknown = self.file_keys.difference(&self.read.borrow()).cloned().collect::<Vec<_>>();
+        unknown.sort();
+        for key in unknown {
+            self.error(format!("Unknown setting {key} in the config file"));
+        }
+
+        if self.errors.is_empty() {
+            return Ok(());
+        }
+
+        Err(Error(format!("Invalid configuration:\n - {}", self.errors.join("\n - "))))
+    }
+}
+
+pub struct ServerOptions {
+    pub bind_url: String,
+    /// Origins allowed by CORS, any origin when empty
+    pub cors_origins: Vec<HeaderValue>,
+    pub cors_methods: Vec<Method>,
+    /// Request headers cross-origin callers may send, `authorization` is left out by default so the
+    /// authenticated routes can't be called from a browser
+    pub cors_headers: Vec<HeaderName>,
+    /// Adds `X-Content-Type-Options`, `X-Frame-Options`, `Referrer-Policy` and `Content-Security-Policy` to every response
+    pub security_headers: bool,
+    /// `Strict-Transport-Security` max age, only sent when set since the api may be served over plain http
+    pub hsts_max_age: Option<Duration>,
+    /// How long in-flight requests may take to finish once shutdown is requested
+    pub drain_timeout: Duration,
+    /// Longest wait between two restarts of a server that keeps failing
+    pub restart_backoff_max: Duration,
+}
+
+impl ServerOptions {
+    pub fn from_config(source: &mut ConfigSource) -> Self {
+        Self {
+            bind_url: source.parse("BIND_URL", "0.0.0.0:8080".to_string()),
+            cors_origins: source.parse_list("CORS_ORIGINS", &[]),
+            cors_methods: source.parse_list("CORS_METHODS", &["GET", "POST"]),
+            cors_headers: source.parse_list("CORS_HEADERS", &["content-type"]),
+            security_headers: source.parse("SECURITY_HEADERS", true),
+            hsts_max_age: source.seconds_or_disabled("HSTS_MAX_AGE_SECONDS", 0),
+            drain_timeout: Duration::from_secs(source.parse("SHUTDOWN_DRAIN_TIMEOUT_SECONDS", 30)),
+            restart_backoff_max: Duration::from_secs(source.parse("RESTART_BACKOFF_MAX_SECONDS", 60)),
+        }
+    }
+}
+
+pub struct Config {
+    pub server: ServerOptions,
+    pub logging: LoggingOptions,
+    /// OTLP collector traces are exported to, when built with the `otlp` feature
+    #[cfg_attr(not(feature = "otlp"), allow(dead_code))]
+    pub otlp_endpoint: Option<String>,
+    pub api: ApiV1Options,
+}
+
+impl Config {
+    pub fn load() -> Result<Self, Error> {
+        let mut source = ConfigSource::load();
+
+        let config = Self {
+            server: ServerOptions::from_config(&mut source),
+            logging: LoggingOptions::from_config(&mut source),
+            otlp_endpoint: source.optional("OTEL_EXPORTER_OTLP_ENDPOINT"),
+            api: ApiV1Options::from_config(&mut source),
+        };
+
+        source.finish()?;
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::ConfigSource;
+
+    fn source(toml: &str) -> ConfigSource {
+        ConfigSource::from_toml(toml)
+    }
+
+    #[test]
+    fn flattens_tables_and_lists() {
+        let source = source(r#"
+            bind_url = "0.0.0.0:80"
+            [database]
+            max_connections = 25
+            [rate_limit.routes]
+            list = ["/a=1/60", "/b=2/60"]
+        "#);
+
+        assert_eq!(source.optional("BIND_URL").as_deref(), Some("0.0.0.0:80"));
+        assert_eq!(source.optional("DATABASE_MAX_CONNECTIONS").as_deref(), Some("25"));
+        assert_eq!(source.optional("RATE_LIMIT_ROUTES_LIST").as_deref(), Some("/a=1/60,/b=2/60"));
+    }
+
+    #[test]
+    fn parses_with_defaults() {
+        let mut source = source("count = 3\nbroken = \"three\"\nempty = \"\"");
+
+        assert_eq!(source.parse("COUNT", 1_u32), 3);
+        assert_eq!(source.parse("EMPTY", 1_u32), 1);
+        assert_eq!(source.parse("MISSING", 1_u32), 1);
+        assert_eq!(source.parse("BROKEN", 1_u32), 1);
+        assert_eq!(source.errors.len(), 1);
+    }
+
+    #[test]
+    fn parses_lists_item_by_item() {
+        let mut source = source(r#"ports = [1, "x", 3]"#);
+
+        assert_eq!(source.parse_list::<u16>("PORTS", &[]), vec![1, 3]);
+        assert_eq!(source.parse_list::<u16>("MISSING", &["8080"]), vec![8080]);
+        assert_eq!(source.errors, vec!["Invalid value x in PORTS: invalid digit found in string".to_string()]);
+    }
+
+    #[test]
+    fn zero_seconds_disables() {
+        let mut source = source("off = 0\non = 90");
+
+        assert_eq!(source.seconds_or_disabled("OFF", 30), None);
+        assert_eq!(source.seconds_or_disabled("ON", 0), Some(Duration::from_secs(90)));
+        assert_eq!(source.seconds_or_disabled("MISSING", 30), Some(Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn reports_keys_nothing_reads() {
+        let source = source("[databse]\nurl = \"postgres://\"\n[leaderboard]\ncache_seconds = 60\n[server]\nbind_url = \"\"");
+        source.optional("SERVER_BIND_URL");
+        source.skip("LEADERBOARD_");
+
+        let Err(error) = source.finish() else {
+            panic!("the misspelled key should be reported");
+        };
+        assert!(error.0.contains("Unknown setting DATABSE_URL"));
+        assert!(!error.0.contains("LEADERBOARD_CACHE_SECONDS"));
+    }
+}
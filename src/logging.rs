@@ -0,0 +1,154 @@
+use flexi_logger::{Age, Cleanup, Criterion, DeferredNow, Duplicate, FileSpec, Logger, LoggerHandle, Naming, WriteMode, TS_DASHES_BLANK_COLONS_DOT_BLANK};
+use log::{kv::{Key, Value, VisitSource}, Record};
+use serde_json::{Map, Number};
+
+use crate::{api::{api_v1::{middlewares::request_id::current_request, services::logs::LOG_DIRECTORY}, Error}, config::ConfigSource};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+pub struct LoggingOptions {
+    /// flexi_logger spec, e.g. `warn, taka_the_discord_bot_api=info`
+    pub level: String,
+    pub format: LogFormat,
+    /// Size after which the current file is rotated, `None` to only rotate on age
+    pub rotate_size: Option<u64>,
+    /// Age after which the current file is rotated, `None` to only rotate on size
+    pub rotate_age: Option<Age>,
+    /// Rotated files kept around, older ones are deleted
+    pub retention: usize,
+    /// Also write every line to stdout, for containers
+    pub stdout: bool,
+}
+
+impl LoggingOptions {
+    pub fn from_config(source: &mut ConfigSource) -> Self {
+        let format = match source.optional("LOG_FORMAT").as_deref() {
+            Some("json") => LogFormat::Json,
+            Some("text") | None => LogFormat::Text,
+            Some(format) => {
+                source.error(format!("Invalid value for LOG_FORMAT: {format}, expected text or json"));
+                LogFormat::Text
+            }
+        };
+
+        let rotate_age = match source.optional("LOG_ROTATE_AGE").as_deref() {
+            Some("day") | None => Some(Age::Day),
+            Some("hour") => Some(Age::Hour),
+            Some("none") => None,
+            Some(age) => {
+                source.error(format!("Invalid value for LOG_ROTATE_AGE: {age}, expected day, hour or none"));
+                None
+            }
+        };
+
+        Self {
+            level: source.parse("LOG_LEVEL", "warn, taka_the_discord_bot_api=info".to_string()),
+            format,
+            rotate_size: match source.parse("LOG_ROTATE_SIZE_MB", 50_u64) {
+                0 => None,
+                megabytes => Some(megabytes * 1024 * 1024)
+            },
+            rotate_age,
+            retention: source.parse("LOG_RETENTION_FILES", 14),
+            stdout: source.parse("LOG_STDOUT", false),
+        }
+    }
+
+    pub fn start(&self) -> Result<LoggerHandle, Error> {
+        let format = match self.format {
+            LogFormat::Text => my_own_format,
+            LogFormat::Json => json_format,
+        };
+
+        let logger = Logger::try_with_str(&self.level)
+            .map_err(|e| Error(format!("Invalid log level {}! {e}", self.level)))?
+            .log_to_file(FileSpec::default().directory(LOG_DIRECTORY))
+            .write_mode(WriteMode::BufferAndFlush)
+            .format(format)
+            .duplicate_to_stdout(if self.stdout { Duplicate::All } else { Duplicate::None });
+
+        let logger = match (self.rotate_size, self.rotate_age) {
+            (Some(size), Some(age)) => logger.rotate(Criterion::AgeOrSize(age, size), Naming::Timestamps, Cleanup::KeepLogFiles(self.retention)),
+            (Some(size), None) => logger.rotate(Criterion::Size(size), Naming::Timestamps, Cleanup::KeepLogFiles(self.retention)),
+            (None, Some(age)) => logger.rotate(Criterion::Age(age), Naming::Timestamps, Cleanup::KeepLogFiles(self.retention)),
+            (None, None) => logger,
+        };
+
+        logger.start().map_err(|e| Error(format!("Couldn't start logger! {e}")))
+    }
+}
+
+/// Key-values attached to a record, plus what is known about the current request
+fn record_fields(record: &Record) -> Map<String, serde_json::Value> {
+    struct Fields(Map<String, serde_json::Value>);
+
+    impl<'kvs> VisitSource<'kvs> for Fields {
+        fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), log::kv::Error> {
+            let value = value.to_i64().map(serde_json::Value::from)
+                .or_else(|| value.to_f64().and_then(Number::from_f64).map(serde_json::Value::Number))
+                .or_else(|| value.to_bool().map(serde_json::Value::Bool))
+                .unwrap_or_else(|| serde_json::Value::String(value.to_string()));
+            self.0.insert(key.to_string(), value);
+            Ok(())
+        }
+    }
+
+    let mut fields = Fields(Map::new());
+    if let Some(request) = current_request() {
+        fields.0.insert("request_id".to_string(), request.id.clone().into());
+        if let Some(route) = request.route() {
+            fields.0.insert("route".to_string(), route.into());
+        }
+        if let Some(user_id) = request.user_id() {
+            fields.0.insert("user_id".to_string(), user_id.into());
+        }
+    }
+    let _ = record.key_values().visit(&mut fields);
+    fields.0
+}
+
+pub fn my_own_format(
+    w: &mut dyn std::io::Write,
+    now: &mut DeferredNow,
+    record: &Record,
+) -> Result<(), std::io::Error> {
+    let mut fields = record_fields(record);
+    let request_id = fields.remove("request_id");
+    write!(
+        w,
+        "{} [Thread {}] [Request {}] Severity {}, Message: {}",
+        now.format(TS_DASHES_BLANK_COLONS_DOT_BLANK),
+        std::thread::current().name().unwrap_or("<unnamed>"),
+        request_id.as_ref().and_then(|id| id.as_str()).unwrap_or("-"),
+        record.level(),
+        &record.args()
+    )?;
+
+    for (key, value) in fields {
+        match value {
+            serde_json::Value::String(value) => write!(w, " {key}={value}")?,
+            value => write!(w, " {key}={value}")?,
+        }
+    }
+    Ok(())
+}
+
+pub fn json_format(
+    w: &mut dyn std::io::Write,
+    now: &mut DeferredNow,
+    record: &Record,
+) -> Result<(), std::io::Error> {
+    let mut line = record_fields(record);
+    line.insert("timestamp".to_string(), now.now().to_rfc3339().into());
+    line.insert("level".to_string(), record.level().as_str().into());
+    line.insert("target".to_string(), record.target().into());
+    line.insert("thread".to_string(), std::thread::current().name().unwrap_or("<unnamed>").into());
+    line.insert("message".to_string(), record.args().to_string().into());
+
+    serde_json::to_writer(&mut *w, &line)?;
+    Ok(())
+}
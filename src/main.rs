@@ -1,101 +1,224 @@
 mod api;
-use api::Error;
-use axum::{Router, response::IntoResponse};
-use flexi_logger::{TS_DASHES_BLANK_COLONS_DOT_BLANK, DeferredNow, Logger, FileSpec, WriteMode};
-use itertools::Itertools;
-use log::Record;
-use tower_http::cors::CorsLayer;
+mod config;
+mod logging;
+mod telemetry;
+use api::{api_v1::middlewares::{rate_limit::RATE_LIMIT_HEADERS, request_id::{request_id, REQUEST_ID_HEADER}}, Error, DOCS_PATH};
+use std::{future::IntoFuture, net::SocketAddr, time::{Duration, Instant}};
+
+use axum::{Router, extract::Request, http::{header, HeaderValue, StatusCode}, middleware::{self, Next}, response::{IntoResponse, Response}};
+use config::{Config, ServerOptions};
+use tokio::sync::watch;
+use tower_http::{cors::{AllowOrigin, CorsLayer}, set_header::SetResponseHeaderLayer};
+
+fn cors_layer(options: &ServerOptions) -> CorsLayer {
+    let origins = if options.cors_origins.is_empty() {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(options.cors_origins.clone())
+    };
+
+    CorsLayer::new()
+        .allow_origin(origins)
+        .allow_methods(options.cors_methods.clone())
+        .allow_headers(options.cors_headers.clone())
+        .expose_headers(RATE_LIMIT_HEADERS.iter().cloned().chain([header::RETRY_AFTER, REQUEST_ID_HEADER.clone()]).collect::<Vec<_>>())
+}
 
+// Swagger UI loads its scripts and styles from the server, styles elements inline, draws icons from data urls
+// and fetches the document, all of which `default-src 'none'` blocks
+const DOCS_CONTENT_SECURITY_POLICY: &str = "default-src 'self'; style-src 'self' 'unsafe-inline'; img-src 'self' data:; frame-ancestors 'none'";
+
+/// Gives the docs their own policy, set before the api's one so that one is skipped
+async fn docs_content_security_policy(request: Request, next: Next) -> Response {
+    let docs = request.uri().path().starts_with(DOCS_PATH);
+    let mut response = next.run(request).await;
+    if docs {
+        response.headers_mut()
+            .entry(header::CONTENT_SECURITY_POLICY)
+            .or_insert(HeaderValue::from_static(DOCS_CONTENT_SECURITY_POLICY));
+    }
+    response
+}
 
+fn with_security_headers(app: Router, options: &ServerOptions) -> Router {
+    let app = match options.hsts_max_age.and_then(|max_age| HeaderValue::from_str(&format!("max-age={}", max_age.as_secs())).ok()) {
+        Some(value) => app.layer(SetResponseHeaderLayer::if_not_present(header::STRICT_TRANSPORT_SECURITY, value)),
+        None => app,
+    };
 
+    if !options.security_headers {
+        return app;
+    }
 
-async fn run_server() -> Result<(), Error> {
-    let ip_bind = std::env::var("BIND_URL").unwrap_or("0.0.0.0:8080".to_string());
+    app
+        .layer(middleware::from_fn(docs_content_security_policy))
+        .layer(SetResponseHeaderLayer::if_not_present(header::X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff")))
+        .layer(SetResponseHeaderLayer::if_not_present(header::X_FRAME_OPTIONS, HeaderValue::from_static("DENY")))
+        .layer(SetResponseHeaderLayer::if_not_present(header::REFERRER_POLICY, HeaderValue::from_static("no-referrer")))
+        .layer(SetResponseHeaderLayer::if_not_present(header::CONTENT_SECURITY_POLICY, HeaderValue::from_static("default-src 'none'; frame-ancestors 'none'")))
+}
 
-    let cors = CorsLayer::permissive();
+// first wait before restarting a failed server, doubled on each failure in a row
+const RESTART_BACKOFF_MIN: Duration = Duration::from_secs(1);
+// renders still running when the runtime stops are dropped after this
+const RUNTIME_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Resolves on ctrl-c or SIGTERM
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            log::error!("Couldn't listen for ctrl-c! {e}");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => { signal.recv().await; },
+            Err(e) => {
+                log::error!("Couldn't listen for SIGTERM! {e}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => log::info!("Received ctrl-c, shutting down"),
+        _ = terminate => log::info!("Received SIGTERM, shutting down"),
+    }
+}
+
+/// Resolves once shutdown has been requested
+async fn stopping(shutdown: &mut watch::Receiver<bool>) {
+    let _ = shutdown.wait_for(|stop| *stop).await;
+}
+
+/// Serves until shutdown is requested, then drains in-flight requests for at most the drain timeout
+async fn run_server(config: &Config, shutdown: &watch::Receiver<bool>) -> Result<(), Error> {
+    let ip_bind = &config.server.bind_url;
+
+    // bound first so a busy address is retried without launching chrome or connecting to the database
+    let listener = tokio::net::TcpListener::bind(ip_bind).await.map_err(|e| {
+        Error(format!("Couldn't bind to address {ip_bind}: {e}"))
+    })?;
+
+    let (api, state) = api::api(&config.api).await?;
     // build our application with a route
     let app = Router::new()
         // `GET /` goes to `root`
-        .nest("/api", api::api().await?)
+        .merge(api)
         .route("/health", axum::routing::get(health_status))
-        .route("/logs",axum::routing::get(logs))       
-        .layer(cors);
+        .route("/metrics", axum::routing::get(metrics))
+        .layer(middleware::from_fn(request_id));
+    let app = with_security_headers(app, &config.server)
+        .layer(cors_layer(&config.server));
 
-    let listener = tokio::net::TcpListener::bind(&ip_bind).await.map_err(|e| {
-        Error(format!("Couldn't bind to address {ip_bind}: {e}"))
-    })?;
+    log::info!("Listening on {ip_bind}");
     // run our app with hyper
-    let _ = axum::serve(listener, app)
-            .await;
+    let mut draining = shutdown.clone();
+    // the peer address is what anonymous callers are rate limited by
+    let server = axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(async move { stopping(&mut draining).await })
+        .into_future();
+
+    let drain_timeout = config.server.drain_timeout;
+    let mut timed_out = shutdown.clone();
+    let result = tokio::select! {
+        result = server => result.map_err(|e| Error(format!("Server stopped unexpectedly! {e}"))),
+        _ = async { stopping(&mut timed_out).await; tokio::time::sleep(drain_timeout).await } => {
+            log::warn!("Requests still running after {drain_timeout:?}, dropping them");
+            Ok(())
+        }
+    };
 
-    Ok(())
+    state.close().await;
+    result
 }
 
+async fn run() {
+    // settings may also come from the environment or config.toml alone
+    let _ = dotenvy::dotenv();
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(Error(message)) => {
+            eprintln!("{message}");
+            std::process::exit(1)
+        }
+    };
 
+    let logger = config.logging.start().expect("Couldn't start logger");
 
-pub fn my_own_format(
-    w: &mut dyn std::io::Write,
-    now: &mut DeferredNow,
-    record: &Record,
-) -> Result<(), std::io::Error> {
-    write!(
-        w,
-        "{} [Thread {}] Severity {}, Message: {}",
-        now.format(TS_DASHES_BLANK_COLONS_DOT_BLANK),
-        std::thread::current().name().unwrap_or("<unnamed>"),
-        record.level(),
-        &record.args()
-    )
-}
+    #[cfg(feature = "otlp")]
+    let tracer_provider = telemetry::init(config.otlp_endpoint.as_deref()).expect("Couldn't initialize tracing");
 
+    let (stop, shutdown) = watch::channel(false);
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        let _ = stop.send(true);
+    });
 
+    let mut backoff = RESTART_BACKOFF_MIN;
+    loop {
+        let started = Instant::now();
+        match run_server(&config, &shutdown).await {
+            // the server only stops cleanly once shutdown was requested
+            Ok(()) => break,
+            Err(Error(message)) => log::error!("Couldn't run server! {message}"),
+        }
 
-async fn run() -> ! {
-    dotenvy::dotenv().expect("Couldn't find env vars");
-    let _logger = Logger::try_with_str("warn, taka_the_discord_bot_api=info").expect("Couldn't initialize logger")
-    .log_to_file(FileSpec::default().directory("./logs"))
-    .write_mode(WriteMode::BufferAndFlush)
-    .format(my_own_format)
-    .start().expect("Couldn't start logger");
+        // a server that ran for a while before failing starts over from the shortest wait
+        if started.elapsed() > config.server.restart_backoff_max {
+            backoff = RESTART_BACKOFF_MIN;
+        }
 
-    loop {
-        match run_server().await {
-            Ok(()) => continue,
-            Err(Error(message)) => {
-                log::error!("{message}");
-                panic!("Couldn't run server! {message}")
-            }
+        log::warn!("Restarting server in {backoff:?}");
+        let mut waiting = shutdown.clone();
+        tokio::select! {
+            _ = tokio::time::sleep(backoff) => {},
+            _ = stopping(&mut waiting) => break,
+        }
+        backoff = (backoff * 2).min(config.server.restart_backoff_max);
+    }
+
+    log::info!("Server stopped");
+
+    #[cfg(feature = "otlp")]
+    if let Some(provider) = tracer_provider {
+        if let Err(e) = provider.shutdown() {
+            log::error!("Couldn't flush traces! {e}");
         }
     }
+
+    logger.flush();
+    logger.shutdown();
 }
 
-fn start() -> ! {
-    tokio::runtime::Builder::new_current_thread()
+fn start() {
+    let runtime = tokio::runtime::Builder::new_current_thread()
     .enable_all()
     .build()
-    .unwrap()
-    .block_on(async {
-        run().await
-    });
+    .expect("Couldn't build runtime");
 
-    panic!("Run loop has ended")
-}
-fn main() -> ! {
-    start();  
-}
+    runtime.block_on(run());
 
-async fn logs() -> impl IntoResponse {
-    let last_modified_file = std::fs::read_dir("./logs")
-    .expect("Couldn't access local directory")
-    .flatten() // Remove failed
-    .filter(|f| f.metadata().unwrap().is_file()) // Filter out directories (only consider files)
-    .max_by_key(|x| x.metadata().unwrap().modified().unwrap()).unwrap(); // Get the most recently modified file
+    // dropping what is left of the tasks drops their browsers too, which kills chrome
+    runtime.shutdown_timeout(RUNTIME_SHUTDOWN_TIMEOUT);
+}
 
-    let value = std::fs::read_to_string(format!("{}", last_modified_file.path().to_str().unwrap())).unwrap();
-    let return_val: String = value.lines().map(|c| c.to_string()).join("\n");
-    return_val
+fn main() {
+    start();
 }
 
 async fn health_status() -> impl IntoResponse {
     "OK"
+}
+
+async fn metrics() -> impl IntoResponse {
+    match api::api_v1::metrics::Metrics::global().encode() {
+        Ok(metrics) => ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], metrics).into_response(),
+        Err(Error(message)) => (StatusCode::INTERNAL_SERVER_ERROR, message).into_response()
+    }
 }
\ No newline at end of file
@@ -0,0 +1,32 @@
+#![cfg(feature = "otlp")]
+
+use opentelemetry::{trace::TracerProvider as _, KeyValue};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{runtime::TokioCurrentThread, trace::{Config, TracerProvider}, Resource};
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+use crate::api::Error;
+
+/// Exports spans to the OTLP collector at `endpoint`, does nothing without one
+pub fn init(endpoint: Option<&str>) -> Result<Option<TracerProvider>, Error> {
+    let Some(endpoint) = endpoint else {
+        return Ok(None);
+    };
+
+    let provider = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(opentelemetry_otlp::new_exporter().tonic().with_endpoint(endpoint))
+        .with_trace_config(Config::default().with_resource(Resource::new(vec![
+            KeyValue::new("service.name", env!("CARGO_PKG_NAME")),
+        ])))
+        .install_batch(TokioCurrentThread)
+        .map_err(|e| Error(format!("Couldn't create OTLP exporter for {endpoint}! {e}")))?;
+
+    tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer(env!("CARGO_PKG_NAME"))))
+        .try_init()
+        .map_err(|e| Error(format!("Couldn't install tracing subscriber! {e}")))?;
+
+    log::info!("Exporting traces to {endpoint}");
+    Ok(Some(provider))
+}